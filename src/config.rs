@@ -1,27 +1,80 @@
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthMode {
+    /// Bearer token or `token` cookie issued by `/api/auth/login`.
+    Jwt,
+    /// Trust the user headers set by a reverse proxy in front of us.
+    Proxy,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmtpSecurity {
+    /// TLS from the start, usually on port 465.
+    Tls,
+    /// Plain text upgraded with STARTTLS, usually on port 587.
+    StartTls,
+    /// No encryption at all, only for a relay on the same machine.
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmbeddingBackend {
+    /// A sentence embedding model run by libtorch, needs the `rust-bert` feature.
+    RustBert,
+    /// A sentence embedding model run by ONNX Runtime, needs the `onnx` feature.
+    Onnx,
+    /// Weighted bag of words, no model needed.
+    TfIdf,
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub app_name: String,
     pub server_address: String,
     pub server_port: u16,
+    /// Where the server is reachable from the outside, used to build links in emails
+    pub public_url: String,
     pub database_url: String,
     pub library_path: String,
 
     pub jwt_secret: String,
     pub jwt_maxage: chrono::Duration,
 
+    pub auth_mode: AuthMode,
+    pub proxy_user_header: String,
+    pub proxy_email_header: String,
+    pub proxy_trusted_ips: Vec<IpAddr>,
+    /// Users allowed to use `/api/admin`
+    pub admin_usernames: Vec<String>,
+
     pub smtp_host: Option<String>,
     pub smtp_port: Option<usize>,
+    pub smtp_security: SmtpSecurity,
     pub smtp_username: Option<String>,
     pub smtp_password: Option<String>,
     pub smtp_from_email: Option<String>,
     pub smtp_from_name: Option<String>,
 
+    pub mail_transport: String,
+    pub mail_file_dir: String,
+    pub mail_templates_dir: Option<String>,
+    pub mail_max_retries: u32,
+
     pub ffmpeg_path: Option<String>,
     pub djxl_path: Option<String>,
     pub ffmpeg_log_path: Option<String>,
     pub temp_path: String,
 
+    /// `None` disables similarity and semantic search
+    pub embedding_backend: Option<EmbeddingBackend>,
     pub sentence_embedding_model_path: Option<String>,
+    /// How many similar titles to keep for each title
+    pub ssim_top_k: usize,
+
+    /// Serve the Komga compatible API under `/komga`, for the apps that only
+    /// speak Komga
+    pub komga_compat: bool,
 }
 
 impl Config {
@@ -42,6 +95,12 @@ impl Config {
         let server_port = Self::get_env("SERVER_PORT", Some("3000"))
             .parse()
             .unwrap_or(3000);
+        let public_url = Self::get_env(
+            "PUBLIC_URL",
+            Some(&format!("http://localhost:{}", server_port)),
+        )
+        .trim_end_matches('/')
+        .to_string();
         let database_url = Self::get_env("DATABASE_URL", Some("sqlite:./database/sqlite.db"));
         let library_path = Self::get_env("LIBRARY_PATH", Some("./library"));
 
@@ -50,43 +109,123 @@ impl Config {
             .parse()
             .unwrap_or(30);
 
+        let auth_mode = match Self::get_env("AUTH_MODE", Some("jwt"))
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "proxy" => AuthMode::Proxy,
+            _ => AuthMode::Jwt,
+        };
+        let proxy_user_header = Self::get_env("PROXY_USER_HEADER", Some("Remote-User"));
+        let proxy_email_header = Self::get_env("PROXY_EMAIL_HEADER", Some("Remote-Email"));
+        let proxy_trusted_ips = Self::get_env("PROXY_TRUSTED_IPS", Some("127.0.0.1,::1"))
+            .split(',')
+            .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+            .collect();
+        let admin_usernames = Self::get_env("ADMIN_USERNAMES", Some(""))
+            .split(',')
+            .map(|username| username.trim().to_string())
+            .filter(|username| !username.is_empty())
+            .collect();
+
         let smtp_host = Self::may_get("SMTP_HOST");
         let smtp_port = Self::may_get("SMTP_PORT").map(|port| port.parse::<usize>().unwrap_or(587));
+        let smtp_security = match Self::may_get("SMTP_SECURE")
+            .map(|secure| secure.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("tls" | "ssl" | "true") => SmtpSecurity::Tls,
+            Some("starttls" | "false") => SmtpSecurity::StartTls,
+            Some("none") => SmtpSecurity::None,
+            // implicit TLS has its own port, anything else is most likely
+            // the submission port
+            _ => match smtp_port {
+                None | Some(465) => SmtpSecurity::Tls,
+                Some(_) => SmtpSecurity::StartTls,
+            },
+        };
         let smtp_username = Self::may_get("SMTP_USERNAME");
         let smtp_password = Self::may_get("SMTP_PASSWORD");
         let smtp_from_email = Self::may_get("SMTP_FROM_EMAIL");
         let smtp_from_name = Self::may_get("SMTP_FROM_NAME");
 
+        let mail_transport = Self::get_env("MAIL_TRANSPORT", Some("smtp")).to_ascii_lowercase();
+        let mail_file_dir = Self::get_env("MAIL_FILE_DIR", Some("./mails"));
+        let mail_templates_dir = Self::may_get("MAIL_TEMPLATES_DIR");
+        let mail_max_retries = Self::get_env("MAIL_MAX_RETRIES", Some("3"))
+            .parse()
+            .unwrap_or(3);
+
         let ffmpeg_path = Self::may_get("FFMPEG_PATH");
         let djxl_path = Self::may_get("DJXL_PATH");
         let ffmpeg_log_path = Self::may_get("FFMPEG_LOG_PATH");
         let temp_path = Self::get_env("TEMP_DIR", Some("/tmp"));
 
         let sentence_embedding_model_path = Self::may_get("SENTENCE_EMBEDDING_MODEL_PATH");
+        let embedding_backend = match Self::may_get("EMBEDDING_BACKEND")
+            .map(|backend| backend.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("rust-bert") => Some(EmbeddingBackend::RustBert),
+            Some("onnx") => Some(EmbeddingBackend::Onnx),
+            Some("tfidf") => Some(EmbeddingBackend::TfIdf),
+            Some(_) => None,
+            // what was used before there was a choice
+            None => sentence_embedding_model_path
+                .as_ref()
+                .map(|_| EmbeddingBackend::RustBert),
+        };
+        let ssim_top_k = Self::get_env("SSIM_TOP_K", Some("50"))
+            .parse()
+            .unwrap_or(50);
+
+        let komga_compat = matches!(
+            Self::get_env("KOMGA_COMPAT", Some("false"))
+                .to_ascii_lowercase()
+                .as_str(),
+            "true" | "1"
+        );
 
         Self {
             library_path,
             app_name,
             server_address,
             server_port,
+            public_url,
             database_url,
 
             jwt_secret,
             jwt_maxage: chrono::Duration::days(jwt_maxage_day),
 
+            auth_mode,
+            proxy_user_header,
+            proxy_email_header,
+            proxy_trusted_ips,
+            admin_usernames,
+
             smtp_host,
             smtp_port,
+            smtp_security,
             smtp_username,
             smtp_password,
             smtp_from_email,
             smtp_from_name,
 
+            mail_transport,
+            mail_file_dir,
+            mail_templates_dir,
+            mail_max_retries,
+
             ffmpeg_path,
             djxl_path,
             ffmpeg_log_path,
             temp_path,
 
+            embedding_backend,
             sentence_embedding_model_path,
+            ssim_top_k,
+
+            komga_compat,
         }
     }
 }
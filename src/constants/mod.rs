@@ -22,3 +22,68 @@ pub fn blurhash_dimension_cap() -> f32 {
 pub fn ratio_percision() -> u32 {
     1000
 }
+
+/// Progress updates further apart than this start a new reading session
+pub fn reading_session_gap() -> chrono::Duration {
+    chrono::Duration::minutes(30)
+}
+
+/// Titles less similar than this, out of 1.0, aren't recommended by default
+pub fn similarity_threshold() -> f32 {
+    0.5
+}
+
+/// Titles more alike than this, out of 1.0, are reported as duplicates by default
+pub fn duplicate_confidence() -> f32 {
+    0.8
+}
+
+/// Pages this many times wider than the typical page of their title are double spreads
+pub fn double_spread_ratio() -> f32 {
+    1.5
+}
+
+/// Height webtoon strips are cut into, in pixels at the strip's width
+pub fn strip_tile_height() -> u32 {
+    2048
+}
+
+/// Entries per page of the OPDS acquisition feeds
+pub fn opds_page_size() -> u64 {
+    50
+}
+
+/// Signed page URLs stay valid at least this long, and at most twice as long
+pub fn signed_page_lifetime() -> chrono::Duration {
+    chrono::Duration::minutes(15)
+}
+
+/// Most pages signed per batch, and how many are unless asked for fewer
+pub fn signed_pages_batch_size() -> u64 {
+    20
+}
+
+/// Verified API keys are trusted without hashing them again this long
+pub fn api_key_cache_lifetime() -> chrono::Duration {
+    chrono::Duration::minutes(1)
+}
+
+/// `last_used_at` of API keys is only written again once it's this old
+pub fn api_key_touch_interval() -> chrono::Duration {
+    chrono::Duration::minutes(1)
+}
+
+/// CBZ repacks not downloaded for this long are deleted
+pub fn cbz_cache_lifetime() -> chrono::Duration {
+    chrono::Duration::days(7)
+}
+
+/// Most bytes the CBZ repacks take, the least recently downloaded go first
+pub fn cbz_cache_size() -> u64 {
+    4 * 1024 * 1024 * 1024
+}
+
+/// Largest page of the Komga API, bigger sizes are cut down to it
+pub fn komga_max_page_size() -> u64 {
+    1000
+}
@@ -0,0 +1,232 @@
+#[cfg(feature = "onnx")]
+mod onnx;
+#[cfg(feature = "rust-bert")]
+mod rust_bert;
+mod tfidf;
+
+use crate::config::{Config, EmbeddingBackend};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    RwLock,
+};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
+use tracing::{debug, error, warn};
+
+/// Something that turns text into vectors, see `EMBEDDING_BACKEND`.
+pub trait EmbeddingProvider {
+    /// Stored along with each embedding, vectors with different ids can't be
+    /// compared.
+    fn model_id(&self) -> String;
+
+    /// Learn from the text of every title before a scan embeds them, for the
+    /// providers whose vectors depend on the whole library.
+    fn fit(&mut self, _corpus: &[String]) {}
+
+    fn encode(&mut self, inputs: &[String]) -> Result<Vec<Vec<f32>>, String>;
+}
+
+type ProviderLoader = Box<dyn Fn() -> Result<Box<dyn EmbeddingProvider>, String> + Send>;
+
+enum EmbedJob {
+    Fit {
+        corpus: Vec<String>,
+        /// With the new model id
+        reply: oneshot::Sender<Result<String, String>>,
+    },
+    Encode {
+        inputs: Vec<String>,
+        reply: oneshot::Sender<Result<Vec<Vec<f32>>, String>>,
+    },
+}
+
+impl std::fmt::Debug for EmbedJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fit { corpus, .. } => f
+                .debug_struct("Fit")
+                .field("corpus", &corpus.len())
+                .finish(),
+            Self::Encode { inputs, .. } => f
+                .debug_struct("Encode")
+                .field("inputs", &inputs.len())
+                .finish(),
+        }
+    }
+}
+
+/// Owns the embedding provider on a dedicated thread, so it's only loaded
+/// once and shared by the scanner and the search endpoint.
+#[derive(Debug)]
+pub struct Embedder {
+    model_id: RwLock<String>,
+    /// Providers that depend on the whole library can't encode before `fit`
+    ready: AtomicBool,
+    jobs: UnboundedSender<EmbedJob>,
+}
+
+impl Embedder {
+    /// Pick the provider set by `EMBEDDING_BACKEND`, `None` if there's none or
+    /// it can't be used.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let model_dir = config.sentence_embedding_model_path.clone();
+        match (&config.embedding_backend, model_dir) {
+            (None, _) => None,
+            (Some(EmbeddingBackend::TfIdf), _) => Some(Self::new(
+                tfidf::model_id(),
+                true,
+                Box::new(|| Ok(Box::<tfidf::TfIdf>::default() as Box<dyn EmbeddingProvider>)),
+            )),
+            (Some(backend), None) => {
+                warn!(
+                    "the {:?} embedding backend needs SENTENCE_EMBEDDING_MODEL_PATH",
+                    backend
+                );
+                None
+            }
+            #[cfg(feature = "rust-bert")]
+            (Some(EmbeddingBackend::RustBert), Some(model_dir)) => Some(Self::new(
+                rust_bert::model_id(&model_dir),
+                false,
+                Box::new(move || {
+                    rust_bert::RustBert::load(&model_dir)
+                        .map(|provider| Box::new(provider) as Box<dyn EmbeddingProvider>)
+                }),
+            )),
+            #[cfg(feature = "onnx")]
+            (Some(EmbeddingBackend::Onnx), Some(model_dir)) => Some(Self::new(
+                onnx::model_id(&model_dir),
+                false,
+                Box::new(move || {
+                    onnx::Onnx::load(&model_dir)
+                        .map(|provider| Box::new(provider) as Box<dyn EmbeddingProvider>)
+                }),
+            )),
+            #[allow(unreachable_patterns)]
+            (Some(backend), Some(_)) => {
+                warn!(
+                    "the {:?} embedding backend isn't compiled in, enable its cargo feature",
+                    backend
+                );
+                None
+            }
+        }
+    }
+
+    /// Spawn the provider thread, the provider itself is loaded on first use.
+    fn new(model_id: String, needs_fit: bool, loader: ProviderLoader) -> Self {
+        let (jobs, receiver) = unbounded_channel();
+        std::thread::spawn(move || run_provider(loader, receiver));
+
+        Self {
+            model_id: RwLock::new(model_id),
+            ready: AtomicBool::new(!needs_fit),
+            jobs,
+        }
+    }
+
+    /// Id of the vectors `encode` returns right now.
+    pub fn model_id(&self) -> String {
+        self.model_id
+            .read()
+            .map(|model_id| model_id.clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether the vectors `encode` returns are worth comparing yet, that is
+    /// the provider doesn't need `fit` or it's been fitted.
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+
+    /// Prepare the provider for the current library, this may change `model_id`.
+    pub async fn fit(&self, corpus: Vec<String>) -> Result<(), String> {
+        let (reply, response) = oneshot::channel();
+        self.jobs
+            .send(EmbedJob::Fit { corpus, reply })
+            .map_err(|_| "the embedding thread has stopped".to_string())?;
+        let model_id = response
+            .await
+            .map_err(|_| "the embedding thread has stopped".to_string())??;
+
+        if let Ok(mut current) = self.model_id.write() {
+            *current = model_id;
+        }
+        self.ready.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Embed every input, the vectors are unit-length.
+    pub async fn encode(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+        let (reply, response) = oneshot::channel();
+        self.jobs
+            .send(EmbedJob::Encode { inputs, reply })
+            .map_err(|_| "the embedding thread has stopped".to_string())?;
+        response
+            .await
+            .map_err(|_| "the embedding thread has stopped".to_string())?
+    }
+}
+
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|&x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+/// Cosine similarity of two unit-length vectors.
+pub fn similarity(vec1: &[f32], vec2: &[f32]) -> f32 {
+    vec1.iter().zip(vec2).map(|(&x1, &x2)| x1 * x2).sum()
+}
+
+fn run_provider(loader: ProviderLoader, mut receiver: UnboundedReceiver<EmbedJob>) {
+    let mut provider: Option<Box<dyn EmbeddingProvider>> = None;
+
+    while let Some(job) = receiver.blocking_recv() {
+        if provider.is_none() {
+            match loader() {
+                Ok(loaded) => {
+                    debug!("embedding provider {} loaded", loaded.model_id());
+                    provider = Some(loaded);
+                }
+                Err(e) => {
+                    // try again on the next job
+                    error!("cannot load embedding provider: {}", e);
+                    match job {
+                        EmbedJob::Fit { reply, .. } => {
+                            let _ = reply.send(Err(e));
+                        }
+                        EmbedJob::Encode { reply, .. } => {
+                            let _ = reply.send(Err(e));
+                        }
+                    }
+                    continue;
+                }
+            }
+        }
+        let Some(provider) = provider.as_mut() else {
+            continue;
+        };
+
+        match job {
+            EmbedJob::Fit { corpus, reply } => {
+                provider.fit(&corpus);
+                let _ = reply.send(Ok(provider.model_id()));
+            }
+            EmbedJob::Encode { inputs, reply } => {
+                let result = match provider.encode(&inputs) {
+                    Ok(vectors) => Ok(vectors.into_iter().map(normalize).collect()),
+                    Err(e) => {
+                        error!("cannot encode input: {}", e);
+                        Err(e)
+                    }
+                };
+                let _ = reply.send(result);
+            }
+        }
+    }
+}
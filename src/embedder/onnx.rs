@@ -0,0 +1,118 @@
+use super::EmbeddingProvider;
+use ndarray::{Array2, Axis};
+use ort::{
+    session::{builder::GraphOptimizationLevel, Session, SessionInputValue},
+    value::Tensor,
+};
+use std::path::Path;
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+/// Longest input the usual sentence transformers accept, in tokens.
+const MAX_TOKENS: usize = 512;
+
+pub fn model_id(model_dir: &str) -> String {
+    format!("onnx:{}", model_dir)
+}
+
+/// A sentence transformer exported to ONNX, the directory needs a
+/// `model.onnx` and its `tokenizer.json`.
+pub struct Onnx {
+    model_id: String,
+    session: Session,
+    tokenizer: Tokenizer,
+}
+
+impl Onnx {
+    pub fn load(model_dir: &str) -> Result<Self, String> {
+        let dir = Path::new(model_dir);
+
+        let mut tokenizer =
+            Tokenizer::from_file(dir.join("tokenizer.json")).map_err(|e| e.to_string())?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_TOKENS,
+                ..Default::default()
+            }))
+            .map_err(|e| e.to_string())?;
+
+        let session = Session::builder()
+            .and_then(|builder| builder.with_optimization_level(GraphOptimizationLevel::Level3))
+            .and_then(|builder| builder.commit_from_file(dir.join("model.onnx")))
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            model_id: model_id(model_dir),
+            session,
+            tokenizer,
+        })
+    }
+}
+
+impl EmbeddingProvider for Onnx {
+    fn model_id(&self) -> String {
+        self.model_id.clone()
+    }
+
+    fn encode(&mut self, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        if inputs.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let encodings = self
+            .tokenizer
+            .encode_batch(inputs.to_vec(), true)
+            .map_err(|e| e.to_string())?;
+        let batch = encodings.len();
+        let length = encodings[0].get_ids().len();
+
+        let tensor = |values: fn(&tokenizers::Encoding) -> &[u32]| {
+            let flat = encodings
+                .iter()
+                .flat_map(|encoding| values(encoding).iter().map(|&x| x as i64))
+                .collect::<Vec<_>>();
+            Array2::from_shape_vec((batch, length), flat).map_err(|e| e.to_string())
+        };
+        let input_ids = tensor(tokenizers::Encoding::get_ids)?;
+        let attention_mask = tensor(tokenizers::Encoding::get_attention_mask)?;
+        let token_type_ids = tensor(tokenizers::Encoding::get_type_ids)?;
+
+        let values = [&input_ids, &attention_mask, &token_type_ids]
+            .into_iter()
+            .zip(self.session.inputs.iter())
+            .map(|(array, input)| {
+                Tensor::from_array(array.clone())
+                    .map(|tensor| (input.name.clone(), SessionInputValue::from(tensor)))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let outputs = self.session.run(values).map_err(|e| e.to_string())?;
+        let hidden = outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(|e| e.to_string())?;
+
+        // mean pooling over the real tokens
+        let mut vectors = Vec::with_capacity(batch);
+        for (i, sequence) in hidden.axis_iter(Axis(0)).enumerate() {
+            let mut vector = vec![0.0; sequence.shape()[1]];
+            let mut count = 0.0;
+            for (j, token) in sequence.axis_iter(Axis(0)).enumerate() {
+                if attention_mask[[i, j]] == 0 {
+                    continue;
+                }
+                vector
+                    .iter_mut()
+                    .zip(token.iter())
+                    .for_each(|(sum, x)| *sum += x);
+                count += 1.0;
+            }
+            if count > 0.0 {
+                vector.iter_mut().for_each(|x| *x /= count);
+            }
+            vectors.push(vector);
+        }
+
+        Ok(vectors)
+    }
+}
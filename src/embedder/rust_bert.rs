@@ -0,0 +1,40 @@
+use super::EmbeddingProvider;
+use rust_bert::pipelines::sentence_embeddings::{
+    SentenceEmbeddingsBuilder, SentenceEmbeddingsModel,
+};
+
+/// Embeddings made before the backends were pluggable used the bare
+/// directory as their id, keep it so they stay valid.
+pub fn model_id(model_dir: &str) -> String {
+    model_dir.to_string()
+}
+
+/// A sentence embedding model run by libtorch.
+pub struct RustBert {
+    model_id: String,
+    model: SentenceEmbeddingsModel,
+}
+
+impl RustBert {
+    pub fn load(model_dir: &str) -> Result<Self, String> {
+        let model = SentenceEmbeddingsBuilder::local(model_dir)
+            .with_device(tch::Device::cuda_if_available())
+            .create_model()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            model_id: model_id(model_dir),
+            model,
+        })
+    }
+}
+
+impl EmbeddingProvider for RustBert {
+    fn model_id(&self) -> String {
+        self.model_id.clone()
+    }
+
+    fn encode(&mut self, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        self.model.encode(inputs).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,228 @@
+use super::EmbeddingProvider;
+#[cfg(target_pointer_width = "64")]
+use murmur3::murmur3_x64_128 as murmur3_128;
+#[cfg(target_pointer_width = "32")]
+use murmur3::murmur3_x86_128 as murmur3_128;
+use std::collections::HashMap;
+
+/// Number of hashed features, colliding terms share a dimension.
+const DIMENSION: usize = 4096;
+/// BM25 term frequency saturation.
+const K1: f32 = 1.2;
+/// BM25 document length normalization.
+const B: f32 = 0.75;
+
+/// Id before the first fit, `TfIdf::model_id` adds a fingerprint of the
+/// fitted weights, since the vectors of different fits can't be compared.
+pub fn model_id() -> String {
+    format!("tfidf:bm25:{}", DIMENSION)
+}
+
+fn hash(term: &str) -> u128 {
+    murmur3_128(&mut term.as_bytes(), 0).unwrap_or_default()
+}
+
+/// Lowercased words, scripts written without spaces are split into
+/// character bigrams instead.
+fn terms(text: &str) -> Vec<String> {
+    let mut terms = vec![];
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        let word = word.to_lowercase();
+        if word.is_ascii() {
+            terms.push(word);
+            continue;
+        }
+        let chars = word.chars().collect::<Vec<_>>();
+        match chars.len() {
+            1 => terms.push(word),
+            _ => terms.extend(chars.windows(2).map(|pair| pair.iter().collect())),
+        }
+    }
+    terms
+}
+
+/// Dependency-free BM25 weighted bag of words, hashed into a fixed number of
+/// dimensions. The weights are learnt from the whole library.
+#[derive(Debug, Default)]
+pub struct TfIdf {
+    document_count: usize,
+    average_length: f32,
+    document_frequencies: HashMap<String, usize>,
+    /// Hash of the fitted weights, `None` before the first fit
+    fingerprint: Option<u128>,
+}
+
+impl TfIdf {
+    fn idf(&self, term: &str) -> f32 {
+        let n = self.document_count as f32;
+        let df = self.document_frequencies.get(term).copied().unwrap_or(0) as f32;
+        (1.0 + (n - df + 0.5) / (df + 0.5)).ln()
+    }
+
+    fn vector(&self, text: &str) -> Vec<f32> {
+        let terms = terms(text);
+        let length = terms.len() as f32;
+        let mut frequencies: HashMap<&str, f32> = HashMap::new();
+        for term in &terms {
+            *frequencies.entry(term).or_default() += 1.0;
+        }
+
+        let length_norm = match self.average_length > 0.0 {
+            true => 1.0 - B + B * length / self.average_length,
+            false => 1.0,
+        };
+        let mut vector = vec![0.0; DIMENSION];
+        for (term, frequency) in frequencies {
+            let weight = frequency * (K1 + 1.0) / (frequency + K1 * length_norm);
+            let hash = hash(term);
+            // the sign bit keeps colliding terms from only adding up
+            let sign = if hash >> 127 == 1 { -1.0 } else { 1.0 };
+            vector[(hash % DIMENSION as u128) as usize] += sign * weight * self.idf(term);
+        }
+        vector
+    }
+}
+
+impl EmbeddingProvider for TfIdf {
+    fn model_id(&self) -> String {
+        match self.fingerprint {
+            Some(fingerprint) => format!("{}:{:032x}", model_id(), fingerprint),
+            None => model_id(),
+        }
+    }
+
+    fn fit(&mut self, corpus: &[String]) {
+        let mut document_frequencies = HashMap::new();
+        let mut total_length = 0;
+        for document in corpus {
+            let mut terms = terms(document);
+            total_length += terms.len();
+            terms.sort_unstable();
+            terms.dedup();
+            for term in terms {
+                *document_frequencies.entry(term).or_default() += 1;
+            }
+        }
+
+        self.document_count = corpus.len();
+        self.average_length = match corpus.len() {
+            0 => 0.0,
+            n => total_length as f32 / n as f32,
+        };
+        self.document_frequencies = document_frequencies;
+
+        let mut weights = self.document_frequencies.iter().collect::<Vec<_>>();
+        weights.sort_unstable();
+        let mut fitted = format!("{} {}", self.document_count, self.average_length);
+        for (term, frequency) in weights {
+            fitted.push_str(&format!(" {}:{}", term, frequency));
+        }
+        self.fingerprint = Some(hash(&fitted));
+    }
+
+    fn encode(&mut self, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        Ok(inputs.iter().map(|input| self.vector(input)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedder::{normalize, similarity};
+
+    fn corpus() -> Vec<String> {
+        [
+            "A pirate crew sails the sea in search of treasure",
+            "Pirates and sea monsters fight over a treasure map",
+            "A quiet romance between two students at school",
+            "School club members practice for the music festival",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect()
+    }
+
+    #[test]
+    fn terms_are_lowercased_words() {
+        assert_eq!(terms("One-Piece, vol. 2!"), ["one", "piece", "vol", "2"]);
+        assert!(terms("  ...  ").is_empty());
+    }
+
+    #[test]
+    fn terms_without_spaces_are_bigrams() {
+        assert_eq!(terms("進撃の巨人"), ["進撃", "撃の", "の巨", "巨人"]);
+        assert_eq!(terms("猫 Cat"), ["猫", "cat"]);
+    }
+
+    #[test]
+    fn model_id_changes_with_the_fit() {
+        let mut tfidf = TfIdf::default();
+        assert_eq!(tfidf.model_id(), model_id());
+        tfidf.fit(&corpus());
+        let fitted = tfidf.model_id();
+        assert!(fitted.starts_with(&model_id()));
+        assert_ne!(fitted, model_id());
+
+        // same library, same id
+        let mut other = TfIdf::default();
+        other.fit(&corpus());
+        assert_eq!(other.model_id(), fitted);
+        tfidf.fit(&corpus());
+        assert_eq!(tfidf.model_id(), fitted);
+
+        tfidf.fit(&corpus()[..2]);
+        assert_ne!(tfidf.model_id(), fitted);
+    }
+
+    #[test]
+    fn fit_and_encode() {
+        let mut tfidf = TfIdf::default();
+        tfidf.fit(&corpus());
+        assert_eq!(tfidf.document_count, 4);
+        assert_eq!(tfidf.document_frequencies.get("treasure"), Some(&2));
+        assert_eq!(tfidf.document_frequencies.get("romance"), Some(&1));
+        // rarer terms weigh more
+        assert!(tfidf.idf("romance") > tfidf.idf("treasure"));
+
+        let vectors = tfidf
+            .encode(&["pirate treasure".to_string(), "pirate treasure".to_string()])
+            .unwrap();
+        assert_eq!(vectors.len(), 2);
+        assert_eq!(vectors[0].len(), DIMENSION);
+        assert_eq!(vectors[0], vectors[1]);
+        assert!(vectors[0].iter().any(|&x| x != 0.0));
+        assert!(tfidf.encode(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn similar_texts_rank_first() {
+        let mut tfidf = TfIdf::default();
+        let corpus = corpus();
+        tfidf.fit(&corpus);
+        let vectors = tfidf
+            .encode(&corpus)
+            .unwrap()
+            .into_iter()
+            .map(normalize)
+            .collect::<Vec<_>>();
+        let query = normalize(
+            tfidf
+                .encode(&["sea treasure pirates".to_string()])
+                .unwrap()
+                .remove(0),
+        );
+
+        let mut ranked = vectors
+            .iter()
+            .enumerate()
+            .map(|(index, vector)| (index, similarity(&query, vector)))
+            .collect::<Vec<_>>();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let ranked = ranked.iter().map(|(index, _)| *index).collect::<Vec<_>>();
+        assert_eq!(&ranked[..2], [1, 0]);
+        assert!(similarity(&vectors[0], &vectors[1]) > similarity(&vectors[0], &vectors[2]));
+    }
+}
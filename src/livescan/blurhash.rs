@@ -1,3 +1,4 @@
+use super::perceptual_hash::{dhash, phash};
 use crate::constants::{native_img_formats, ratio_percision};
 use blurhash::encode;
 use image::{imageops::FilterType::Gaussian, DynamicImage, GenericImageView};
@@ -5,7 +6,7 @@ use std::path::PathBuf;
 use std::{fs, process::Command};
 use tracing::{debug, error, warn};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Blurhash {
     pub ffmpeg_path: Option<String>,
     pub djxl_path: Option<String>,
@@ -18,10 +19,15 @@ pub struct BlurhashResult {
     pub ratio: u32,
     /// This is the file's name, not the full path
     pub file_name: String,
+    pub dhash: i64,
+    pub phash: i64,
+    /// Width and height of the original image, if known
+    pub dimensions: Option<(u32, u32)>,
 }
 
 impl Blurhash {
-    /// Encodes the image at the given path into a blurhash.
+    /// Encodes the image at the given path into a blurhash, and its perceptual
+    /// hashes.
     ///
     /// # Arguments
     ///
@@ -41,9 +47,8 @@ impl Blurhash {
     pub fn encode(&self, image_path: &PathBuf, format: &str) -> Option<BlurhashResult> {
         let input_img_path = image_path.to_str().unwrap_or_default();
 
-        let decoded_image = self
-            .transcode(input_img_path, format)?
-            .resize(100, 100, Gaussian);
+        let (decoded_image, dimensions) = self.transcode(input_img_path, format)?;
+        let decoded_image = decoded_image.resize(100, 100, Gaussian);
         let (width, height) = decoded_image.dimensions();
 
         let scale = width.min(height) / 3;
@@ -64,11 +69,20 @@ impl Blurhash {
             blurhash: encoded,
             ratio: (width * ratio_percision()) / height,
             file_name,
+            dhash: dhash(&decoded_image),
+            phash: phash(&decoded_image),
+            dimensions,
         })
     }
 
+    /// Decodes the image, along with its original dimensions when they're
+    /// known, ffmpeg only hands back a downscaled one.
     #[tracing::instrument]
-    pub fn transcode(&self, in_file: &str, format: &str) -> Option<DynamicImage> {
+    pub fn transcode(
+        &self,
+        in_file: &str,
+        format: &str,
+    ) -> Option<(DynamicImage, Option<(u32, u32)>)> {
         match format {
             format if native_img_formats().contains(&format) => {
                 debug!("native");
@@ -78,10 +92,17 @@ impl Blurhash {
                         err
                     })
                     .ok()
+                    .map(|image| {
+                        let dimensions = image.dimensions();
+                        (image, Some(dimensions))
+                    })
             }
             "jxl" => {
                 debug!("djxl");
-                self.jpegxl(in_file)
+                self.jpegxl(in_file).map(|image| {
+                    let dimensions = image.dimensions();
+                    (image, Some(dimensions))
+                })
             }
             _ => {
                 debug!("ffmpeg");
@@ -91,7 +112,7 @@ impl Blurhash {
     }
 
     #[tracing::instrument]
-    fn ffmpeg(&self, in_file: &str) -> Option<DynamicImage> {
+    fn ffmpeg(&self, in_file: &str) -> Option<(DynamicImage, Option<(u32, u32)>)> {
         let ffmpeg = match self.ffmpeg_path {
             Some(ref path) => path.clone(),
             None => {
@@ -115,6 +136,7 @@ impl Blurhash {
             ])
             .output()
             .ok()?;
+        let dimensions = ffmpeg_dimensions(&String::from_utf8_lossy(&output.stderr));
 
         if !output.status.success() {
             let decode_log = self.ffmpeg_log_path.as_ref();
@@ -143,6 +165,7 @@ impl Blurhash {
                 error!("{}", err_msg);
             })
             .ok()
+            .map(|image| (image, dimensions))
     }
 
     #[tracing::instrument]
@@ -179,3 +202,17 @@ impl Blurhash {
             .ok()
     }
 }
+
+/// The input's dimensions, from the `Stream #0:0: Video: webp, yuv420p,
+/// 800x1200, ...` line ffmpeg prints.
+fn ffmpeg_dimensions(stderr: &str) -> Option<(u32, u32)> {
+    let line = stderr.lines().find(|line| line.contains("Video:"))?;
+    line.split([',', ' ']).find_map(|token| {
+        let (width, height) = token.split_once('x')?;
+        // not a `0x...` codec tag
+        if width.starts_with('0') {
+            return None;
+        }
+        Some((width.parse().ok()?, height.parse().ok()?))
+    })
+}
@@ -124,6 +124,8 @@ impl Scanner {
                 debug!("thumbnail already exists in DB");
                 let mut active_thumbnail: thumbnails::ActiveModel = thumbnail_in_db.into();
                 active_thumbnail.path = Set(thumbnail.1.to_string_lossy().to_string());
+                active_thumbnail.dhash = Set(Some(thumbnail.0.dhash));
+                active_thumbnail.phash = Set(Some(thumbnail.0.phash));
                 let _ = active_thumbnail
                     .update(&self.app_state.db)
                     .await
@@ -146,6 +148,8 @@ impl Scanner {
                     path: Set(thumbnail.1.to_string_lossy().into_owned()),
                     blurhash: Set(thumbnail.0.blurhash),
                     ratio: Set(thumbnail.0.ratio),
+                    dhash: Set(Some(thumbnail.0.dhash)),
+                    phash: Set(Some(thumbnail.0.phash)),
                 }
                 .insert(&self.app_state.db)
                 .await
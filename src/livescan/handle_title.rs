@@ -1,6 +1,10 @@
 use super::{scan_category::ScannedTitle, Scanner};
 use crate::{
-    livescan::thumbnail_finder::title_thumbnail_finder,
+    livescan::{
+        page_hashes::{double_spreads, title_page_hashes},
+        strip_tiles::update_strip_tiles,
+        thumbnail_finder::title_thumbnail_finder,
+    },
     models::{metadata::TitleMetadata, prelude::*},
 };
 #[cfg(target_pointer_width = "64")]
@@ -8,7 +12,7 @@ use murmur3::murmur3_x64_128 as murmur3_128;
 #[cfg(target_pointer_width = "32")]
 use murmur3::murmur3_x86_128 as murmur3_128;
 use sea_orm::{ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, EntityTrait, QueryFilter, Set};
-use std::{fs::File, path::PathBuf};
+use std::{collections::HashMap, fs::File, path::PathBuf};
 use tracing::{debug, error, info};
 use uuid::Uuid;
 use zip::ZipArchive;
@@ -28,6 +32,7 @@ impl Scanner {
             title_metadata_path
         })
         .await;
+        title_metadata.read_comic_info(&title.path);
         /* #endregion */
 
         /* #region - title's name defined in <title>.toml ? use it : use title file_stem */
@@ -111,6 +116,13 @@ impl Scanner {
                         need_update = true;
                         active_title.release = Set(title_metadata.release_date.clone());
                     }
+                    if title_model.reader_settings() != title_metadata.reader {
+                        need_update = true;
+                        active_title.reading_direction =
+                            Set(title_metadata.reader.reading_direction);
+                        active_title.layout = Set(title_metadata.reader.layout);
+                        active_title.zoom = Set(title_metadata.reader.zoom);
+                    }
                     if title_model.hash != title_hash_current {
                         need_update = true;
                         active_title.date_updated = Set(chrono::Utc::now().timestamp().to_string());
@@ -194,6 +206,8 @@ impl Scanner {
 
                 if title_model.hash == title_hash_current {
                     info!("found in DB by path, hash match, skipping");
+                    self.backfill_hashes(&title_model, &title.path).await?;
+                    self.update_strip_tiles(&title_id).await?;
                     return Ok(());
                 }
                 info!("found in DB by path, hash not match, finding hash");
@@ -223,12 +237,16 @@ impl Scanner {
                 active_title.description = Set(title_metadata.description.clone());
                 active_title.author = Set(title_metadata.author.clone());
                 active_title.release = Set(title_metadata.release_date.clone());
+                active_title.reading_direction = Set(title_metadata.reader.reading_direction);
+                active_title.layout = Set(title_metadata.reader.layout);
+                active_title.zoom = Set(title_metadata.reader.zoom);
                 active_title.date_updated = Set(chrono::Utc::now().timestamp().to_string());
 
-                let _ = active_title.update(&self.app_state.db).await.map_err(|e| {
+                let title_model = active_title.update(&self.app_state.db).await.map_err(|e| {
                     error!("error update metadata in DB: {}", e);
                     e
                 })?;
+                self.update_strip_tiles(&title_model.id).await?;
 
                 return Ok(()); // return this handle_title function
             }
@@ -257,6 +275,10 @@ impl Scanner {
                 hash: Set(title_hash_current),
                 date_added: Set(now.clone()),
                 date_updated: Set(now),
+                reading_direction: Set(title_metadata.reader.reading_direction),
+                layout: Set(title_metadata.reader.layout),
+                zoom: Set(title_metadata.reader.zoom),
+                backfilled_hash: Set(None),
             }
             .insert(&self.app_state.db)
             .await
@@ -349,6 +371,20 @@ impl Scanner {
         }
 
         /* #region - pages */
+        // pages still at the same path keep their id, so page bookmarks
+        // follow them across rescans
+        let page_ids = Pages::find()
+            .filter(pages::Column::TitleId.eq(&title_id))
+            .all(&self.app_state.db)
+            .await
+            .map_err(|e| {
+                error!("error finding pages in DB: {}", e);
+                e
+            })?
+            .into_iter()
+            .map(|page| (page.path, page.id))
+            .collect::<HashMap<_, _>>();
+
         let _ = Pages::delete_many()
             .filter(pages::Column::TitleId.eq(&title_id))
             .exec(&self.app_state.db)
@@ -358,15 +394,42 @@ impl Scanner {
                 e
             })?;
 
-        let pages = list_files_in_zip(&title.path)?;
+        let files = list_files_in_zip(&title.path)?;
+        let pages = files
+            .iter()
+            .map(|file| file.name.clone())
+            .collect::<Vec<_>>();
         debug!("file_names: {:?}", pages);
 
-        'iteration: for page in &pages {
+        let page_hashes = title_page_hashes(
+            &self.temp_path,
+            &title_id,
+            &title.path,
+            &pages,
+            &self.blurhash,
+        )
+        .await;
+        let double_spreads = double_spreads(&page_hashes);
+
+        'iteration: for file in &files {
+            let page_hash = page_hashes.get(&file.name);
+            let dimensions = page_hash.and_then(|hash| hash.dimensions);
             let result = pages::ActiveModel {
-                id: Set(Uuid::new_v4().to_string()),
+                id: Set(page_ids
+                    .get(&file.name)
+                    .cloned()
+                    .unwrap_or_else(|| Uuid::new_v4().to_string())),
                 title_id: Set(title_id.clone()),
-                path: Set(page.clone()),
-                description: Set(title_metadata.get_page_desc(page)),
+                path: Set(file.name.clone()),
+                description: Set(title_metadata.get_page_desc(&file.name)),
+                dhash: Set(page_hash.map(|hash| hash.dhash)),
+                phash: Set(page_hash.map(|hash| hash.phash)),
+                content_hash: Set(Some(file.content_hash.clone())),
+                width: Set(dimensions.map(|(width, _)| width)),
+                height: Set(dimensions.map(|(_, height)| height)),
+                size: Set(Some(file.size as i64)),
+                blurhash: Set(page_hash.map(|hash| hash.blurhash.clone())),
+                double_spread: Set(double_spreads.get(&file.name).copied()),
             }
             .insert(&self.app_state.db)
             .await;
@@ -384,6 +447,8 @@ impl Scanner {
         self.update_thumbnail(&mut title_metadata, &title_id, &title.path)
             .await?;
 
+        self.update_strip_tiles(&title_id).await?;
+
         Ok(())
     }
 
@@ -432,6 +497,8 @@ impl Scanner {
                 path: Set(thumbnail.file_name),
                 blurhash: Set(thumbnail.blurhash),
                 ratio: Set(thumbnail.ratio),
+                dhash: Set(Some(thumbnail.dhash)),
+                phash: Set(Some(thumbnail.phash)),
             }
             .insert(&self.app_state.db)
             .await
@@ -445,7 +512,162 @@ impl Scanner {
     }
 }
 
-fn list_files_in_zip(path: &PathBuf) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+impl Scanner {
+    /// Re-cut the strip once the pages and the thumbnail, which goes first,
+    /// are in place.
+    async fn update_strip_tiles(&self, title_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        update_strip_tiles(&self.app_state.db, title_id)
+            .await
+            .map_err(|e| {
+                error!("error update strip tiles in DB: {}", e);
+                e
+            })?;
+        Ok(())
+    }
+
+    /// Compute the page hashes, dimensions, sizes and blurhashes of titles
+    /// scanned before they existed.
+    ///
+    /// Pages are only decoded once per version of the archive, even if none of
+    /// them could be.
+    async fn backfill_hashes(
+        &self,
+        title_model: &titles::Model,
+        title_path: &PathBuf,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let title_id = title_model.id.as_str();
+        let page_models = Pages::find()
+            .filter(pages::Column::TitleId.eq(title_id))
+            .all(&self.app_state.db)
+            .await
+            .map_err(|e| {
+                error!("error search pages in DB: {}", e);
+                e
+            })?;
+
+        let needs_content_hashes = page_models
+            .iter()
+            .any(|page| page.content_hash.is_none() || page.size.is_none());
+        // pages that aren't images never get one, so a single hashed page
+        // means the title is done
+        let backfilled = title_model.backfilled_hash.as_ref() == Some(&title_model.hash);
+        let needs_perceptual_hashes = !backfilled
+            && (page_models.iter().all(|page| page.dhash.is_none())
+                || page_models.iter().all(|page| page.width.is_none())
+                || page_models.iter().all(|page| page.blurhash.is_none()));
+        if page_models.is_empty() || !(needs_content_hashes || needs_perceptual_hashes) {
+            return Ok(());
+        }
+        info!("computing hashes of {} pages", page_models.len());
+
+        let files = match needs_content_hashes {
+            true => list_files_in_zip(title_path)?
+                .into_iter()
+                .map(|file| (file.name.clone(), file))
+                .collect(),
+            false => HashMap::new(),
+        };
+        let page_hashes = match needs_perceptual_hashes {
+            true => {
+                let page_paths = page_models
+                    .iter()
+                    .map(|page| page.path.clone())
+                    .collect::<Vec<_>>();
+                title_page_hashes(
+                    &self.temp_path,
+                    title_id,
+                    title_path,
+                    &page_paths,
+                    &self.blurhash,
+                )
+                .await
+            }
+            false => HashMap::new(),
+        };
+        let double_spreads = double_spreads(&page_hashes);
+
+        for page in page_models {
+            let file = files.get(&page.path);
+            let page_hash = page_hashes.get(&page.path);
+            let double_spread = double_spreads.get(&page.path).copied();
+            if file.is_none() && page_hash.is_none() {
+                continue;
+            }
+            let mut active_page: pages::ActiveModel = page.into();
+            if let Some(file) = file {
+                active_page.content_hash = Set(Some(file.content_hash.clone()));
+                active_page.size = Set(Some(file.size as i64));
+            }
+            if let Some(page_hash) = page_hash {
+                active_page.dhash = Set(Some(page_hash.dhash));
+                active_page.phash = Set(Some(page_hash.phash));
+                active_page.blurhash = Set(Some(page_hash.blurhash.clone()));
+                if let Some((width, height)) = page_hash.dimensions {
+                    active_page.width = Set(Some(width));
+                    active_page.height = Set(Some(height));
+                }
+                if double_spread.is_some() {
+                    active_page.double_spread = Set(double_spread);
+                }
+            }
+            active_page.update(&self.app_state.db).await.map_err(|e| {
+                error!("error update page in DB: {}", e);
+                e
+            })?;
+        }
+
+        // the thumbnail is one of the pages, stored by file name
+        let thumbnail_model = Thumbnails::find_by_id(title_id)
+            .filter(thumbnails::Column::Dhash.is_null())
+            .one(&self.app_state.db)
+            .await
+            .map_err(|e| {
+                error!("error search thumbnail in DB: {}", e);
+                e
+            })?;
+        if let Some(thumbnail_model) = thumbnail_model {
+            let page_hash = page_hashes.iter().find_map(|(path, page_hash)| {
+                (path.rsplit('/').next() == Some(thumbnail_model.path.as_str()))
+                    .then_some(page_hash)
+            });
+            if let Some(page_hash) = page_hash {
+                let mut active_thumbnail: thumbnails::ActiveModel = thumbnail_model.into();
+                active_thumbnail.dhash = Set(Some(page_hash.dhash));
+                active_thumbnail.phash = Set(Some(page_hash.phash));
+                active_thumbnail
+                    .update(&self.app_state.db)
+                    .await
+                    .map_err(|e| {
+                        error!("error update thumbnail in DB: {}", e);
+                        e
+                    })?;
+            }
+        }
+
+        if needs_perceptual_hashes {
+            let mut active_title: titles::ActiveModel = title_model.clone().into();
+            active_title.backfilled_hash = Set(Some(title_model.hash.clone()));
+            active_title.update(&self.app_state.db).await.map_err(|e| {
+                error!("error update title in DB: {}", e);
+                e
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+struct ZipFile {
+    name: String,
+    /// murmur3 of the uncompressed content, so re-compressed copies hash the
+    /// same
+    content_hash: String,
+    /// Uncompressed size
+    size: u64,
+}
+
+/// Every file in the zip, hashed.
+fn list_files_in_zip(path: &PathBuf) -> Result<Vec<ZipFile>, Box<dyn std::error::Error>> {
     let reader = File::open(path).map_err(|e| {
         error!("error openning title: {}", e);
         e
@@ -453,14 +675,22 @@ fn list_files_in_zip(path: &PathBuf) -> Result<Vec<String>, Box<dyn std::error::
 
     let mut archive = ZipArchive::new(reader)?;
 
-    let mut file_names = Vec::new();
+    let mut files = Vec::new();
     for i in 0..archive.len() {
-        let file = archive.by_index(i).map_err(|e| {
+        let mut file = archive.by_index(i).map_err(|e| {
             error!("error reading zip: {}", e);
             e
         })?;
-        file_names.push(file.name().to_string());
+        let content_hash = murmur3_128(&mut file, 0).map_err(|e| {
+            error!("error hashing: {}", e);
+            e
+        })?;
+        files.push(ZipFile {
+            name: file.name().to_string(),
+            content_hash: content_hash.to_string(),
+            size: file.size(),
+        });
     }
 
-    Ok(file_names)
+    Ok(files)
 }
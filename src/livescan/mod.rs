@@ -1,10 +1,13 @@
 mod blurhash;
 mod handle_category;
 mod handle_title;
+mod page_hashes;
+pub mod perceptual_hash;
 mod scan_category;
 mod scan_library;
+mod strip_tiles;
 mod thumbnail_finder;
-mod title_ssim_score;
+pub mod title_ssim_score;
 
 use self::{
     blurhash::Blurhash,
@@ -69,12 +72,15 @@ impl Scanner {
 
         tracing::info!("✅ finished scanning library");
 
-        match self.app_state.env.sentence_embedding_model_path.clone() {
-            Some(path) => {
-                title_ssim_score(&self.app_state.db, path).await?;
+        match self.app_state.embedder.as_ref() {
+            Some(embedder) => {
+                let top_k = self.app_state.env.ssim_top_k;
+                title_ssim_score(&self.app_state.db, embedder, top_k).await?;
             }
             None => {
-                tracing::warn!("no sentence embedding model path provided, skipping title relevance score calculation");
+                tracing::warn!(
+                    "no embedding backend configured, skipping title relevance score calculation"
+                );
             }
         };
 
@@ -0,0 +1,127 @@
+use super::blurhash::{Blurhash, BlurhashResult};
+use crate::constants::{double_spread_ratio, extended_img_formats};
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+};
+use tracing::error;
+use zip::ZipArchive;
+
+/// Extract a title zip, then encode every page, keyed by its path in the zip.
+///
+/// Pages that aren't images or fail to decode are left out.
+pub async fn title_page_hashes(
+    temp_dir: &PathBuf,
+    title_id: &str,
+    title_path: &Path,
+    page_paths: &[String],
+    blurhash: &Blurhash,
+) -> HashMap<String, BlurhashResult> {
+    // Creating a temp dir for the title, the id keeps it apart from the
+    // thumbnail finder's
+    let title_temp_dir = {
+        let mut title_temp_dir = PathBuf::from(temp_dir);
+        title_temp_dir.push(title_id);
+        title_temp_dir
+    };
+
+    // Extracting and decoding every page takes a while, off the runtime
+    let hashes = {
+        let title_temp_dir = title_temp_dir.clone();
+        let title_path = title_path.to_path_buf();
+        let page_paths = page_paths.to_vec();
+        let blurhash = blurhash.clone();
+        tokio::task::spawn_blocking(move || {
+            extract_and_hash(&title_temp_dir, &title_path, &page_paths, &blurhash)
+        })
+        .await
+        .unwrap_or_else(|e| {
+            error!("error hashing pages: {}", e);
+            HashMap::new()
+        })
+    };
+
+    // Delete temp dir
+    let handle = tokio::spawn(async move {
+        let _ = tokio::fs::remove_dir_all(&title_temp_dir).await;
+    });
+    std::mem::drop(handle);
+
+    hashes
+}
+
+fn extract_and_hash(
+    title_temp_dir: &Path,
+    title_path: &Path,
+    page_paths: &[String],
+    blurhash: &Blurhash,
+) -> HashMap<String, BlurhashResult> {
+    let extracted = File::open(title_path)
+        .map_err(|e| error!("error openning title: {}", e))
+        .ok()
+        .and_then(|file| {
+            ZipArchive::new(file)
+                .map_err(|e| error!("error reading title: {}", e))
+                .ok()
+        })
+        .and_then(|mut archive| {
+            archive
+                .extract(title_temp_dir)
+                .map_err(|e| {
+                    let temp_dir = title_temp_dir.to_string_lossy();
+                    error!("error extracting title to {}: {}", temp_dir, e);
+                })
+                .ok()
+        });
+
+    match extracted {
+        Some(_) => page_paths
+            .par_iter()
+            .filter_map(|page_path| {
+                let path = title_temp_dir.join(page_path);
+                let extension = path
+                    .extension()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_ascii_lowercase();
+                if !path.is_file() || !extended_img_formats().contains(&extension.as_str()) {
+                    return None;
+                }
+                blurhash
+                    .encode(&path, &extension)
+                    .map(|result| (page_path.clone(), result))
+            })
+            .collect(),
+        None => HashMap::new(),
+    }
+}
+
+/// Which of the encoded pages are double spreads: landscape, and much wider
+/// than the median page of the title.
+///
+/// A title of landscape pages has none.
+pub fn double_spreads(page_hashes: &HashMap<String, BlurhashResult>) -> HashMap<String, bool> {
+    let mut ratios = page_hashes
+        .values()
+        .filter_map(|result| result.dimensions)
+        .filter(|(_, height)| *height > 0)
+        .map(|(width, height)| width as f32 / height as f32)
+        .collect::<Vec<_>>();
+    if ratios.is_empty() {
+        return HashMap::new();
+    }
+    ratios.sort_by(f32::total_cmp);
+    let median = ratios[ratios.len() / 2];
+
+    page_hashes
+        .iter()
+        .filter_map(|(page_path, result)| {
+            let (width, height) = result.dimensions?;
+            let ratio = width as f32 / height.max(1) as f32;
+            let double_spread = width > height && ratio >= median * double_spread_ratio();
+            Some((page_path.clone(), double_spread))
+        })
+        .collect()
+}
@@ -0,0 +1,68 @@
+use image::{imageops::FilterType::Triangle, DynamicImage};
+use std::f32::consts::PI;
+
+/// Difference hash: one bit per pair of horizontally adjacent pixels of a 9x8
+/// grayscale thumbnail, set when the left one is brighter.
+///
+/// The `u64` is returned as an `i64`, SQLite has no unsigned 64-bit integers.
+pub fn dhash(image: &DynamicImage) -> i64 {
+    let small = image.resize_exact(9, 8, Triangle).to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let brighter = small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | brighter as u64;
+        }
+    }
+    hash as i64
+}
+
+/// DCT hash: one bit per coefficient of the 8x8 lowest frequencies of a 32x32
+/// grayscale thumbnail, set when it's above their median. Returned like `dhash`.
+pub fn phash(image: &DynamicImage) -> i64 {
+    const SIZE: usize = 32;
+    const LOW: usize = 8;
+
+    let small = image
+        .resize_exact(SIZE as u32, SIZE as u32, Triangle)
+        .to_luma8();
+    let pixels = small.pixels().map(|p| p[0] as f32).collect::<Vec<_>>();
+    let cos = |i: usize, k: usize| (PI / SIZE as f32 * (i as f32 + 0.5) * k as f32).cos();
+
+    // separable DCT-II, only the low frequencies are needed
+    let mut rows = [[0.0; LOW]; SIZE];
+    for (y, row) in rows.iter_mut().enumerate() {
+        for (u, coefficient) in row.iter_mut().enumerate() {
+            *coefficient = (0..SIZE).map(|x| pixels[y * SIZE + x] * cos(x, u)).sum();
+        }
+    }
+    let mut coefficients = [0.0; LOW * LOW];
+    for v in 0..LOW {
+        for u in 0..LOW {
+            coefficients[v * LOW + u] = (0..SIZE).map(|y| rows[y][u] * cos(y, v)).sum();
+        }
+    }
+
+    // the first coefficient is the average brightness, it would skew the median
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let median = sorted[sorted.len() / 2];
+
+    coefficients.iter().fold(0u64, |hash, coefficient| {
+        (hash << 1) | (*coefficient > median) as u64
+    }) as i64
+}
+
+/// Number of differing bits, from 0 to 64.
+pub fn hamming(hash_a: i64, hash_b: i64) -> u32 {
+    (hash_a ^ hash_b).count_ones()
+}
+
+/// Similarity of two images from their `(dhash, phash)`, from 0.0 to 1.0.
+///
+/// Unrelated images differ by about half of the bits, which scores 0.0.
+pub fn visual_similarity(hashes_a: (i64, i64), hashes_b: (i64, i64)) -> f32 {
+    let distance = (hamming(hashes_a.0, hashes_b.0) + hamming(hashes_a.1, hashes_b.1)) as f32 / 2.0;
+    (1.0 - distance / 32.0).clamp(0.0, 1.0)
+}
@@ -0,0 +1,179 @@
+use crate::{
+    constants::strip_tile_height,
+    models::{prelude::*, reader::ReadingLayout, strip_tiles::StripSegment},
+    routes::{find_ordered_pages, is_decodable},
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, Set};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Pages taller than this many tiles are sliced
+const SLICE_THRESHOLD: f64 = 1.5;
+
+/// Height to width ratios of the median page that read as a strip, when the
+/// title doesn't say
+const TALL_RATIO: f64 = 2.5;
+const SHORT_RATIO: f64 = 0.4;
+
+struct Tile {
+    width: u32,
+    height: u32,
+    segments: Vec<StripSegment>,
+}
+
+/// Whether the pages are a strip: either the title says so, or they're all
+/// as wide and either very tall or very short.
+fn is_strip(layout: Option<ReadingLayout>, images: &[(&pages::Model, u32, u32)]) -> bool {
+    match layout {
+        Some(ReadingLayout::Webtoon) => return true,
+        Some(_) => return false,
+        None => {}
+    }
+    let Some((_, first_width, _)) = images.first() else {
+        return false;
+    };
+    if images.iter().any(|(_, width, _)| width != first_width) {
+        return false;
+    }
+    let mut ratios = images
+        .iter()
+        .map(|(_, width, height)| *height as f64 / *width as f64)
+        .collect::<Vec<_>>();
+    ratios.sort_by(f64::total_cmp);
+    let median = ratios[ratios.len() / 2];
+    !(SHORT_RATIO..=TALL_RATIO).contains(&median)
+}
+
+/// Cut the strip into tiles about `strip_tile_height()` tall, at the width
+/// most pages have.
+///
+/// Short pages in a row are stitched into one tile, and pages much taller
+/// than a tile are sliced into tiles of their own, so a tile never cuts a page
+/// it doesn't have to.
+fn cut_tiles(images: &[(&pages::Model, u32, u32)]) -> Vec<Tile> {
+    let mut width_counts = HashMap::new();
+    for (_, width, _) in images {
+        *width_counts.entry(*width).or_insert(0) += 1;
+    }
+    let Some(tile_width) = width_counts
+        .into_iter()
+        .max_by_key(|(width, count)| (*count, *width))
+        .map(|(width, _)| width)
+    else {
+        return Vec::new();
+    };
+
+    let target = strip_tile_height() as f64;
+    // height of `rows` of a page once scaled to the tile width
+    let scaled = |rows: u32, width: u32| (rows as f64 * tile_width as f64 / width as f64).round();
+
+    let mut tiles = Vec::new();
+    let mut current = Vec::new();
+    let mut current_height = 0.0;
+    let mut flush = |current: &mut Vec<StripSegment>, current_height: &mut f64| {
+        if !current.is_empty() {
+            tiles.push(Tile {
+                width: tile_width,
+                height: *current_height as u32,
+                segments: std::mem::take(current),
+            });
+        }
+        *current_height = 0.0;
+    };
+
+    for (page, width, height) in images {
+        let page_height = scaled(*height, *width);
+        if page_height > target * SLICE_THRESHOLD {
+            flush(&mut current, &mut current_height);
+            let slices = (page_height / target).round().max(1.0) as u32;
+            for slice in 0..slices {
+                let top = (*height as u64 * slice as u64 / slices as u64) as u32;
+                let bottom = (*height as u64 * (slice + 1) as u64 / slices as u64) as u32;
+                current.push(StripSegment {
+                    page_id: page.id.clone(),
+                    top,
+                    bottom,
+                });
+                current_height = scaled(bottom - top, *width);
+                flush(&mut current, &mut current_height);
+            }
+            continue;
+        }
+        if current_height + page_height > target {
+            flush(&mut current, &mut current_height);
+        }
+        current.push(StripSegment {
+            page_id: page.id.clone(),
+            top: 0,
+            bottom: *height,
+        });
+        current_height += page_height;
+    }
+    flush(&mut current, &mut current_height);
+
+    tiles
+}
+
+/// Store the tiles of the strip of a title, if it's one, see `cut_tiles`.
+///
+/// Nothing is written if they didn't change.
+pub async fn update_strip_tiles(db: &DatabaseConnection, title_id: &str) -> Result<(), DbErr> {
+    let layout = Titles::find_by_id(title_id)
+        .one(db)
+        .await?
+        .and_then(|title| title.layout);
+    let page_models = find_ordered_pages(db, title_id).await?;
+    // only the images have dimensions
+    let images = page_models
+        .iter()
+        .filter_map(|page| Some((page, page.width?, page.height?)))
+        .filter(|(_, width, height)| *width > 0 && *height > 0)
+        .collect::<Vec<_>>();
+
+    // tiles are rendered with `image`, pages in other formats are read as
+    // they are
+    let decodable = images.iter().all(|(page, _, _)| is_decodable(page));
+    let tiles = match decodable && is_strip(layout, &images) {
+        true => cut_tiles(&images),
+        false => Vec::new(),
+    };
+
+    let stored = StripTiles::find()
+        .filter(strip_tiles::Column::TitleId.eq(title_id))
+        .order_by_asc(strip_tiles::Column::Index)
+        .all(db)
+        .await?;
+    let unchanged = stored.len() == tiles.len()
+        && stored.iter().zip(tiles.iter()).all(|(stored, tile)| {
+            stored.width == tile.width
+                && stored.height == tile.height
+                && stored.decode_segments() == tile.segments
+        });
+    if unchanged {
+        return Ok(());
+    }
+    debug!("{} strip tiles for title {}", tiles.len(), title_id);
+
+    StripTiles::delete_many()
+        .filter(strip_tiles::Column::TitleId.eq(title_id))
+        .exec(db)
+        .await?;
+    if tiles.is_empty() {
+        return Ok(());
+    }
+    let active_models = tiles
+        .iter()
+        .enumerate()
+        .map(|(index, tile)| strip_tiles::ActiveModel {
+            title_id: Set(title_id.to_string()),
+            index: Set(index as u32),
+            width: Set(tile.width),
+            height: Set(tile.height),
+            segments: Set(strip_tiles::Model::encode_segments(&tile.segments)),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+    StripTiles::insert_many(active_models).exec(db).await?;
+
+    Ok(())
+}
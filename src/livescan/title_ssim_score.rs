@@ -1,94 +1,88 @@
-use crate::models::prelude::*;
+use super::perceptual_hash::visual_similarity;
+use crate::{
+    embedder::{similarity, Embedder},
+    models::prelude::*,
+};
+#[cfg(target_pointer_width = "64")]
+use murmur3::murmur3_x64_128 as murmur3_128;
+#[cfg(target_pointer_width = "32")]
+use murmur3::murmur3_x86_128 as murmur3_128;
 use rayon::prelude::*;
-use rust_bert::pipelines::sentence_embeddings::SentenceEmbeddingsBuilder;
-use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter,
+    QuerySelect, Set,
+};
+use std::collections::{HashMap, HashSet};
 use tracing::{debug, error};
 
-fn cosine_similarity(vec1: &[f32], vec2: &[f32]) -> f32 {
-    let dot_product: f32 = vec1.iter().zip(vec2).map(|(&x1, &x2)| x1 * x2).sum();
-    let vec1_norm: f32 = vec1.iter().map(|&x| x * x).sum::<f32>().sqrt();
-    let vec2_norm: f32 = vec2.iter().map(|&x| x * x).sum::<f32>().sqrt();
-    dot_product / (vec1_norm * vec2_norm)
+/// Rows per `INSERT`, to stay under SQLite's bound parameter limit.
+const INSERT_CHUNK_SIZE: usize = 500;
+
+/// Hash of everything the scores of a title depend on.
+fn input_hash(input: &InputData) -> String {
+    let hashed = match input.cover {
+        Some((dhash, phash)) => format!("{} {} {}", input.input_soup, dhash, phash),
+        None => input.input_soup.clone(),
+    };
+    murmur3_128(&mut hashed.as_bytes(), 0)
+        .map(|hash| hash.to_string())
+        .unwrap_or_default()
+}
+
+/// Keep the `k` most similar, most similar first.
+fn top_k(mut neighbours: Vec<(String, f32)>, k: usize) -> Vec<(String, f32)> {
+    let by_similarity = |a: &(String, f32), b: &(String, f32)| b.1.total_cmp(&a.1);
+    if neighbours.len() > k && k > 0 {
+        neighbours.select_nth_unstable_by(k - 1, by_similarity);
+    }
+    neighbours.truncate(k);
+    neighbours.sort_by(by_similarity);
+    neighbours
 }
 
 #[derive(Clone)]
 struct InputData {
     title_id: String,
     input_soup: String,
+    /// Perceptual hashes of the cover
+    cover: Option<(i64, i64)>,
 }
 
-struct SsimCalculationJob {
-    title_id_a: String,
-    vector_a: Vec<f32>,
-    title_id_b: String,
-    vector_b: Vec<f32>,
-}
-
-struct SsimCalculationResult {
-    title_id_a: String,
-    title_id_b: String,
-    similarity: f32,
-}
-
-fn build_ssim_jobs(vectors: Vec<Vec<f32>>, input_data: Vec<InputData>) -> Vec<SsimCalculationJob> {
-    let mut ssim_calculation_job: Vec<SsimCalculationJob> = Vec::new();
-    for (i, vector_a) in vectors.iter().enumerate() {
-        'scoped: for (j, vector_b) in vectors.iter().enumerate().skip(i) {
-            if i == j {
-                continue 'scoped;
-            }
-            ssim_calculation_job.push(SsimCalculationJob {
-                title_id_a: input_data[i].title_id.clone(),
-                vector_a: vector_a.clone(),
-                title_id_b: input_data[j].title_id.clone(),
-                vector_b: vector_b.clone(),
-            });
-        }
-    }
-    ssim_calculation_job
-}
-
-async fn embedding(data: &[InputData], model_dir: String) -> Result<Vec<Vec<f32>>, String> {
-    let mapped_input_data = data
-        .to_owned()
-        .clone()
-        .into_iter()
-        .map(|input| input.input_soup.clone())
-        .collect::<Vec<_>>();
-
-    let result = tokio::task::spawn_blocking(move || {
-        let model = match SentenceEmbeddingsBuilder::local(model_dir)
-            .with_device(tch::Device::cuda_if_available())
-            .create_model()
-        {
-            Ok(model) => model,
-            Err(e) => {
-                error!("cannot create model: {}", e);
-                return Err(e.to_string());
-            }
-        };
-        match model.encode(&mapped_input_data) {
-            Ok(vectors) => Ok(vectors),
-            Err(e) => {
-                error!("cannot encode input: {}", e);
-                Err(e.to_string())
-            }
-        }
-    })
-    .await;
-
-    match result {
-        Ok(Ok(vectors)) => Ok(vectors),
-        Ok(Err(e)) => Err(e),
-        Err(e) => Err(e.to_string()),
-    }
+/// Similarity of two titles from their embeddings and cover hashes.
+///
+/// Text similarity, unless the covers look more alike, most titles have no
+/// description.
+pub fn title_similarity(
+    vector_a: &[f32],
+    vector_b: &[f32],
+    cover_a: Option<(i64, i64)>,
+    cover_b: Option<(i64, i64)>,
+) -> f32 {
+    let text = similarity(vector_a, vector_b);
+    let visual = match (cover_a, cover_b) {
+        (Some(cover_a), Some(cover_b)) => visual_similarity(cover_a, cover_b),
+        _ => 0.0,
+    };
+    text.max(visual)
 }
 
+/// Embed the titles that changed since the last scan, and keep the
+/// `top_k_count` most similar titles of each title in `titles_ssim`.
+///
+/// A pair scores the similarity of their embeddings, or of their covers if
+/// that's higher.
 pub async fn title_ssim_score(
     db: &DatabaseConnection,
-    model_dir: String,
+    embedder: &Embedder,
+    top_k_count: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Prepare data
+    let covers = Thumbnails::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|thumbnail| Some((thumbnail.id, (thumbnail.dhash?, thumbnail.phash?))))
+        .collect::<HashMap<_, _>>();
     let mut input_data: Vec<InputData> = Vec::new();
     for title in Titles::find().all(db).await?.iter() {
         let soup_tags = 'soup_tags: {
@@ -130,41 +124,226 @@ pub async fn title_ssim_score(
                 title.description.clone().unwrap_or_default(),
                 soup_tags
             ),
+            cover: covers.get(&title.id).copied(),
         });
     }
     debug!("input data prepared");
 
-    let vectors = embedding(&input_data, model_dir).await?;
-    debug!("input data encoded");
-
-    // Calculate similarity
-    let jobs = build_ssim_jobs(vectors, input_data);
-    let results: Vec<SsimCalculationResult> = jobs
-        .into_par_iter()
-        .map(|job| {
-            let similarity = cosine_similarity(&job.vector_a, &job.vector_b);
-            SsimCalculationResult {
-                title_id_a: job.title_id_a,
-                title_id_b: job.title_id_b,
-                similarity,
+    embedder
+        .fit(
+            input_data
+                .iter()
+                .map(|input| input.input_soup.clone())
+                .collect(),
+        )
+        .await?;
+    let model_id = embedder.model_id();
+
+    let stored = TitleEmbeddings::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|embedding| (embedding.title_id.clone(), embedding))
+        .collect::<HashMap<_, _>>();
+
+    // titles whose text or model changed since they were last embedded
+    let stale = input_data
+        .iter()
+        .filter(|input| match stored.get(&input.title_id) {
+            Some(embedding) => {
+                embedding.model_id != model_id || embedding.input_hash != input_hash(input)
             }
+            None => true,
         })
-        .collect();
-    debug!("similarity calculated");
+        .cloned()
+        .collect::<Vec<_>>();
+    let current = input_data
+        .iter()
+        .map(|input| input.title_id.as_str())
+        .collect::<HashSet<_>>();
+    let removed = stored
+        .keys()
+        .filter(|title_id| !current.contains(title_id.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if stale.is_empty() && removed.is_empty() {
+        debug!("embeddings are up to date");
+        return Ok(());
+    }
+    debug!(
+        "{} titles to embed, {} to remove",
+        stale.len(),
+        removed.len()
+    );
+
+    if !removed.is_empty() {
+        TitleEmbeddings::delete_many()
+            .filter(title_embeddings::Column::TitleId.is_in(removed.iter()))
+            .exec(db)
+            .await?;
+        TitlesSsim::delete_many()
+            .filter(
+                Condition::any()
+                    .add(titles_ssim::Column::TitleIdA.is_in(removed.iter()))
+                    .add(titles_ssim::Column::TitleIdB.is_in(removed.iter())),
+            )
+            .exec(db)
+            .await?;
+    }
+
+    let mut vectors = stored
+        .into_values()
+        .filter(|embedding| current.contains(embedding.title_id.as_str()))
+        .map(|embedding| (embedding.title_id.clone(), embedding.decode_vector()))
+        .collect::<HashMap<_, _>>();
+
+    if !stale.is_empty() {
+        let inputs = stale
+            .iter()
+            .map(|input| input.input_soup.clone())
+            .collect::<Vec<_>>();
+        let new_vectors = embedder.encode(inputs).await?;
+        debug!("input data encoded");
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut active_models = Vec::with_capacity(stale.len());
+        for (input, vector) in stale.iter().zip(new_vectors) {
+            active_models.push(title_embeddings::ActiveModel {
+                title_id: Set(input.title_id.clone()),
+                model_id: Set(model_id.clone()),
+                input_hash: Set(input_hash(input)),
+                vector: Set(title_embeddings::Model::encode_vector(&vector)),
+                updated_at: Set(now.clone()),
+            });
+            vectors.insert(input.title_id.clone(), vector);
+        }
+        for chunk in active_models.chunks(INSERT_CHUNK_SIZE) {
+            TitleEmbeddings::insert_many(chunk.to_vec())
+                .on_conflict(
+                    OnConflict::column(title_embeddings::Column::TitleId)
+                        .update_columns([
+                            title_embeddings::Column::ModelId,
+                            title_embeddings::Column::InputHash,
+                            title_embeddings::Column::Vector,
+                            title_embeddings::Column::UpdatedAt,
+                        ])
+                        .to_owned(),
+                )
+                .exec(db)
+                .await?;
+        }
+    }
+
+    // Update the neighbours
+    let re_embedded = stale
+        .iter()
+        .map(|input| input.title_id.as_str())
+        .collect::<HashSet<_>>();
+    let changed = re_embedded
+        .iter()
+        .copied()
+        .chain(removed.iter().map(String::as_str))
+        .collect::<HashSet<_>>();
 
-    // Remove everything from table
-    TitlesSsim::delete_many().exec(db).await?;
+    let similar_to = |title_id: &String, other_id: &String| {
+        title_similarity(
+            &vectors[title_id],
+            &vectors[other_id],
+            covers.get(title_id).copied(),
+            covers.get(other_id).copied(),
+        )
+    };
 
-    let active_models = results
+    // Only the lists of the changed titles, of the titles that had one of them
+    // as a neighbour, and of the titles a re-embedded one now beats the last
+    // neighbour of, are computed again
+    let mut affected = changed
+        .iter()
+        .map(|title_id| title_id.to_string())
+        .collect::<HashSet<_>>();
+    let changed_ids = changed.iter().copied().collect::<Vec<_>>();
+    for chunk in changed_ids.chunks(INSERT_CHUNK_SIZE) {
+        let title_ids = TitlesSsim::find()
+            .select_only()
+            .column(titles_ssim::Column::TitleIdA)
+            .filter(titles_ssim::Column::TitleIdB.is_in(chunk.iter().copied()))
+            .into_tuple::<String>()
+            .all(db)
+            .await?;
+        affected.extend(title_ids);
+    }
+
+    // score of the last neighbour of each title, and how many it has
+    let floors = TitlesSsim::find()
+        .select_only()
+        .column(titles_ssim::Column::TitleIdA)
+        .column_as(titles_ssim::Column::Ssim.min(), "floor")
+        .column_as(titles_ssim::Column::Id.count(), "count")
+        .group_by(titles_ssim::Column::TitleIdA)
+        .into_tuple::<(String, i64, i64)>()
+        .all(db)
+        .await?
         .into_iter()
-        .map(|result| titles_ssim::ActiveModel {
-            title_id_a: Set(result.title_id_a),
-            title_id_b: Set(result.title_id_b),
-            ssim: Set((result.similarity * 1000.0) as u16),
-            ..Default::default()
+        .map(|(title_id, floor, count)| (title_id, (floor, count as usize)))
+        .collect::<HashMap<_, _>>();
+    let candidates = vectors.len().saturating_sub(1).min(top_k_count);
+    let beaten = vectors
+        .par_iter()
+        .filter(|(title_id, _)| !affected.contains(*title_id))
+        .filter(|(title_id, _)| match floors.get(*title_id) {
+            Some((floor, count)) if *count >= candidates => re_embedded.iter().any(|other_id| {
+                *other_id != title_id.as_str()
+                    && (similar_to(title_id, &other_id.to_string()).clamp(0.0, 1.0) * 1000.0) as i64
+                        > *floor
+            }),
+            _ => candidates > 0,
         })
+        .map(|(title_id, _)| title_id.clone())
+        .collect::<Vec<_>>();
+    affected.extend(beaten);
+
+    let updates = affected
+        .par_iter()
+        .filter(|title_id| vectors.contains_key(*title_id))
+        .map(|title_id| {
+            let neighbours = vectors
+                .keys()
+                .filter(|other_id| *other_id != title_id)
+                .map(|other_id| (other_id.clone(), similar_to(title_id, other_id)))
+                .collect();
+            (title_id.clone(), top_k(neighbours, top_k_count))
+        })
+        .collect::<Vec<_>>();
+    debug!("similarity calculated for {} titles", updates.len());
+
+    let updated_ids = updates
+        .iter()
+        .map(|(title_id, _)| title_id.clone())
         .collect::<Vec<_>>();
-    TitlesSsim::insert_many(active_models).exec(db).await?;
+    for chunk in updated_ids.chunks(INSERT_CHUNK_SIZE) {
+        TitlesSsim::delete_many()
+            .filter(titles_ssim::Column::TitleIdA.is_in(chunk.iter()))
+            .exec(db)
+            .await?;
+    }
+
+    let active_models = updates
+        .into_iter()
+        .flat_map(|(title_id, neighbours)| {
+            neighbours
+                .into_iter()
+                .map(move |(other_id, similarity)| titles_ssim::ActiveModel {
+                    title_id_a: Set(title_id.clone()),
+                    title_id_b: Set(other_id),
+                    ssim: Set((similarity.clamp(0.0, 1.0) * 1000.0) as u16),
+                    ..Default::default()
+                })
+        })
+        .collect::<Vec<_>>();
+    for chunk in active_models.chunks(INSERT_CHUNK_SIZE) {
+        TitlesSsim::insert_many(chunk.to_vec()).exec(db).await?;
+    }
 
     Ok(())
 }
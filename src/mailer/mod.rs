@@ -0,0 +1,296 @@
+mod templates;
+
+use crate::config::{Config, SmtpSecurity};
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::{path::PathBuf, time::Duration};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tracing::{debug, error, warn};
+
+pub use templates::{MailTemplate, Templates};
+
+enum Transport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    /// Write every mail as an `.eml` file into a directory
+    File(AsyncFileTransport<Tokio1Executor>),
+    /// Fail the first `failures` sends, counting every attempt
+    #[cfg(test)]
+    Flaky {
+        failures: u32,
+        attempts: std::sync::Arc<std::sync::atomic::AtomicU32>,
+    },
+    /// Print every mail to the log at `debug`, for development, the links in
+    /// them log in as the receiver
+    Log,
+}
+
+impl Transport {
+    fn from_config(env: &Config) -> Result<Self, String> {
+        match env.mail_transport.as_str() {
+            "smtp" => {
+                let host = env.smtp_host.as_ref().ok_or("Invalid smtp host")?;
+                let username = env.smtp_username.clone().ok_or("Invalid smtp username")?;
+                let password = env.smtp_password.clone().ok_or("Invalid smtp password")?;
+
+                let builder = match env.smtp_security {
+                    SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+                    SmtpSecurity::StartTls => {
+                        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                    }
+                    SmtpSecurity::None => Ok(
+                        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+                    ),
+                };
+                let mut builder = builder
+                    .map_err(|e| format!("Failed to create mailer: {}", e))?
+                    .credentials(Credentials::new(username, password));
+                if let Some(port) = env.smtp_port {
+                    builder = builder.port(port as u16);
+                }
+                Ok(Self::Smtp(builder.build()))
+            }
+            "file" => {
+                std::fs::create_dir_all(&env.mail_file_dir)
+                    .map_err(|e| format!("Failed to create {}: {}", env.mail_file_dir, e))?;
+                Ok(Self::File(AsyncFileTransport::<Tokio1Executor>::new(
+                    &env.mail_file_dir,
+                )))
+            }
+            "log" => Ok(Self::Log),
+            other => Err(format!("Unknown mail transport: {}", other)),
+        }
+    }
+
+    async fn send(&self, message: &Message) -> Result<(), String> {
+        match self {
+            Self::Smtp(transport) => transport
+                .send(message.clone())
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            Self::File(transport) => transport
+                .send(message.clone())
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            #[cfg(test)]
+            Self::Flaky { failures, attempts } => {
+                let attempt = attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                match attempt < *failures {
+                    true => Err("connection refused".to_string()),
+                    false => Ok(()),
+                }
+            }
+            Self::Log => {
+                debug!("mail:\n{}", String::from_utf8_lossy(&message.formatted()));
+                Ok(())
+            }
+        }
+    }
+}
+
+struct QueuedMail {
+    message: Message,
+    /// How many times sending this mail has failed so far
+    attempt: u32,
+}
+
+impl std::fmt::Debug for QueuedMail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueuedMail")
+            .field("attempt", &self.attempt)
+            .finish()
+    }
+}
+
+/// Renders templated mails and hands them to a background queue, which
+/// sends them through the configured transport, retrying on failure.
+#[derive(Debug)]
+pub struct Mailer {
+    from: Mailbox,
+    app_name: String,
+    templates: Templates,
+    queue: UnboundedSender<QueuedMail>,
+}
+
+impl Mailer {
+    /// Build the transport from the config and spawn the sending queue.
+    ///
+    /// Must be called from within the tokio runtime.
+    pub fn new(env: &Config) -> Result<Self, String> {
+        let transport = Transport::from_config(env)?;
+
+        let from_name = env.smtp_from_name.clone().unwrap_or(env.app_name.clone());
+        let from_email = match (&env.smtp_from_email, env.mail_transport.as_str()) {
+            (Some(email), _) => email.clone(),
+            (None, "smtp") => return Err("Invalid smtp from email".to_string()),
+            (None, _) => "yomuyume@localhost".to_string(),
+        };
+        let from: Mailbox = format!("{} <{}>", from_name, from_email)
+            .parse()
+            .map_err(|_| format!("Invalid from address: {}", from_email))?;
+
+        let (queue, receiver) = unbounded_channel();
+        tokio::spawn(run_queue(
+            transport,
+            env.mail_max_retries,
+            queue.clone(),
+            receiver,
+        ));
+
+        Ok(Self {
+            from,
+            app_name: env.app_name.clone(),
+            templates: Templates {
+                dir: env.mail_templates_dir.as_ref().map(PathBuf::from),
+            },
+            queue,
+        })
+    }
+
+    /// Render a template and queue it for sending.
+    ///
+    /// `username` and `app_name` are always available to the template,
+    /// on top of the given `vars`.
+    pub fn send(
+        &self,
+        template: MailTemplate,
+        receiver_name: &str,
+        receiver_email: &str,
+        vars: &[(&str, &str)],
+    ) -> Result<(), String> {
+        let to: Mailbox = format!("{} <{}>", receiver_name, receiver_email)
+            .parse()
+            .map_err(|_| format!("Invalid to address: {}", receiver_email))?;
+
+        let mut all_vars = vec![("username", receiver_name), ("app_name", &self.app_name)];
+        all_vars.extend_from_slice(vars);
+        let (text, html) = self.templates.render(template, &all_vars);
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(template.subject(&self.app_name))
+            .multipart(MultiPart::alternative_plain_html(text, html))
+            .map_err(|_| "Failed to build email")?;
+
+        self.queue
+            .send(QueuedMail {
+                message,
+                attempt: 0,
+            })
+            .map_err(|_| "Mail queue is closed".to_string())
+    }
+}
+
+/// Longest wait before sending a mail again
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Back off 2, 4, 8... seconds after the `attempt`th failure, up to
+/// `MAX_RETRY_DELAY`.
+fn retry_delay(attempt: u32) -> Duration {
+    2u64.checked_pow(attempt.saturating_add(1))
+        .map_or(MAX_RETRY_DELAY, Duration::from_secs)
+        .min(MAX_RETRY_DELAY)
+}
+
+async fn run_queue(
+    transport: Transport,
+    max_retries: u32,
+    queue: UnboundedSender<QueuedMail>,
+    mut receiver: UnboundedReceiver<QueuedMail>,
+) {
+    while let Some(mail) = receiver.recv().await {
+        let err = match transport.send(&mail.message).await {
+            Ok(()) => {
+                debug!("mail sent after {} failed attempt(s)", mail.attempt);
+                continue;
+            }
+            Err(e) => e,
+        };
+
+        if mail.attempt >= max_retries {
+            error!(
+                "giving up sending mail after {} attempts: {}",
+                mail.attempt.saturating_add(1),
+                err
+            );
+            continue;
+        }
+
+        // without blocking the rest of the queue
+        let delay = retry_delay(mail.attempt);
+        warn!("failed to send mail, retrying in {:?}: {}", delay, err);
+        let queue = queue.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = queue.send(QueuedMail {
+                message: mail.message,
+                attempt: mail.attempt + 1,
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    fn message() -> Message {
+        Message::builder()
+            .from("Yomuyume <yomuyume@localhost>".parse().unwrap())
+            .to("Reader <reader@localhost>".parse().unwrap())
+            .subject("Hello")
+            .body(String::from("Hello"))
+            .unwrap()
+    }
+
+    /// Queue one mail through a transport failing `failures` times, and count
+    /// the attempts once every retry is due.
+    async fn attempts(failures: u32, max_retries: u32) -> u32 {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let transport = Transport::Flaky {
+            failures,
+            attempts: attempts.clone(),
+        };
+        let (queue, receiver) = unbounded_channel();
+        tokio::spawn(run_queue(transport, max_retries, queue.clone(), receiver));
+
+        queue
+            .send(QueuedMail {
+                message: message(),
+                attempt: 0,
+            })
+            .unwrap();
+        tokio::time::sleep(MAX_RETRY_DELAY * (max_retries + 1)).await;
+        attempts.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        assert_eq!(retry_delay(0), Duration::from_secs(2));
+        assert_eq!(retry_delay(2), Duration::from_secs(8));
+        assert_eq!(retry_delay(11), Duration::from_secs(60 * 60));
+        assert_eq!(retry_delay(63), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_until_sent() {
+        assert_eq!(attempts(0, 3).await, 1);
+        assert_eq!(attempts(2, 3).await, 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_max_retries() {
+        assert_eq!(attempts(u32::MAX, 3).await, 4);
+        assert_eq!(attempts(u32::MAX, 0).await, 1);
+    }
+}
@@ -0,0 +1,141 @@
+use std::path::PathBuf;
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MailTemplate {
+    VerifyAccount,
+    ResetPassword,
+    DeleteAccount,
+}
+
+impl MailTemplate {
+    /// File stem of the template, `<name>.txt` and `<name>.html` are looked up
+    /// in the templates dir before falling back to the built-in ones.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::VerifyAccount => "verify_account",
+            Self::ResetPassword => "reset_password",
+            Self::DeleteAccount => "delete_account",
+        }
+    }
+
+    pub fn subject(&self, app_name: &str) -> String {
+        match self {
+            Self::VerifyAccount => format!("{} - Verify your account", app_name),
+            Self::ResetPassword => format!("{} - Reset your password", app_name),
+            Self::DeleteAccount => format!("{} - Delete your account", app_name),
+        }
+    }
+
+    fn builtin_text(&self) -> &'static str {
+        match self {
+            Self::VerifyAccount => include_str!("templates/verify_account.txt"),
+            Self::ResetPassword => include_str!("templates/reset_password.txt"),
+            Self::DeleteAccount => include_str!("templates/delete_account.txt"),
+        }
+    }
+
+    fn builtin_html(&self) -> &'static str {
+        match self {
+            Self::VerifyAccount => include_str!("templates/verify_account.html"),
+            Self::ResetPassword => include_str!("templates/reset_password.html"),
+            Self::DeleteAccount => include_str!("templates/delete_account.html"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Templates {
+    /// Where to look for user-provided templates, if any
+    pub dir: Option<PathBuf>,
+}
+
+impl Templates {
+    /// Render both the plain-text and the HTML body of a template.
+    ///
+    /// Every `{{key}}` in the template is replaced by its value in `vars`,
+    /// values are HTML-escaped in the HTML body.
+    pub fn render(&self, template: MailTemplate, vars: &[(&str, &str)]) -> (String, String) {
+        let text = self
+            .read_override(template, "txt")
+            .unwrap_or_else(|| template.builtin_text().to_string());
+        let html = self
+            .read_override(template, "html")
+            .unwrap_or_else(|| template.builtin_html().to_string());
+
+        let text = vars.iter().fold(text, |body, (key, value)| {
+            body.replace(&format!("{{{{{}}}}}", key), value)
+        });
+        let html = vars.iter().fold(html, |body, (key, value)| {
+            body.replace(&format!("{{{{{}}}}}", key), &escape_html(value))
+        });
+
+        (text, html)
+    }
+
+    fn read_override(&self, template: MailTemplate, extension: &str) -> Option<String> {
+        let mut path = self.dir.clone()?;
+        path.push(template.name());
+        path.set_extension(extension);
+        if !path.is_file() {
+            return None;
+        }
+        std::fs::read_to_string(&path)
+            .map_err(|e| {
+                warn!(
+                    "error reading mail template {}: {}",
+                    path.to_string_lossy(),
+                    e
+                )
+            })
+            .ok()
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_values_in_html_only() {
+        let vars = [
+            ("username", "<b>Tom & \"Jerry\"</b>"),
+            ("app_name", "Yomuyume"),
+            ("link", "https://example.com/?a=1&b='2'"),
+            ("token", "abc"),
+        ];
+        let (text, html) = Templates::default().render(MailTemplate::ResetPassword, &vars);
+
+        assert!(text.contains("Hello, <b>Tom & \"Jerry\"</b>!"));
+        assert!(text.contains("https://example.com/?a=1&b='2'"));
+        assert!(html.contains("Hello, &lt;b&gt;Tom &amp; &quot;Jerry&quot;&lt;/b&gt;!"));
+        assert!(html.contains("href=\"https://example.com/?a=1&amp;b=&#39;2&#39;\""));
+        assert!(!html.contains("{{"));
+    }
+
+    #[test]
+    fn overrides_builtin_templates() {
+        let dir = std::env::temp_dir().join(format!("yomuyume-mail-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("verify_account.html"), "<p>{{username}}</p>").unwrap();
+
+        let templates = Templates {
+            dir: Some(dir.clone()),
+        };
+        let (text, html) = templates.render(MailTemplate::VerifyAccount, &[("username", "<me>")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(html, "<p>&lt;me&gt;</p>");
+        // no text override, the built-in one is used
+        assert!(text.contains("Hello <me>,"));
+    }
+}
@@ -1,11 +1,13 @@
 use crate::{
     config::Config,
+    embedder::Embedder,
+    mailer::Mailer,
     migrator::Migrator,
-    routes::{auth, ApiDoc},
+    routes::{admin, auth, feed_auth, opds_challenge, ApiDoc, ApiKeyCache},
 };
 use axum::{
-    middleware::from_fn_with_state as apply,
-    routing::{get, post, put},
+    middleware::{from_fn_with_state as apply, map_response_with_state},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 use routes::*;
@@ -21,7 +23,9 @@ use utoipa_swagger_ui::SwaggerUi;
 
 mod config;
 mod constants;
+mod embedder;
 mod livescan;
+mod mailer;
 mod migrator;
 mod models;
 mod routes;
@@ -30,8 +34,11 @@ mod routes;
 pub struct AppState {
     db: DatabaseConnection,
     env: Config,
+    embedder: Option<Embedder>,
+    mailer: Option<Mailer>,
     scanning_complete: Mutex<bool>,
     scanning_progress: Mutex<f64>,
+    api_keys: ApiKeyCache,
 }
 
 #[tokio::main]
@@ -66,14 +73,33 @@ async fn main() -> Result<(), DbErr> {
     assert!(schema_manager.has_table("thumbnails").await?);
     assert!(schema_manager.has_table("favorites").await?);
     assert!(schema_manager.has_table("progresses").await?);
+    assert!(schema_manager.has_table("reading_sessions").await?);
+    assert!(schema_manager.has_table("collections").await?);
+    assert!(schema_manager.has_table("collections_titles").await?);
+    assert!(schema_manager.has_table("ratings").await?);
+    assert!(schema_manager.has_table("smart_collections").await?);
+    assert!(schema_manager.has_table("title_embeddings").await?);
+    assert!(schema_manager.has_table("ssim_feedbacks").await?);
+    assert!(schema_manager.has_table("api_keys").await?);
+    assert!(schema_manager.has_table("reader_preferences").await?);
+    assert!(schema_manager.has_table("strip_tiles").await?);
 
     info!("database migrations complete!");
 
+    let mailer = Mailer::new(&config)
+        .map_err(|e| tracing::warn!("mail is disabled: {}", e))
+        .ok();
+
+    let embedder = Embedder::from_config(&config);
+
     let app_state = Arc::new(AppState {
         db,
         env: config.clone(),
+        embedder,
+        mailer,
         scanning_complete: Mutex::new(false),
         scanning_progress: Mutex::new(0.0),
+        api_keys: ApiKeyCache::default(),
     });
 
     let auth_routes = Router::new()
@@ -87,7 +113,8 @@ async fn main() -> Result<(), DbErr> {
     let utils_routes = Router::new()
         .route("/tags", get(get_tags))
         .route("/scanning_progress", get(get_scanning_progress))
-        .route("/ssim_eval", get(get_ssim_eval))
+        .route("/ssim_eval", get(get_ssim_eval).post(post_ssim_eval))
+        .route("/stats", get(get_stats))
         .layer(apply(app_state.clone(), auth));
 
     let user_routes = Router::new()
@@ -98,23 +125,164 @@ async fn main() -> Result<(), DbErr> {
         .route("/modify", post(post_modify))
         .route("/bookmark/:id", put(put_bookmark).delete(delete_bookmark))
         .route("/favorite/:id", put(put_favorite).delete(delete_favorite))
+        .route("/rating/:title_id", put(put_rating).delete(delete_rating))
+        .route(
+            "/reader/:title_id",
+            put(put_reader_preference).delete(delete_reader_preference),
+        )
+        .route("/bookmarks", get(get_bookmarks).post(post_bookmark))
+        .route(
+            "/bookmarks/:bookmark_id",
+            put(put_bookmark_note).delete(delete_page_bookmark),
+        )
+        .route("/bookmarks/:bookmark_id/page", get(get_bookmark_page))
+        .route("/collections", get(get_collections).post(post_collection))
+        .route(
+            "/collections/:collection_id",
+            get(get_collection)
+                .put(put_collection)
+                .delete(delete_collection),
+        )
+        .route(
+            "/collections/:collection_id/titles/:title_id",
+            put(put_collection_title).delete(delete_collection_title),
+        )
+        .route(
+            "/collections/:collection_id/order",
+            put(put_collection_order),
+        )
+        .route(
+            "/smart_collections",
+            get(get_smart_collections).post(post_smart_collection),
+        )
+        .route(
+            "/smart_collections/:smart_collection_id",
+            get(get_smart_collection)
+                .put(put_smart_collection)
+                .delete(delete_smart_collection),
+        )
+        .route("/progress", get(get_progresses))
+        .route("/progress/sync", post(post_sync_progress))
+        .route("/progress/:title_id", get(get_progress))
         .route("/progress/:title_id/:page", put(put_progress))
+        .route("/history", get(get_history).delete(delete_history))
+        .route("/stats", get(get_user_stats))
+        .route("/api_keys", get(get_api_keys).post(post_api_key))
+        .route("/api_keys/:api_key_id", delete(delete_api_key))
         .layer(apply(app_state.clone(), auth));
 
     let index_routes = Router::new()
         .route("/filter", post(post_filter))
+        .route("/search", post(post_search))
         .route("/categories", get(get_categories))
+        .route("/continue", get(get_continue))
+        .route("/home", get(get_home))
         .route("/title/:title_id", get(get_title))
+        .route("/title/:title_id/reviews", get(get_reviews))
+        .route("/title/:title_id/similar", get(get_similar))
+        .route("/for_you", get(get_for_you))
         .layer(apply(app_state.clone(), auth));
 
     let file_routes = Router::new()
         .route("/page/:page_id", get(get_page))
-        .route("/thumbnail/:thumbnail_id", get(get_thumbnail))
+        .route("/title/:title_id/download", get(get_title_download))
+        .route("/title/:title_id/pages", get(get_signed_pages))
+        .route(
+            "/category/:category_id/download",
+            get(get_category_download),
+        )
+        .route(
+            "/collection/:collection_id/download",
+            get(get_collection_download),
+        )
+        .layer(apply(app_state.clone(), auth))
+        // linked from the OPDS and Komga feeds
+        .merge(
+            Router::new()
+                .route("/thumbnail/:thumbnail_id", get(get_thumbnail))
+                .layer(apply(app_state.clone(), feed_auth)),
+        );
+
+    let admin_routes = Router::new()
+        .route("/ssim_feedback/report", get(get_ssim_report))
+        .route("/ssim_feedback/export", get(get_ssim_export))
+        .route("/duplicates", get(get_duplicates))
+        .layer(apply(app_state.clone(), admin))
         .layer(apply(app_state.clone(), auth));
 
+    let opds_routes = Router::new()
+        .route("/", get(get_opds_root))
+        .route("/categories", get(get_opds_categories))
+        .route("/categories/:category_id", get(get_opds_category))
+        .route("/new", get(get_opds_new))
+        .route("/updated", get(get_opds_updated))
+        .route("/search.xml", get(get_opds_search_description))
+        .route("/search", get(get_opds_search))
+        .route("/titles/:title_id/file", get(get_opds_title_file))
+        .route("/titles/:title_id/pages/:page_number", get(get_opds_page))
+        .layer(apply(app_state.clone(), feed_auth))
+        .layer(map_response_with_state(app_state.clone(), opds_challenge));
+
+    // Komga clients add `/api/v1` to the address they're given
+    let komga_routes = match config.komga_compat {
+        true => Router::new().nest(
+            "/komga/api",
+            Router::new()
+                .route("/v1/libraries", get(get_komga_libraries))
+                .route("/v1/libraries/:library_id", get(get_komga_library))
+                .route("/v1/series", get(get_komga_series_list))
+                .route("/v1/series/new", get(get_komga_series_new))
+                .route("/v1/series/updated", get(get_komga_series_updated))
+                .route("/v1/series/latest", get(get_komga_series_updated))
+                .route("/v1/series/:series_id", get(get_komga_series))
+                .route("/v1/series/:series_id/books", get(get_komga_series_books))
+                .route(
+                    "/v1/series/:series_id/thumbnail",
+                    get(get_komga_series_thumbnail),
+                )
+                .route(
+                    "/v2/series/:series_id/read-progress/tachiyomi",
+                    get(get_komga_tachiyomi_progress).put(put_komga_tachiyomi_progress),
+                )
+                .route("/v1/books/:book_id", get(get_komga_book))
+                .route("/v1/books/:book_id/pages", get(get_komga_book_pages))
+                .route(
+                    "/v1/books/:book_id/pages/:page_number",
+                    get(get_komga_book_page),
+                )
+                .route(
+                    "/v1/books/:book_id/thumbnail",
+                    get(get_komga_book_thumbnail),
+                )
+                .route("/v1/books/:book_id/file", get(get_komga_book_file))
+                .route(
+                    "/v1/books/:book_id/read-progress",
+                    patch(patch_komga_read_progress).delete(delete_komga_read_progress),
+                )
+                .route("/v1/tags", get(get_komga_tags))
+                .route("/v1/genres", get(get_komga_empty_list))
+                .route("/v1/publishers", get(get_komga_empty_list))
+                .route("/v1/authors", get(get_komga_authors))
+                .route("/v1/collections", get(get_komga_collections))
+                .route("/v1/users/me", get(get_komga_me))
+                .layer(apply(app_state.clone(), feed_auth)),
+        ),
+        false => Router::new(),
+    };
+
     let open_routes = Router::new()
         .route("/user/reset/:email", get(get_reset))
-        .route("/utils/status", get(get_status).post(post_status));
+        .route("/user/link/verify/:token", get(get_verify_link))
+        .route(
+            "/user/link/reset/:token",
+            get(get_reset_link).post(post_reset_link),
+        )
+        .route(
+            "/user/link/delete/:token",
+            get(get_delete_link).post(post_delete_link),
+        )
+        .route("/utils/status", get(get_status).post(post_status))
+        .route("/file/signed/:token", get(get_signed_page));
 
     let app = Router::new()
         .nest("/api/auth", auth_routes)
@@ -122,6 +290,9 @@ async fn main() -> Result<(), DbErr> {
         .nest("/api/user", user_routes)
         .nest("/api/utils", utils_routes)
         .nest("/api/file", file_routes)
+        .nest("/api/admin", admin_routes)
+        .nest("/opds", opds_routes)
+        .merge(komga_routes)
         .nest("/api", open_routes)
         .merge(SwaggerUi::new("/swagger").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .merge(Redoc::with_url("/redoc", ApiDoc::openapi()))
@@ -134,7 +305,12 @@ async fn main() -> Result<(), DbErr> {
 
     let server_handle = tokio::spawn(async move {
         tracing::debug!("listening on: {}", addr);
-        if let Err(e) = axum::serve(listener, app.into_make_service()).await {
+        if let Err(e) = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        {
             tracing::error!("server error: {}", e);
         };
     });
@@ -0,0 +1,83 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240106_000012_alter_progresses_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            ColumnDef::new(Progresses::ScrollOffset)
+                .integer()
+                .not_null()
+                .default(0)
+                .to_owned(),
+            ColumnDef::new(Progresses::Completed)
+                .boolean()
+                .not_null()
+                .default(false)
+                .to_owned(),
+            ColumnDef::new(Progresses::StartedAt).date_time().to_owned(),
+            ColumnDef::new(Progresses::FinishedAt)
+                .date_time()
+                .to_owned(),
+            ColumnDef::new(Progresses::DeviceId).string().to_owned(),
+        ];
+
+        // SQLite only takes one column per ALTER TABLE
+        for mut column in columns {
+            let table = Table::alter()
+                .table(Progresses::Table)
+                .add_column(&mut column)
+                .to_owned();
+            manager.alter_table(table).await?;
+        }
+
+        // `page == 0` used to mean "finished"
+        let db = manager.get_connection();
+        db.execute_unprepared("UPDATE progresses SET started_at = last_read_at")
+            .await?;
+        db.execute_unprepared(
+            "UPDATE progresses SET completed = 1, finished_at = last_read_at WHERE page = 0",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            Progresses::ScrollOffset,
+            Progresses::Completed,
+            Progresses::StartedAt,
+            Progresses::FinishedAt,
+            Progresses::DeviceId,
+        ];
+
+        for column in columns {
+            let table = Table::alter()
+                .table(Progresses::Table)
+                .drop_column(column)
+                .to_owned();
+            manager.alter_table(table).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Progresses {
+    Table,
+    ScrollOffset,
+    Completed,
+    StartedAt,
+    FinishedAt,
+    DeviceId,
+}
@@ -0,0 +1,96 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::{
+    m_20231113_000001_create_users_table::Users, m_20231115_000003_create_titles_table::Titles,
+};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240108_000013_create_reading_sessions_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(ReadingSessions::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(ReadingSessions::Id)
+                    .integer()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(ReadingSessions::UserId).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-reading_session-user_id")
+                    .from(ReadingSessions::Table, ReadingSessions::UserId)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(ReadingSessions::TitleId).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-reading_session-title_id")
+                    .from(ReadingSessions::Table, ReadingSessions::TitleId)
+                    .to(Titles::Table, Titles::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(
+                ColumnDef::new(ReadingSessions::StartedAt)
+                    .date_time()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(ReadingSessions::EndedAt)
+                    .date_time()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(ReadingSessions::FirstPage)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(ReadingSessions::LastPage)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(ReadingSessions::PagesRead)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(ReadingSessions::Duration)
+                    .integer()
+                    .not_null(),
+            )
+            .to_owned();
+        manager.create_table(table).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::drop().table(ReadingSessions::Table).to_owned();
+        manager.drop_table(table).await
+    }
+}
+
+#[derive(Iden)]
+pub enum ReadingSessions {
+    Table,
+    Id,
+    UserId,
+    TitleId,
+    StartedAt,
+    EndedAt,
+    FirstPage,
+    LastPage,
+    PagesRead,
+    Duration,
+}
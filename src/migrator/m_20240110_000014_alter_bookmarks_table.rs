@@ -0,0 +1,63 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240110_000014_alter_bookmarks_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can't add a foreign key to an existing table, pages are
+        // re-created on rescan anyway so `page_id` may dangle
+        let columns = [
+            ColumnDef::new(Bookmarks::PageId).uuid().to_owned(),
+            ColumnDef::new(Bookmarks::Note).string().to_owned(),
+            ColumnDef::new(Bookmarks::CreatedAt).date_time().to_owned(),
+        ];
+
+        // SQLite only takes one column per ALTER TABLE
+        for mut column in columns {
+            let table = Table::alter()
+                .table(Bookmarks::Table)
+                .add_column(&mut column)
+                .to_owned();
+            manager.alter_table(table).await?;
+        }
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let query = Query::update()
+            .table(Bookmarks::Table)
+            .value(Bookmarks::CreatedAt, now)
+            .to_owned();
+        manager.exec_stmt(query).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [Bookmarks::PageId, Bookmarks::Note, Bookmarks::CreatedAt];
+
+        for column in columns {
+            let table = Table::alter()
+                .table(Bookmarks::Table)
+                .drop_column(column)
+                .to_owned();
+            manager.alter_table(table).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Bookmarks {
+    Table,
+    PageId,
+    Note,
+    CreatedAt,
+}
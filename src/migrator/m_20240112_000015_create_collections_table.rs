@@ -0,0 +1,72 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::m_20231113_000001_create_users_table::Users;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240112_000015_create_collections_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(Collections::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(Collections::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(Collections::UserId).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-collection-user_id")
+                    .from(Collections::Table, Collections::UserId)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(Collections::Name).string().not_null())
+            .col(ColumnDef::new(Collections::Description).string())
+            .col(
+                ColumnDef::new(Collections::IsPublic)
+                    .boolean()
+                    .not_null()
+                    .default(false),
+            )
+            .col(
+                ColumnDef::new(Collections::CreatedAt)
+                    .date_time()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(Collections::UpdatedAt)
+                    .date_time()
+                    .not_null(),
+            )
+            .to_owned();
+        manager.create_table(table).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::drop().table(Collections::Table).to_owned();
+        manager.drop_table(table).await
+    }
+}
+
+#[derive(Iden)]
+pub enum Collections {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Description,
+    IsPublic,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,77 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::{
+    m_20231115_000003_create_titles_table::Titles,
+    m_20240112_000015_create_collections_table::Collections,
+};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240112_000016_create_collections_titles_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(CollectionsTitles::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(CollectionsTitles::Id)
+                    .integer()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(CollectionsTitles::CollectionId)
+                    .uuid()
+                    .not_null(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-collection_title-collection_id")
+                    .from(CollectionsTitles::Table, CollectionsTitles::CollectionId)
+                    .to(Collections::Table, Collections::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(CollectionsTitles::TitleId).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-collection_title-title_id")
+                    .from(CollectionsTitles::Table, CollectionsTitles::TitleId)
+                    .to(Titles::Table, Titles::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(
+                ColumnDef::new(CollectionsTitles::Position)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(CollectionsTitles::AddedAt)
+                    .date_time()
+                    .not_null(),
+            )
+            .to_owned();
+        manager.create_table(table).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::drop().table(CollectionsTitles::Table).to_owned();
+        manager.drop_table(table).await
+    }
+}
+
+#[derive(Iden)]
+pub enum CollectionsTitles {
+    Table,
+    Id,
+    CollectionId,
+    TitleId,
+    Position,
+    AddedAt,
+}
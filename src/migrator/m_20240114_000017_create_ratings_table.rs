@@ -0,0 +1,68 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::{
+    m_20231113_000001_create_users_table::Users, m_20231115_000003_create_titles_table::Titles,
+};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240114_000017_create_ratings_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(Ratings::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(Ratings::Id)
+                    .integer()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(Ratings::UserId).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-rating-user_id")
+                    .from(Ratings::Table, Ratings::UserId)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(Ratings::TitleId).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-rating-title_id")
+                    .from(Ratings::Table, Ratings::TitleId)
+                    .to(Titles::Table, Titles::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(Ratings::Rating).integer().not_null())
+            .col(ColumnDef::new(Ratings::Review).string())
+            .col(ColumnDef::new(Ratings::CreatedAt).date_time().not_null())
+            .col(ColumnDef::new(Ratings::UpdatedAt).date_time().not_null())
+            .to_owned();
+        manager.create_table(table).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::drop().table(Ratings::Table).to_owned();
+        manager.drop_table(table).await
+    }
+}
+
+#[derive(Iden)]
+pub enum Ratings {
+    Table,
+    Id,
+    UserId,
+    TitleId,
+    Rating,
+    Review,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,72 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::m_20231113_000001_create_users_table::Users;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240116_000018_create_smart_collections_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(SmartCollections::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(SmartCollections::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(SmartCollections::UserId).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-smart_collection-user_id")
+                    .from(SmartCollections::Table, SmartCollections::UserId)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(SmartCollections::Name).string().not_null())
+            .col(ColumnDef::new(SmartCollections::Filter).string().not_null())
+            .col(
+                ColumnDef::new(SmartCollections::IsPinned)
+                    .boolean()
+                    .not_null()
+                    .default(false),
+            )
+            .col(
+                ColumnDef::new(SmartCollections::CreatedAt)
+                    .date_time()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(SmartCollections::UpdatedAt)
+                    .date_time()
+                    .not_null(),
+            )
+            .to_owned();
+        manager.create_table(table).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::drop().table(SmartCollections::Table).to_owned();
+        manager.drop_table(table).await
+    }
+}
+
+#[derive(Iden)]
+pub enum SmartCollections {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Filter,
+    IsPinned,
+    CreatedAt,
+    UpdatedAt,
+}
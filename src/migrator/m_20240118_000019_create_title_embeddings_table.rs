@@ -0,0 +1,85 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::{
+    m_20231115_000003_create_titles_table::Titles, m_20231212_000011_create_titles_ssim::TitlesSsim,
+};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240118_000019_create_title_embeddings_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(TitleEmbeddings::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(TitleEmbeddings::TitleId)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-title_embedding-title_id")
+                    .from(TitleEmbeddings::Table, TitleEmbeddings::TitleId)
+                    .to(Titles::Table, Titles::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(TitleEmbeddings::ModelId).string().not_null())
+            .col(
+                ColumnDef::new(TitleEmbeddings::InputHash)
+                    .string()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(TitleEmbeddings::Vector).binary().not_null())
+            .col(
+                ColumnDef::new(TitleEmbeddings::UpdatedAt)
+                    .date_time()
+                    .not_null(),
+            )
+            .to_owned();
+        manager.create_table(table).await?;
+
+        // `titles_ssim` now holds the top-k neighbours of each title in
+        // `title_id_a`, instead of every pair once
+        let index = Index::create()
+            .name("idx-titles_ssim-title_id_a")
+            .table(TitlesSsim::Table)
+            .col(TitlesSsim::TitleIdA)
+            .to_owned();
+        manager.create_index(index).await?;
+        manager
+            .exec_stmt(Query::delete().from_table(TitlesSsim::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let index = Index::drop()
+            .name("idx-titles_ssim-title_id_a")
+            .table(TitlesSsim::Table)
+            .to_owned();
+        manager.drop_index(index).await?;
+
+        let table = Table::drop().table(TitleEmbeddings::Table).to_owned();
+        manager.drop_table(table).await
+    }
+}
+
+#[derive(Iden)]
+pub enum TitleEmbeddings {
+    Table,
+    TitleId,
+    ModelId,
+    InputHash,
+    Vector,
+    UpdatedAt,
+}
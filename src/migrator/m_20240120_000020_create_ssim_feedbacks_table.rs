@@ -0,0 +1,101 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::{
+    m_20231113_000001_create_users_table::Users, m_20231115_000003_create_titles_table::Titles,
+};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240120_000020_create_ssim_feedbacks_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(SsimFeedbacks::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(SsimFeedbacks::Id)
+                    .integer()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(SsimFeedbacks::UserId).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-ssim_feedback-user_id")
+                    .from(SsimFeedbacks::Table, SsimFeedbacks::UserId)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(SsimFeedbacks::TitleIdA).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-ssim_feedback-title_id_a")
+                    .from(SsimFeedbacks::Table, SsimFeedbacks::TitleIdA)
+                    .to(Titles::Table, Titles::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(SsimFeedbacks::TitleIdB).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-ssim_feedback-title_id_b")
+                    .from(SsimFeedbacks::Table, SsimFeedbacks::TitleIdB)
+                    .to(Titles::Table, Titles::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(
+                ColumnDef::new(SsimFeedbacks::IsSimilar)
+                    .boolean()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(SsimFeedbacks::Ssim).integer().not_null())
+            .col(
+                ColumnDef::new(SsimFeedbacks::CreatedAt)
+                    .date_time()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(SsimFeedbacks::UpdatedAt)
+                    .date_time()
+                    .not_null(),
+            )
+            .to_owned();
+        manager.create_table(table).await?;
+
+        // one answer per user and pair, title_id_a is always the smaller id
+        let index = Index::create()
+            .name("idx-ssim_feedbacks-user_id-title_ids")
+            .table(SsimFeedbacks::Table)
+            .col(SsimFeedbacks::UserId)
+            .col(SsimFeedbacks::TitleIdA)
+            .col(SsimFeedbacks::TitleIdB)
+            .unique()
+            .if_not_exists()
+            .to_owned();
+        manager.create_index(index).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::drop().table(SsimFeedbacks::Table).to_owned();
+        manager.drop_table(table).await
+    }
+}
+
+#[derive(Iden)]
+pub enum SsimFeedbacks {
+    Table,
+    Id,
+    UserId,
+    TitleIdA,
+    TitleIdB,
+    IsSimilar,
+    Ssim,
+    CreatedAt,
+    UpdatedAt,
+}
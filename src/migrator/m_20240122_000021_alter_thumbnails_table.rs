@@ -0,0 +1,53 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240122_000021_alter_thumbnails_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // filled in by the next scan
+        let columns = [
+            ColumnDef::new(Thumbnails::Dhash).big_integer().to_owned(),
+            ColumnDef::new(Thumbnails::Phash).big_integer().to_owned(),
+        ];
+
+        // SQLite only takes one column per ALTER TABLE
+        for mut column in columns {
+            let table = Table::alter()
+                .table(Thumbnails::Table)
+                .add_column(&mut column)
+                .to_owned();
+            manager.alter_table(table).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [Thumbnails::Dhash, Thumbnails::Phash];
+
+        for column in columns {
+            let table = Table::alter()
+                .table(Thumbnails::Table)
+                .drop_column(column)
+                .to_owned();
+            manager.alter_table(table).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Thumbnails {
+    Table,
+    Dhash,
+    Phash,
+}
@@ -0,0 +1,53 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240122_000022_alter_pages_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // filled in by the next scan
+        let columns = [
+            ColumnDef::new(Pages::Dhash).big_integer().to_owned(),
+            ColumnDef::new(Pages::Phash).big_integer().to_owned(),
+        ];
+
+        // SQLite only takes one column per ALTER TABLE
+        for mut column in columns {
+            let table = Table::alter()
+                .table(Pages::Table)
+                .add_column(&mut column)
+                .to_owned();
+            manager.alter_table(table).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [Pages::Dhash, Pages::Phash];
+
+        for column in columns {
+            let table = Table::alter()
+                .table(Pages::Table)
+                .drop_column(column)
+                .to_owned();
+            manager.alter_table(table).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Pages {
+    Table,
+    Dhash,
+    Phash,
+}
@@ -0,0 +1,50 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240124_000023_alter_pages_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // filled in by the next scan
+        let table = Table::alter()
+            .table(Pages::Table)
+            .add_column(ColumnDef::new(Pages::ContentHash).string())
+            .to_owned();
+        manager.alter_table(table).await?;
+
+        let index = Index::create()
+            .name("idx-pages-content_hash")
+            .table(Pages::Table)
+            .col(Pages::ContentHash)
+            .if_not_exists()
+            .to_owned();
+        manager.create_index(index).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let index = Index::drop()
+            .name("idx-pages-content_hash")
+            .table(Pages::Table)
+            .to_owned();
+        manager.drop_index(index).await?;
+
+        let table = Table::alter()
+            .table(Pages::Table)
+            .drop_column(Pages::ContentHash)
+            .to_owned();
+        manager.alter_table(table).await
+    }
+}
+
+#[derive(Iden)]
+pub enum Pages {
+    Table,
+    ContentHash,
+}
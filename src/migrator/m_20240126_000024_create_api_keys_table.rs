@@ -0,0 +1,52 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::m_20231113_000001_create_users_table::Users;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240126_000024_create_api_keys_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(ApiKeys::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(ApiKeys::Id).uuid().not_null().primary_key())
+            .col(ColumnDef::new(ApiKeys::UserId).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-api_key-user_id")
+                    .from(ApiKeys::Table, ApiKeys::UserId)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(ApiKeys::Name).string().not_null())
+            .col(ColumnDef::new(ApiKeys::SecretHash).string().not_null())
+            .col(ColumnDef::new(ApiKeys::CreatedAt).date_time().not_null())
+            .col(ColumnDef::new(ApiKeys::LastUsedAt).date_time())
+            .to_owned();
+        manager.create_table(table).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::drop().table(ApiKeys::Table).to_owned();
+        manager.drop_table(table).await
+    }
+}
+
+#[derive(Iden)]
+pub enum ApiKeys {
+    Table,
+    Id,
+    UserId,
+    Name,
+    SecretHash,
+    CreatedAt,
+    LastUsedAt,
+}
@@ -0,0 +1,57 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240201_000027_alter_titles_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // filled in by the next scan
+        let columns = [
+            ColumnDef::new(Titles::ReadingDirection)
+                .string_len(16)
+                .to_owned(),
+            ColumnDef::new(Titles::Layout).string_len(16).to_owned(),
+            ColumnDef::new(Titles::Zoom).string_len(16).to_owned(),
+        ];
+
+        // SQLite only takes one column per ALTER TABLE
+        for mut column in columns {
+            let table = Table::alter()
+                .table(Titles::Table)
+                .add_column(&mut column)
+                .to_owned();
+            manager.alter_table(table).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [Titles::ReadingDirection, Titles::Layout, Titles::Zoom];
+
+        for column in columns {
+            let table = Table::alter()
+                .table(Titles::Table)
+                .drop_column(column)
+                .to_owned();
+            manager.alter_table(table).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Titles {
+    Table,
+    ReadingDirection,
+    Layout,
+    Zoom,
+}
@@ -0,0 +1,82 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::{
+    m_20231113_000001_create_users_table::Users, m_20231115_000003_create_titles_table::Titles,
+};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240201_000028_create_reader_preferences_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(ReaderPreferences::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(ReaderPreferences::Id)
+                    .integer()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(ReaderPreferences::UserId).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-reader_preference-user_id")
+                    .from(ReaderPreferences::Table, ReaderPreferences::UserId)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(ReaderPreferences::TitleId).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-reader_preference-title_id")
+                    .from(ReaderPreferences::Table, ReaderPreferences::TitleId)
+                    .to(Titles::Table, Titles::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(ReaderPreferences::ReadingDirection).string_len(16))
+            .col(ColumnDef::new(ReaderPreferences::Layout).string_len(16))
+            .col(ColumnDef::new(ReaderPreferences::Zoom).string_len(16))
+            .col(
+                ColumnDef::new(ReaderPreferences::UpdatedAt)
+                    .date_time()
+                    .not_null(),
+            )
+            .to_owned();
+        manager.create_table(table).await?;
+
+        let index = Index::create()
+            .name("idx-reader_preferences-user_id-title_id")
+            .table(ReaderPreferences::Table)
+            .col(ReaderPreferences::UserId)
+            .col(ReaderPreferences::TitleId)
+            .unique()
+            .if_not_exists()
+            .to_owned();
+        manager.create_index(index).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::drop().table(ReaderPreferences::Table).to_owned();
+        manager.drop_table(table).await
+    }
+}
+
+#[derive(Iden)]
+pub enum ReaderPreferences {
+    Table,
+    Id,
+    UserId,
+    TitleId,
+    ReadingDirection,
+    Layout,
+    Zoom,
+    UpdatedAt,
+}
@@ -0,0 +1,67 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::m_20231115_000003_create_titles_table::Titles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240203_000029_create_strip_tiles_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::create()
+            .table(StripTiles::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(StripTiles::Id)
+                    .integer()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(StripTiles::TitleId).uuid().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-strip_tile-title_id")
+                    .from(StripTiles::Table, StripTiles::TitleId)
+                    .to(Titles::Table, Titles::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .col(ColumnDef::new(StripTiles::Index).integer().not_null())
+            .col(ColumnDef::new(StripTiles::Width).integer().not_null())
+            .col(ColumnDef::new(StripTiles::Height).integer().not_null())
+            .col(ColumnDef::new(StripTiles::Segments).string().not_null())
+            .to_owned();
+        manager.create_table(table).await?;
+
+        let index = Index::create()
+            .name("idx-strip_tiles-title_id-index")
+            .table(StripTiles::Table)
+            .col(StripTiles::TitleId)
+            .col(StripTiles::Index)
+            .unique()
+            .if_not_exists()
+            .to_owned();
+        manager.create_index(index).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = Table::drop().table(StripTiles::Table).to_owned();
+        manager.drop_table(table).await
+    }
+}
+
+#[derive(Iden)]
+pub enum StripTiles {
+    Table,
+    Id,
+    TitleId,
+    Index,
+    Width,
+    Height,
+    Segments,
+}
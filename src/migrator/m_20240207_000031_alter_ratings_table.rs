@@ -0,0 +1,44 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::m_20240114_000017_create_ratings_table::Ratings;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240207_000031_alter_ratings_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // concurrent ratings could insert the same pair twice, keep the latest
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "DELETE FROM ratings WHERE id NOT IN \
+             (SELECT MAX(id) FROM ratings GROUP BY user_id, title_id)",
+        )
+        .await?;
+
+        // one rating per user and title
+        let index = Index::create()
+            .name("idx-ratings-user_id-title_id")
+            .table(Ratings::Table)
+            .col(Ratings::UserId)
+            .col(Ratings::TitleId)
+            .unique()
+            .if_not_exists()
+            .to_owned();
+        manager.create_index(index).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let index = Index::drop()
+            .name("idx-ratings-user_id-title_id")
+            .table(Ratings::Table)
+            .to_owned();
+        manager.drop_index(index).await
+    }
+}
@@ -0,0 +1,54 @@
+use axum::async_trait;
+use sea_orm_migration::prelude::*;
+
+use super::m_20240112_000016_create_collections_titles_table::CollectionsTitles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240209_000032_alter_collections_titles_table"
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // concurrent adds could insert a title twice, or two titles at the same
+        // position, keep the first and number them again
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "DELETE FROM collections_titles WHERE id NOT IN \
+             (SELECT MIN(id) FROM collections_titles GROUP BY collection_id, title_id)",
+        )
+        .await?;
+        db.execute_unprepared(
+            "UPDATE collections_titles SET position = \
+             (SELECT COUNT(*) FROM collections_titles AS earlier \
+             WHERE earlier.collection_id = collections_titles.collection_id \
+             AND (earlier.position < collections_titles.position \
+             OR (earlier.position = collections_titles.position \
+             AND earlier.id < collections_titles.id)))",
+        )
+        .await?;
+
+        // a title is in a collection once
+        let index = Index::create()
+            .name("idx-collections_titles-collection_id-title_id")
+            .table(CollectionsTitles::Table)
+            .col(CollectionsTitles::CollectionId)
+            .col(CollectionsTitles::TitleId)
+            .unique()
+            .if_not_exists()
+            .to_owned();
+        manager.create_index(index).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let index = Index::drop()
+            .name("idx-collections_titles-collection_id-title_id")
+            .table(CollectionsTitles::Table)
+            .to_owned();
+        manager.drop_index(index).await
+    }
+}
@@ -12,6 +12,27 @@ mod m_20231212_000008_create_thumbnails_table;
 mod m_20231212_000009_create_favorites_table;
 mod m_20231212_000010_create_progresses_table;
 mod m_20231212_000011_create_titles_ssim;
+mod m_20240106_000012_alter_progresses_table;
+mod m_20240108_000013_create_reading_sessions_table;
+mod m_20240110_000014_alter_bookmarks_table;
+mod m_20240112_000015_create_collections_table;
+mod m_20240112_000016_create_collections_titles_table;
+mod m_20240114_000017_create_ratings_table;
+mod m_20240116_000018_create_smart_collections_table;
+mod m_20240118_000019_create_title_embeddings_table;
+mod m_20240120_000020_create_ssim_feedbacks_table;
+mod m_20240122_000021_alter_thumbnails_table;
+mod m_20240122_000022_alter_pages_table;
+mod m_20240124_000023_alter_pages_table;
+mod m_20240126_000024_create_api_keys_table;
+mod m_20240128_000025_alter_pages_table;
+mod m_20240130_000026_alter_pages_table;
+mod m_20240201_000027_alter_titles_table;
+mod m_20240201_000028_create_reader_preferences_table;
+mod m_20240203_000029_create_strip_tiles_table;
+mod m_20240205_000030_alter_titles_table;
+mod m_20240207_000031_alter_ratings_table;
+mod m_20240209_000032_alter_collections_titles_table;
 
 pub struct Migrator;
 
@@ -30,6 +51,27 @@ impl MigratorTrait for Migrator {
             Box::new(m_20231212_000009_create_favorites_table::Migration),
             Box::new(m_20231212_000010_create_progresses_table::Migration),
             Box::new(m_20231212_000011_create_titles_ssim::Migration),
+            Box::new(m_20240106_000012_alter_progresses_table::Migration),
+            Box::new(m_20240108_000013_create_reading_sessions_table::Migration),
+            Box::new(m_20240110_000014_alter_bookmarks_table::Migration),
+            Box::new(m_20240112_000015_create_collections_table::Migration),
+            Box::new(m_20240112_000016_create_collections_titles_table::Migration),
+            Box::new(m_20240114_000017_create_ratings_table::Migration),
+            Box::new(m_20240116_000018_create_smart_collections_table::Migration),
+            Box::new(m_20240118_000019_create_title_embeddings_table::Migration),
+            Box::new(m_20240120_000020_create_ssim_feedbacks_table::Migration),
+            Box::new(m_20240122_000021_alter_thumbnails_table::Migration),
+            Box::new(m_20240122_000022_alter_pages_table::Migration),
+            Box::new(m_20240124_000023_alter_pages_table::Migration),
+            Box::new(m_20240126_000024_create_api_keys_table::Migration),
+            Box::new(m_20240128_000025_alter_pages_table::Migration),
+            Box::new(m_20240130_000026_alter_pages_table::Migration),
+            Box::new(m_20240201_000027_alter_titles_table::Migration),
+            Box::new(m_20240201_000028_create_reader_preferences_table::Migration),
+            Box::new(m_20240203_000029_create_strip_tiles_table::Migration),
+            Box::new(m_20240205_000030_alter_titles_table::Migration),
+            Box::new(m_20240207_000031_alter_ratings_table::Migration),
+            Box::new(m_20240209_000032_alter_collections_titles_table::Migration),
         ]
     }
 }
@@ -1,3 +1,8 @@
+use super::users;
+#[cfg(target_pointer_width = "64")]
+use murmur3::murmur3_x64_128 as murmur3_128;
+#[cfg(target_pointer_width = "32")]
+use murmur3::murmur3_x86_128 as murmur3_128;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
@@ -21,4 +26,31 @@ pub struct TokenClaims {
     pub exp: usize,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub purpose: Option<TokenClaimsPurpose>,
+    /// Set on emailed tokens, see `TokenClaims::user_fingerprint`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+}
+
+impl TokenClaims {
+    /// Hash of the parts of the user that the emailed actions change.
+    ///
+    /// A token carrying it stops working once it has been used: verifying
+    /// flips `is_verified`, resetting changes the password, deleting removes
+    /// the user, and changing the email invalidates pending verifications.
+    pub fn user_fingerprint(user: &users::Model) -> String {
+        let state = format!("{}|{}|{}", user.password, user.email, user.is_verified);
+        murmur3_128(&mut state.as_bytes(), 0)
+            .map(|hash| hash.to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Claims of a signed page URL, see `routes::file::signed_pages`.
+///
+/// There's no `sub`, so it can't be passed off as a login token.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PageTokenClaims {
+    pub page_id: String,
+    pub iat: usize,
+    pub exp: usize,
 }
@@ -9,13 +9,17 @@ pub struct Model {
     pub id: i64,
     pub user_id: String,
     pub title_id: String,
+    /// `None` for a bookmark on the whole title
+    pub page_id: Option<String>,
+    pub note: Option<String>,
+    pub created_at: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(
         belongs_to = "super::users::Entity",
-        from = "Column::Id",
+        from = "Column::UserId",
         to = "super::users::Column::Id",
         on_update = "NoAction",
         on_delete = "Cascade"
@@ -23,7 +27,7 @@ pub enum Relation {
     Users,
     #[sea_orm(
         belongs_to = "super::titles::Entity",
-        from = "Column::Id",
+        from = "Column::TitleId",
         to = "super::titles::Column::Id",
         on_update = "NoAction",
         on_delete = "Cascade"
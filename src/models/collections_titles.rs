@@ -0,0 +1,49 @@
+use sea_orm::entity::prelude::*;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, ToSchema)]
+#[schema(as = CollectionTitle)]
+#[sea_orm(table_name = "collections_titles")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub collection_id: String,
+    pub title_id: String,
+    /// 0-based position of the title in the collection
+    pub position: i64,
+    pub added_at: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::collections::Entity",
+        from = "Column::CollectionId",
+        to = "super::collections::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Collections,
+    #[sea_orm(
+        belongs_to = "super::titles::Entity",
+        from = "Column::TitleId",
+        to = "super::titles::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Titles,
+}
+
+impl Related<super::collections::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Collections.def()
+    }
+}
+
+impl Related<super::titles::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Titles.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
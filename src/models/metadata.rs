@@ -1,6 +1,9 @@
-use std::path::PathBuf;
+use super::reader::{ReaderSettings, ReadingDirection, ReadingLayout};
+use sea_orm::ActiveEnum;
+use std::{fs::File, io::Read, path::PathBuf};
 use toml_edit::Document;
 use tracing::{debug, info, warn};
+use zip::ZipArchive;
 
 fn try_read_toml(path: &PathBuf) -> Option<Document> {
     if !path.exists() {
@@ -28,6 +31,13 @@ fn try_read_toml(path: &PathBuf) -> Option<Document> {
     }
 }
 
+/// Text of the first `<tag>`, good enough for the flat `ComicInfo.xml`.
+fn xml_value<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = xml[start..].find(&format!("</{}>", tag))? + start;
+    Some(xml[start..end].trim())
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct TitleMetadata {
     pub title: Option<String>,
@@ -40,6 +50,8 @@ pub struct TitleMetadata {
     /// 1st element is the page number
     /// 2nd element is the description
     pub descriptions: Option<Vec<(String, String)>>,
+    /// How the title is read, `reading_direction`, `layout` and `zoom`
+    pub reader: ReaderSettings,
     doc: Document,
     path: PathBuf,
 }
@@ -59,6 +71,11 @@ impl TitleMetadata {
         new.thumbnail = new.parse_string("thumbnail");
         new.release_date = new.parse_string("release");
         new.descriptions = new.parse_table("descriptions");
+        new.reader = ReaderSettings {
+            reading_direction: new.parse_enum("reading_direction"),
+            layout: new.parse_enum("layout"),
+            zoom: new.parse_enum("zoom"),
+        };
         new.path = path.clone();
 
         new
@@ -70,6 +87,14 @@ impl TitleMetadata {
             .and_then(|value| value.as_str().map(|s| s.to_string()))
     }
 
+    /// A value of one of the `reader` enums, by its snake_case name
+    fn parse_enum<T: ActiveEnum<Value = String>>(&self, key: &str) -> Option<T> {
+        let value = self.parse_string(key)?;
+        T::try_from_value(&value)
+            .map_err(|_| warn!("unknown {}: {}", key, value))
+            .ok()
+    }
+
     fn parse_array(&self, key: &str) -> Option<Vec<String>> {
         self.doc.get(key).and_then(|value| {
             value.as_array().map(|a| {
@@ -117,6 +142,41 @@ impl TitleMetadata {
             .map(|(_, description)| description.clone())
     }
 
+    /// Fill the reader settings the TOML doesn't set from the `ComicInfo.xml`
+    /// of the title, if it has one.
+    pub fn read_comic_info(&mut self, title_path: &PathBuf) {
+        let comic_info = File::open(title_path)
+            .ok()
+            .and_then(|file| ZipArchive::new(file).ok())
+            .and_then(|mut archive| {
+                let name = archive
+                    .file_names()
+                    .find(|name| name.eq_ignore_ascii_case("ComicInfo.xml"))?
+                    .to_string();
+                let mut comic_info = String::new();
+                archive
+                    .by_name(&name)
+                    .ok()?
+                    .read_to_string(&mut comic_info)
+                    .ok()?;
+                Some(comic_info)
+            });
+        let comic_info = match comic_info {
+            Some(comic_info) => comic_info,
+            None => return,
+        };
+
+        // `Yes` is a manga read left to right
+        let manga = xml_value(&comic_info, "Manga");
+        if self.reader.reading_direction.is_none() && manga == Some("YesAndRightToLeft") {
+            self.reader.reading_direction = Some(ReadingDirection::RightToLeft);
+        }
+        let format = xml_value(&comic_info, "Format").unwrap_or_default();
+        if self.reader.layout.is_none() && format.to_ascii_lowercase().contains("webtoon") {
+            self.reader.layout = Some(ReadingLayout::Webtoon);
+        }
+    }
+
     pub fn set_thumbnail(&mut self, value: String) {
         self.doc["thumbnail"] = toml_edit::value(&value);
         self.thumbnail = Some(value);
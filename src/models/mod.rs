@@ -1,12 +1,23 @@
+pub mod api_keys;
 pub mod auth;
 pub mod bookmarks;
 pub mod categories;
+pub mod collections;
+pub mod collections_titles;
 pub mod favorites;
 pub mod metadata;
 pub mod pages;
 pub mod progresses;
+pub mod ratings;
+pub mod reader;
+pub mod reader_preferences;
+pub mod reading_sessions;
+pub mod smart_collections;
+pub mod ssim_feedbacks;
+pub mod strip_tiles;
 pub mod tags;
 pub mod thumbnails;
+pub mod title_embeddings;
 pub mod titles;
 pub mod titles_ssim;
 pub mod titles_tags;
@@ -11,6 +11,22 @@ pub struct Model {
     pub title_id: String,
     pub path: String,
     pub description: Option<String>,
+    /// Perceptual hashes, see `livescan::perceptual_hash`
+    #[serde(skip)]
+    pub dhash: Option<i64>,
+    #[serde(skip)]
+    pub phash: Option<i64>,
+    /// murmur3 of the uncompressed file
+    #[serde(skip)]
+    pub content_hash: Option<String>,
+    /// Pixel dimensions, `None` if the page couldn't be decoded
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Uncompressed size in bytes
+    pub size: Option<i64>,
+    pub blurhash: Option<String>,
+    /// Much wider than the other pages of the title, two pages scanned as one
+    pub double_spread: Option<bool>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
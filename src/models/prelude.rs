@@ -1,10 +1,20 @@
+pub use super::api_keys::Entity as ApiKeys;
 pub use super::bookmarks::Entity as Bookmarks;
 pub use super::categories::Entity as Categories;
+pub use super::collections::Entity as Collections;
+pub use super::collections_titles::Entity as CollectionsTitles;
 pub use super::favorites::Entity as Favorites;
 pub use super::pages::Entity as Pages;
 pub use super::progresses::Entity as Progresses;
+pub use super::ratings::Entity as Ratings;
+pub use super::reader_preferences::Entity as ReaderPreferences;
+pub use super::reading_sessions::Entity as ReadingSessions;
+pub use super::smart_collections::Entity as SmartCollections;
+pub use super::ssim_feedbacks::Entity as SsimFeedbacks;
+pub use super::strip_tiles::Entity as StripTiles;
 pub use super::tags::Entity as Tags;
 pub use super::thumbnails::Entity as Thumbnails;
+pub use super::title_embeddings::Entity as TitleEmbeddings;
 pub use super::titles::Entity as Titles;
 pub use super::titles_ssim::Entity as TitlesSsim;
 pub use super::titles_tags::Entity as TitlesTags;
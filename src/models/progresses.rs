@@ -10,7 +10,15 @@ pub struct Model {
     pub user_id: String,
     pub title_id: String,
     pub last_read_at: String,
+    /// 0-based index of the page being read
     pub page: i64,
+    /// How far down the page, normalized to 0-1000
+    pub scroll_offset: u32,
+    pub completed: bool,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// The device that last wrote this progress
+    pub device_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,51 @@
+use sea_orm::entity::prelude::*;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, ToSchema)]
+#[schema(as = Rating)]
+#[sea_orm(table_name = "ratings")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub user_id: String,
+    pub title_id: String,
+    /// From 1 to 10
+    pub rating: u32,
+    pub review: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Users,
+    #[sea_orm(
+        belongs_to = "super::titles::Entity",
+        from = "Column::TitleId",
+        to = "super::titles::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Titles,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
+
+impl Related<super::titles::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Titles.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
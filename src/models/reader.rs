@@ -0,0 +1,113 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Deserialize,
+    Serialize,
+    ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum ReadingDirection {
+    #[default]
+    #[sea_orm(string_value = "left_to_right")]
+    LeftToRight,
+    #[sea_orm(string_value = "right_to_left")]
+    RightToLeft,
+}
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Deserialize,
+    Serialize,
+    ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum ReadingLayout {
+    /// One page at a time
+    #[default]
+    #[sea_orm(string_value = "paged")]
+    Paged,
+    /// Pages one below the other, with gaps
+    #[sea_orm(string_value = "continuous")]
+    Continuous,
+    /// One long strip, no gaps
+    #[sea_orm(string_value = "webtoon")]
+    Webtoon,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Serialize, ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum ReadingZoom {
+    #[sea_orm(string_value = "fit_screen")]
+    FitScreen,
+    #[sea_orm(string_value = "fit_width")]
+    FitWidth,
+    #[sea_orm(string_value = "fit_height")]
+    FitHeight,
+    #[sea_orm(string_value = "original")]
+    Original,
+}
+
+impl ReadingZoom {
+    /// Fit the width of strips, the whole page otherwise
+    pub fn default_for(layout: ReadingLayout) -> Self {
+        match layout {
+            ReadingLayout::Paged => Self::FitScreen,
+            ReadingLayout::Continuous | ReadingLayout::Webtoon => Self::FitWidth,
+        }
+    }
+}
+
+/// How a title is read, every field unset falls back to the next source.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ReaderSettings {
+    pub reading_direction: Option<ReadingDirection>,
+    pub layout: Option<ReadingLayout>,
+    pub zoom: Option<ReadingZoom>,
+}
+
+impl ReaderSettings {
+    pub fn is_empty(&self) -> bool {
+        self.reading_direction.is_none() && self.layout.is_none() && self.zoom.is_none()
+    }
+
+    /// Fill the unset fields from `fallback`.
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            reading_direction: self.reading_direction.or(fallback.reading_direction),
+            layout: self.layout.or(fallback.layout),
+            zoom: self.zoom.or(fallback.zoom),
+        }
+    }
+
+    /// Fill every unset field with the default.
+    pub fn resolve(self) -> Self {
+        let layout = self.layout.unwrap_or_default();
+        Self {
+            reading_direction: Some(self.reading_direction.unwrap_or_default()),
+            layout: Some(layout),
+            zoom: Some(self.zoom.unwrap_or(ReadingZoom::default_for(layout))),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use super::reader::{ReaderSettings, ReadingDirection, ReadingLayout, ReadingZoom};
+use sea_orm::entity::prelude::*;
+use utoipa::ToSchema;
+
+/// A user's overrides of how a title is read.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, ToSchema)]
+#[schema(as = ReaderPreference)]
+#[sea_orm(table_name = "reader_preferences")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub user_id: String,
+    pub title_id: String,
+    pub reading_direction: Option<ReadingDirection>,
+    pub layout: Option<ReadingLayout>,
+    pub zoom: Option<ReadingZoom>,
+    pub updated_at: String,
+}
+
+impl Model {
+    pub fn settings(&self) -> ReaderSettings {
+        ReaderSettings {
+            reading_direction: self.reading_direction,
+            layout: self.layout,
+            zoom: self.zoom,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Users,
+    #[sea_orm(
+        belongs_to = "super::titles::Entity",
+        from = "Column::TitleId",
+        to = "super::titles::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Titles,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
+
+impl Related<super::titles::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Titles.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
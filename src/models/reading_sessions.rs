@@ -0,0 +1,54 @@
+use sea_orm::entity::prelude::*;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, ToSchema)]
+#[schema(as = ReadingSession)]
+#[sea_orm(table_name = "reading_sessions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub user_id: String,
+    pub title_id: String,
+    pub started_at: String,
+    pub ended_at: String,
+    pub first_page: i64,
+    pub last_page: i64,
+    /// Pages turned forward during the session
+    pub pages_read: i64,
+    /// In seconds
+    pub duration: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Users,
+    #[sea_orm(
+        belongs_to = "super::titles::Entity",
+        from = "Column::TitleId",
+        to = "super::titles::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Titles,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
+
+impl Related<super::titles::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Titles.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
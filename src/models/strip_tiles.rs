@@ -0,0 +1,60 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A tile of the long strip of a webtoon, see `livescan::strip_tiles`.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, ToSchema)]
+#[schema(as = StripTile)]
+#[sea_orm(table_name = "strip_tiles")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub title_id: String,
+    /// Position in the strip, from 0
+    pub index: u32,
+    /// Size of the rendered tile
+    pub width: u32,
+    pub height: u32,
+    /// JSON list of `StripSegment`, top to bottom
+    pub segments: String,
+}
+
+/// Rows of a page that go in a tile, scaled to the width of the tile.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StripSegment {
+    pub page_id: String,
+    /// First row, in pixels of the page
+    pub top: u32,
+    /// Row after the last one
+    pub bottom: u32,
+}
+
+impl Model {
+    pub fn decode_segments(&self) -> Vec<StripSegment> {
+        serde_json::from_str(&self.segments).unwrap_or_default()
+    }
+
+    pub fn encode_segments(segments: &[StripSegment]) -> String {
+        serde_json::to_string(segments).unwrap_or_default()
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::titles::Entity",
+        from = "Column::TitleId",
+        to = "super::titles::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Titles,
+}
+
+impl Related<super::titles::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Titles.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -10,6 +10,9 @@ pub struct Model {
     pub path: String,
     pub blurhash: String,
     pub ratio: u32,
+    /// Perceptual hashes, see `livescan::perceptual_hash`
+    pub dhash: Option<i64>,
+    pub phash: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,3 +1,4 @@
+use super::reader::{ReaderSettings, ReadingDirection, ReadingLayout, ReadingZoom};
 use sea_orm::entity::prelude::*;
 use serde::Serialize;
 use utoipa::ToSchema;
@@ -17,6 +18,25 @@ pub struct Model {
     pub path: String,
     pub date_added: String,
     pub date_updated: String,
+    /// How it's read unless users override it, from `<title>.toml` or
+    /// `ComicInfo.xml`
+    pub reading_direction: Option<ReadingDirection>,
+    pub layout: Option<ReadingLayout>,
+    pub zoom: Option<ReadingZoom>,
+    /// Hash of the title when its page hashes were last backfilled, so titles
+    /// whose pages can't be decoded aren't extracted again on every scan
+    #[serde(skip)]
+    pub backfilled_hash: Option<String>,
+}
+
+impl Model {
+    pub fn reader_settings(&self) -> ReaderSettings {
+        ReaderSettings {
+            reading_direction: self.reading_direction,
+            layout: self.layout,
+            zoom: self.zoom,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -41,6 +61,14 @@ pub enum Relation {
     Favorites,
     #[sea_orm(has_many = "super::progresses::Entity")]
     Progresses,
+    #[sea_orm(has_many = "super::reading_sessions::Entity")]
+    ReadingSessions,
+    #[sea_orm(has_many = "super::collections_titles::Entity")]
+    CollectionsTitles,
+    #[sea_orm(has_many = "super::ratings::Entity")]
+    Ratings,
+    #[sea_orm(has_many = "super::reader_preferences::Entity")]
+    ReaderPreferences,
 }
 
 impl Related<super::categories::Entity> for Entity {
@@ -84,4 +112,28 @@ impl Related<super::progresses::Entity> for Entity {
         Relation::Progresses.def()
     }
 }
+
+impl Related<super::reading_sessions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReadingSessions.def()
+    }
+}
+
+impl Related<super::collections_titles::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CollectionsTitles.def()
+    }
+}
+
+impl Related<super::ratings::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Ratings.def()
+    }
+}
+impl Related<super::reader_preferences::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReaderPreferences.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
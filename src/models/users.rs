@@ -19,6 +19,12 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        has_many = "super::api_keys::Entity",
+        from = "Column::Id",
+        to = "super::api_keys::Column::UserId"
+    )]
+    ApiKeys,
     #[sea_orm(
         has_many = "super::bookmarks::Entity",
         from = "Column::Id",
@@ -37,6 +43,48 @@ pub enum Relation {
         to = "super::favorites::Column::UserId"
     )]
     Favorites,
+    #[sea_orm(
+        has_many = "super::reading_sessions::Entity",
+        from = "Column::Id",
+        to = "super::reading_sessions::Column::UserId"
+    )]
+    ReadingSessions,
+    #[sea_orm(
+        has_many = "super::collections::Entity",
+        from = "Column::Id",
+        to = "super::collections::Column::UserId"
+    )]
+    Collections,
+    #[sea_orm(
+        has_many = "super::ratings::Entity",
+        from = "Column::Id",
+        to = "super::ratings::Column::UserId"
+    )]
+    Ratings,
+    #[sea_orm(
+        has_many = "super::smart_collections::Entity",
+        from = "Column::Id",
+        to = "super::smart_collections::Column::UserId"
+    )]
+    SmartCollections,
+    #[sea_orm(
+        has_many = "super::ssim_feedbacks::Entity",
+        from = "Column::Id",
+        to = "super::ssim_feedbacks::Column::UserId"
+    )]
+    SsimFeedbacks,
+    #[sea_orm(
+        has_many = "super::reader_preferences::Entity",
+        from = "Column::Id",
+        to = "super::reader_preferences::Column::UserId"
+    )]
+    ReaderPreferences,
+}
+
+impl Related<super::api_keys::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiKeys.def()
+    }
 }
 
 impl Related<super::bookmarks::Entity> for Entity {
@@ -57,4 +105,40 @@ impl Related<super::favorites::Entity> for Entity {
     }
 }
 
+impl Related<super::reading_sessions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReadingSessions.def()
+    }
+}
+
+impl Related<super::collections::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Collections.def()
+    }
+}
+
+impl Related<super::ratings::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Ratings.def()
+    }
+}
+
+impl Related<super::smart_collections::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SmartCollections.def()
+    }
+}
+
+impl Related<super::ssim_feedbacks::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SsimFeedbacks.def()
+    }
+}
+
+impl Related<super::reader_preferences::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReaderPreferences.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
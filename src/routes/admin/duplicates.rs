@@ -0,0 +1,282 @@
+use crate::{
+    constants::duplicate_confidence, livescan::perceptual_hash::visual_similarity,
+    models::prelude::*, routes::ErrRsp, AppState,
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Arc,
+};
+use utoipa::{IntoParams, ToSchema};
+
+/// Pages at least this visually similar count as the same page.
+const SAME_PAGE_SIMILARITY: f32 = 0.85;
+/// Titles whose page counts are further apart than this aren't compared.
+const MIN_PAGE_COUNT_RATIO: f32 = 0.8;
+/// Hashes shared by more titles than this, like a blank page or a scanlation
+/// group's credits, don't make titles candidates.
+const MAX_SHARED_HASH_TITLES: usize = 50;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DuplicatesQuery {
+    /// From 0.0 to 1.0, 0.8 by default
+    pub min_confidence: Option<f32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DuplicateTitle {
+    pub id: String,
+    pub title: String,
+    pub category_id: String,
+    pub path: String,
+    pub page_count: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DuplicateGroup {
+    /// The lowest confidence of the pairs linking the group, from 0.0 to 1.0
+    pub confidence: f32,
+    pub titles: Vec<DuplicateTitle>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DuplicatesResponseBody {
+    pub data: Vec<DuplicateGroup>,
+}
+
+#[derive(Default)]
+struct TitlePages {
+    content_hashes: HashSet<String>,
+    /// `(dhash, phash)` of the image pages, by path
+    perceptual_hashes: Vec<(i64, i64)>,
+    page_count: usize,
+}
+
+/// How likely two titles are the same book, from 0.0 to 1.0: the share of
+/// identical pages, or of visually identical pages in the same order if
+/// they were re-encoded.
+fn confidence(a: &TitlePages, b: &TitlePages) -> f32 {
+    let content = match a.content_hashes.len().max(b.content_hashes.len()) {
+        0 => 0.0,
+        most => a.content_hashes.intersection(&b.content_hashes).count() as f32 / most as f32,
+    };
+
+    let visual = match a.perceptual_hashes.len().max(b.perceptual_hashes.len()) {
+        0 => 0.0,
+        most => {
+            let same = a
+                .perceptual_hashes
+                .iter()
+                .zip(&b.perceptual_hashes)
+                .filter(|(page_a, page_b)| {
+                    visual_similarity(**page_a, **page_b) >= SAME_PAGE_SIMILARITY
+                })
+                .count();
+            same as f32 / most as f32
+        }
+    };
+
+    content.max(visual)
+}
+
+fn page_count_ratio(a: &TitlePages, b: &TitlePages) -> f32 {
+    match a.page_count.max(b.page_count) {
+        0 => 0.0,
+        most => a.page_count.min(b.page_count) as f32 / most as f32,
+    }
+}
+
+fn find_root(parents: &mut HashMap<String, String>, id: &str) -> String {
+    let parent = parents.get(id).cloned().unwrap_or_else(|| id.to_string());
+    if parent == id {
+        return parent;
+    }
+    let root = find_root(parents, &parent);
+    parents.insert(id.to_string(), root.clone());
+    root
+}
+
+/// Group titles that are likely the same book, like re-zipped or
+/// re-compressed copies, for an admin to resolve.
+///
+/// Titles are compared when they share a page or a cover that looks the same,
+/// and have about the same page count.
+#[utoipa::path(get, path = "/api/admin/duplicates", params(DuplicatesQuery), responses(
+    (status = 200, description = "Fetch duplicates successful", body = DuplicatesResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 403, description = "Not an admin", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_duplicates(
+    State(data): State<Arc<AppState>>,
+    Query(query): Query<DuplicatesQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let min_confidence = query
+        .min_confidence
+        .unwrap_or(duplicate_confidence())
+        .clamp(0.0, 1.0);
+
+    let titles = Titles::find()
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .map(|title| (title.id.clone(), title))
+        .collect::<HashMap<_, _>>();
+
+    let mut page_models = Pages::find().all(&data.db).await.map_err(ErrRsp::db)?;
+    page_models.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut title_pages: HashMap<String, TitlePages> = HashMap::new();
+    for page in page_models {
+        // directories are listed too
+        if page.path.ends_with('/') {
+            continue;
+        }
+        let entry = title_pages.entry(page.title_id).or_default();
+        entry.page_count += 1;
+        if let Some(content_hash) = page.content_hash {
+            entry.content_hashes.insert(content_hash);
+        }
+        if let (Some(dhash), Some(phash)) = (page.dhash, page.phash) {
+            entry.perceptual_hashes.push((dhash, phash));
+        }
+    }
+
+    // candidates share an identical page, a page with the same dhash, or a
+    // cover that looks the same
+    let mut postings: HashMap<String, BTreeSet<&str>> = HashMap::new();
+    for (title_id, pages) in &title_pages {
+        for content_hash in &pages.content_hashes {
+            postings
+                .entry(format!("content {}", content_hash))
+                .or_default()
+                .insert(title_id);
+        }
+        for (dhash, _) in &pages.perceptual_hashes {
+            // plain colors all hash the same
+            if *dhash != 0 && *dhash != -1 {
+                postings
+                    .entry(format!("dhash {}", dhash))
+                    .or_default()
+                    .insert(title_id);
+            }
+        }
+    }
+
+    let mut candidates: BTreeSet<(&str, &str)> = BTreeSet::new();
+    for title_ids in postings.values() {
+        if title_ids.len() > MAX_SHARED_HASH_TITLES {
+            continue;
+        }
+        for (i, title_id_a) in title_ids.iter().enumerate() {
+            for title_id_b in title_ids.iter().skip(i + 1) {
+                candidates.insert((title_id_a, title_id_b));
+            }
+        }
+    }
+
+    let covers = Thumbnails::find()
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .filter(|thumbnail| titles.contains_key(&thumbnail.id))
+        .filter_map(|thumbnail| Some((thumbnail.id, (thumbnail.dhash?, thumbnail.phash?))))
+        .collect::<Vec<_>>();
+    for (i, (title_id_a, cover_a)) in covers.iter().enumerate() {
+        for (title_id_b, cover_b) in covers.iter().skip(i + 1) {
+            if visual_similarity(*cover_a, *cover_b) >= SAME_PAGE_SIMILARITY {
+                let pair = match title_id_a < title_id_b {
+                    true => (title_id_a.as_str(), title_id_b.as_str()),
+                    false => (title_id_b.as_str(), title_id_a.as_str()),
+                };
+                candidates.insert(pair);
+            }
+        }
+    }
+
+    let mut parents: HashMap<String, String> = HashMap::new();
+    let mut confidences: Vec<(String, f32)> = vec![];
+    for (title_id_a, title_id_b) in candidates {
+        let (Some(pages_a), Some(pages_b)) =
+            (title_pages.get(title_id_a), title_pages.get(title_id_b))
+        else {
+            continue;
+        };
+        if page_count_ratio(pages_a, pages_b) < MIN_PAGE_COUNT_RATIO {
+            continue;
+        }
+        let confidence = confidence(pages_a, pages_b);
+        if confidence < min_confidence {
+            continue;
+        }
+
+        for title_id in [title_id_a, title_id_b] {
+            parents
+                .entry(title_id.to_string())
+                .or_insert_with(|| title_id.to_string());
+        }
+        let root_a = find_root(&mut parents, title_id_a);
+        let root_b = find_root(&mut parents, title_id_b);
+        if root_a != root_b {
+            parents.insert(root_b, root_a);
+        }
+        confidences.push((title_id_a.to_string(), confidence));
+    }
+
+    let mut groups: HashMap<String, (f32, BTreeSet<String>)> = HashMap::new();
+    for title_id in parents.keys().cloned().collect::<Vec<_>>() {
+        let root = find_root(&mut parents, &title_id);
+        groups
+            .entry(root)
+            .or_insert((1.0, BTreeSet::new()))
+            .1
+            .insert(title_id);
+    }
+    for (title_id, confidence) in confidences {
+        let root = find_root(&mut parents, &title_id);
+        if let Some((group_confidence, _)) = groups.get_mut(&root) {
+            *group_confidence = group_confidence.min(confidence);
+        }
+    }
+
+    let mut resp_data = groups
+        .into_values()
+        .map(|(confidence, title_ids)| DuplicateGroup {
+            confidence,
+            titles: title_ids
+                .into_iter()
+                .filter_map(|title_id| {
+                    let title = titles.get(&title_id)?;
+                    Some(DuplicateTitle {
+                        page_count: title_pages
+                            .get(&title_id)
+                            .map_or(0, |pages| pages.page_count as u64),
+                        id: title_id,
+                        title: title.title.clone(),
+                        category_id: title.category_id.clone(),
+                        path: title.path.clone(),
+                    })
+                })
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    resp_data.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then_with(|| b.titles.len().cmp(&a.titles.len()))
+    });
+
+    Ok((
+        StatusCode::OK,
+        Json(DuplicatesResponseBody { data: resp_data }),
+    ))
+}
@@ -0,0 +1,5 @@
+mod duplicates;
+mod ssim_feedback;
+
+pub use duplicates::*;
+pub use ssim_feedback::*;
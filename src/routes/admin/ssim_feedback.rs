@@ -0,0 +1,284 @@
+use crate::{
+    constants::similarity_threshold,
+    models::prelude::*,
+    routes::{find_ssim, ErrRsp},
+    AppState,
+};
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
+use utoipa::ToSchema;
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SsimThresholdStats {
+    /// Pairs scoring at least this are predicted similar
+    pub threshold: f32,
+    pub true_positive: u64,
+    pub false_positive: u64,
+    pub true_negative: u64,
+    pub false_negative: u64,
+    pub precision: Option<f32>,
+    pub recall: Option<f32>,
+    pub accuracy: Option<f32>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SsimReportResponseBody {
+    /// Number of answers
+    pub judgements: u64,
+    /// Number of distinct pairs answered
+    pub pairs: u64,
+    /// Number of users who answered
+    pub users: u64,
+    /// Answers saying the pair is similar
+    pub similar: u64,
+    /// Pearson correlation between the score and the answers, from -1.0 to 1.0
+    pub correlation: Option<f32>,
+    /// Average score of the pairs answered similar
+    pub mean_ssim_similar: Option<f32>,
+    /// Average score of the pairs answered not similar
+    pub mean_ssim_not_similar: Option<f32>,
+    pub thresholds: Vec<SsimThresholdStats>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SsimDatasetEntry {
+    pub title_id_a: String,
+    pub title_a: String,
+    pub description_a: Option<String>,
+    pub title_id_b: String,
+    pub title_b: String,
+    pub description_b: Option<String>,
+    /// Current score, from 0.0 to 1.0
+    pub ssim: f32,
+    pub similar_votes: u64,
+    pub not_similar_votes: u64,
+    /// Share of the answers saying the pair is similar, from 0.0 to 1.0
+    pub label: f32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SsimDatasetResponseBody {
+    pub data: Vec<SsimDatasetEntry>,
+}
+
+/// Every answer with the current score of its pair, or the score when it was
+/// answered if the pair can't be scored anymore.
+async fn scored_feedbacks(
+    db: &DatabaseConnection,
+) -> Result<Vec<(ssim_feedbacks::Model, f32)>, ErrRsp> {
+    let feedbacks = SsimFeedbacks::find().all(db).await.map_err(ErrRsp::db)?;
+
+    let mut scores: BTreeMap<(String, String), f32> = BTreeMap::new();
+    let mut scored = Vec::with_capacity(feedbacks.len());
+    for feedback in feedbacks {
+        let pair = (feedback.title_id_a.clone(), feedback.title_id_b.clone());
+        let ssim = match scores.get(&pair) {
+            Some(ssim) => *ssim,
+            None => {
+                let ssim = find_ssim(db, &pair.0, &pair.1)
+                    .await?
+                    .unwrap_or(feedback.ssim as f32 / 1000.0);
+                scores.insert(pair, ssim);
+                ssim
+            }
+        };
+        scored.push((feedback, ssim));
+    }
+    Ok(scored)
+}
+
+fn mean(values: &[f32]) -> Option<f32> {
+    match values.is_empty() {
+        true => None,
+        false => Some(values.iter().sum::<f32>() / values.len() as f32),
+    }
+}
+
+fn ratio(numerator: u64, denominator: u64) -> Option<f32> {
+    match denominator {
+        0 => None,
+        _ => Some(numerator as f32 / denominator as f32),
+    }
+}
+
+fn correlation(samples: &[(f32, f32)]) -> Option<f32> {
+    let n = samples.len() as f32;
+    let mean_x = samples.iter().map(|(x, _)| x).sum::<f32>() / n;
+    let mean_y = samples.iter().map(|(_, y)| y).sum::<f32>() / n;
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in samples {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    match variance_x > 0.0 && variance_y > 0.0 {
+        true => Some(covariance / (variance_x * variance_y).sqrt()),
+        false => None,
+    }
+}
+
+fn threshold_stats(threshold: f32, samples: &[(f32, bool)]) -> SsimThresholdStats {
+    let (mut true_positive, mut false_positive, mut true_negative, mut false_negative) =
+        (0, 0, 0, 0);
+    for (ssim, is_similar) in samples {
+        match (*ssim >= threshold, is_similar) {
+            (true, true) => true_positive += 1,
+            (true, false) => false_positive += 1,
+            (false, false) => true_negative += 1,
+            (false, true) => false_negative += 1,
+        }
+    }
+
+    SsimThresholdStats {
+        threshold,
+        true_positive,
+        false_positive,
+        true_negative,
+        false_negative,
+        precision: ratio(true_positive, true_positive + false_positive),
+        recall: ratio(true_positive, true_positive + false_negative),
+        accuracy: ratio(true_positive + true_negative, samples.len() as u64),
+    }
+}
+
+/// Compare the users' answers from `POST /api/utils/ssim_eval` to the scores.
+///
+/// The confusion matrices are for thresholds from 0.1 to 0.9 and the current
+/// default threshold.
+#[utoipa::path(get, path = "/api/admin/ssim_feedback/report", responses(
+    (status = 200, description = "Fetch report successful", body = SsimReportResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 403, description = "Not an admin", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_ssim_report(
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let scored = scored_feedbacks(&data.db).await?;
+
+    let samples = scored
+        .iter()
+        .map(|(feedback, ssim)| (*ssim, feedback.is_similar))
+        .collect::<Vec<_>>();
+    let pairs = scored
+        .iter()
+        .map(|(feedback, _)| (&feedback.title_id_a, &feedback.title_id_b))
+        .collect::<HashSet<_>>();
+    let users = scored
+        .iter()
+        .map(|(feedback, _)| &feedback.user_id)
+        .collect::<HashSet<_>>();
+
+    let (similar, not_similar): (Vec<_>, Vec<_>) =
+        samples.iter().partition(|(_, is_similar)| *is_similar);
+    let similar = similar
+        .into_iter()
+        .map(|(ssim, _)| ssim)
+        .collect::<Vec<_>>();
+    let not_similar = not_similar
+        .into_iter()
+        .map(|(ssim, _)| ssim)
+        .collect::<Vec<_>>();
+
+    let mut thresholds = (1..10).map(|i| i as f32 / 10.0).collect::<Vec<_>>();
+    if !thresholds.contains(&similarity_threshold()) {
+        thresholds.push(similarity_threshold());
+        thresholds.sort_by(f32::total_cmp);
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(SsimReportResponseBody {
+            judgements: samples.len() as u64,
+            pairs: pairs.len() as u64,
+            users: users.len() as u64,
+            similar: similar.len() as u64,
+            correlation: correlation(
+                &samples
+                    .iter()
+                    .map(|(ssim, is_similar)| (*ssim, *is_similar as u8 as f32))
+                    .collect::<Vec<_>>(),
+            ),
+            mean_ssim_similar: mean(&similar),
+            mean_ssim_not_similar: mean(&not_similar),
+            thresholds: thresholds
+                .into_iter()
+                .map(|threshold| threshold_stats(threshold, &samples))
+                .collect(),
+        }),
+    ))
+}
+
+/// Export the answered pairs as a labelled dataset, one entry per pair.
+#[utoipa::path(get, path = "/api/admin/ssim_feedback/export", responses(
+    (status = 200, description = "Export successful", body = SsimDatasetResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 403, description = "Not an admin", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_ssim_export(
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let scored = scored_feedbacks(&data.db).await?;
+
+    let mut votes: BTreeMap<(String, String), (f32, u64, u64)> = BTreeMap::new();
+    for (feedback, ssim) in scored {
+        let (_, similar_votes, not_similar_votes) = votes
+            .entry((feedback.title_id_a, feedback.title_id_b))
+            .or_insert((ssim, 0, 0));
+        match feedback.is_similar {
+            true => *similar_votes += 1,
+            false => *not_similar_votes += 1,
+        }
+    }
+
+    let mut entries = Vec::with_capacity(votes.len());
+    for ((title_id_a, title_id_b), (ssim, similar_votes, not_similar_votes)) in votes {
+        let title_a = Titles::find_by_id(&title_id_a)
+            .one(&data.db)
+            .await
+            .map_err(ErrRsp::db)?;
+        let title_b = Titles::find_by_id(&title_id_b)
+            .one(&data.db)
+            .await
+            .map_err(ErrRsp::db)?;
+        let (Some(title_a), Some(title_b)) = (title_a, title_b) else {
+            continue;
+        };
+
+        entries.push(SsimDatasetEntry {
+            title_id_a,
+            title_a: title_a.title,
+            description_a: title_a.description,
+            title_id_b,
+            title_b: title_b.title,
+            description_b: title_b.description,
+            ssim,
+            similar_votes,
+            not_similar_votes,
+            label: similar_votes as f32 / (similar_votes + not_similar_votes) as f32,
+        });
+    }
+
+    Ok((
+        StatusCode::OK,
+        [(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"ssim_feedback.json\"",
+        )],
+        Json(SsimDatasetResponseBody { data: entries }),
+    ))
+}
@@ -56,6 +56,7 @@ pub async fn post_login(
         exp,
         iat,
         purpose: None,
+        fingerprint: None,
     };
 
     let token = encode(
@@ -1,6 +1,6 @@
 use crate::{
     models::prelude::*,
-    routes::{ErrRsp, GenericRsp},
+    routes::{check_pass_strength, ErrRsp, GenericRsp},
     AppState,
 };
 use argon2::{password_hash::SaltString, Argon2, PasswordHasher};
@@ -45,17 +45,7 @@ pub async fn post_register(
         ));
     }
 
-    let password = &query.password;
-    let has_uppercase = password.chars().any(|c| c.is_uppercase());
-    let has_lowercase = password.chars().any(|c| c.is_lowercase());
-    let has_numeric = password.chars().any(|c| c.is_numeric());
-    let has_special = password.chars().any(|c| c.is_ascii_punctuation());
-    let has_valid_length = password.len() >= 8 && password.len() <= 100;
-    if !(has_uppercase && has_lowercase && has_numeric && has_special && has_valid_length) {
-        return Err(ErrRsp::bad_request(
-            "Password must be between 8 and 100 characters long and contain at least one uppercase letter, one lowercase letter, one number and one special character.",
-        ));
-    }
+    check_pass_strength(&query.password)?;
 
     let salt = SaltString::generate(&mut OsRng);
     let hashed_password = Argon2::default()
@@ -0,0 +1,526 @@
+use super::{zip_stream, ZipStreamEntry, ZipStreamSource};
+use crate::{
+    constants::{cbz_cache_lifetime, cbz_cache_size},
+    models::prelude::*,
+    routes::{
+        find_ordered_pages, find_tag_names, find_visible_collection, percent_encode, release_date,
+        xml_escape, ErrRsp,
+    },
+    AppState,
+};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use chrono::Datelike;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, SeekFrom, Write},
+    path::{Path as FilePath, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+use utoipa::{IntoParams, ToSchema};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadFormat {
+    /// The archive as it is in the library
+    #[default]
+    Original,
+    /// Only the pages, in reading order, with a `ComicInfo.xml`
+    Cbz,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DownloadQuery {
+    /// `original` by default
+    pub format: Option<DownloadFormat>,
+}
+
+/// `attachment` with an ASCII fallback for the clients that don't read
+/// `filename*`.
+fn content_disposition(file_name: &str) -> HeaderValue {
+    let fallback = file_name
+        .chars()
+        .map(
+            |c| match c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect::<String>();
+    HeaderValue::from_str(&format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback,
+        percent_encode(file_name)
+    ))
+    .unwrap_or_else(|_| HeaderValue::from_static("attachment"))
+}
+
+/// Make a title usable as a file name in a zip or a download.
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Parse the first range of a `Range` header for a `len` bytes file, as an
+/// inclusive `(start, end)`. `None` if it can't be satisfied.
+fn parse_range(value: &str, len: u64) -> Option<(u64, u64)> {
+    let range = value.strip_prefix("bytes=")?.split(',').next()?.trim();
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", "") => return None,
+        ("", suffix) => {
+            let suffix = suffix.parse::<u64>().ok().filter(|suffix| *suffix > 0)?;
+            (len.saturating_sub(suffix), len.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)?),
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<u64>().ok()?.min(len.checked_sub(1)?),
+        ),
+    };
+    (start <= end && start < len).then_some((start, end))
+}
+
+/// Stream a file, or the part of it asked for with a `Range` header.
+pub async fn file_response(
+    path: &FilePath,
+    file_name: &str,
+    content_type: &str,
+    headers: &HeaderMap,
+) -> Result<Response, ErrRsp> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| ErrRsp::internal(format!("Read file error: {}", e)))?;
+    let len = file
+        .metadata()
+        .await
+        .map_err(|e| ErrRsp::internal(format!("Read file error: {}", e)))?
+        .len();
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|range| range.to_str().ok());
+    let (status, start, end) = match range {
+        None => (StatusCode::OK, 0, len.saturating_sub(1)),
+        Some(range) => match parse_range(range, len) {
+            Some((start, end)) => (StatusCode::PARTIAL_CONTENT, start, end),
+            None => {
+                return Ok((
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    [(header::CONTENT_RANGE, format!("bytes */{}", len))],
+                )
+                    .into_response())
+            }
+        },
+    };
+    let content_length = match len {
+        0 => 0,
+        _ => end - start + 1,
+    };
+
+    file.seek(SeekFrom::Start(start))
+        .await
+        .map_err(|e| ErrRsp::internal(format!("Read file error: {}", e)))?;
+    let body = Body::from_stream(ReaderStream::new(file.take(content_length)));
+
+    let mut response = (
+        status,
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_LENGTH, content_length.to_string()),
+            (header::ACCEPT_RANGES, "bytes".to_string()),
+        ],
+        body,
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(header::CONTENT_DISPOSITION, content_disposition(file_name));
+    if status == StatusCode::PARTIAL_CONTENT {
+        if let Ok(content_range) =
+            HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, len))
+        {
+            response
+                .headers_mut()
+                .insert(header::CONTENT_RANGE, content_range);
+        }
+    }
+
+    Ok(response)
+}
+
+fn comic_info(title: &titles::Model, tags: &[String], page_count: usize) -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+        "<ComicInfo xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\"",
+        " xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\">\n"
+    ));
+    let mut element = |name: &str, value: &str| {
+        xml.push_str(&format!("  <{0}>{1}</{0}>\n", name, xml_escape(value)));
+    };
+    element("Title", &title.title);
+    element("Series", &title.title);
+    if let Some(description) = &title.description {
+        element("Summary", description);
+    }
+    if let Some(author) = &title.author {
+        element("Writer", author);
+    }
+    if let Some(date) = release_date(title.release.as_deref()) {
+        element("Year", &date.year().to_string());
+        element("Month", &date.month().to_string());
+        element("Day", &date.day().to_string());
+    }
+    if !tags.is_empty() {
+        element("Tags", &tags.join(","));
+    }
+    element("PageCount", &page_count.to_string());
+    xml.push_str("  <Pages>\n    <Page Image=\"0\" Type=\"FrontCover\" />\n  </Pages>\n");
+    xml.push_str("</ComicInfo>\n");
+    xml
+}
+
+/// Delete the repacks not downloaded for `cbz_cache_lifetime`, then the least
+/// recently downloaded ones until the rest fit in `cbz_cache_size`. `keep`
+/// is about to be sent, it always stays.
+async fn prune_cbz_cache(cache_dir: &FilePath, keep: &FilePath) {
+    let Ok(mut cached) = tokio::fs::read_dir(cache_dir).await else {
+        return;
+    };
+    let lifetime = cbz_cache_lifetime().to_std().unwrap_or_default();
+    let mut repacks = vec![];
+    while let Ok(Some(entry)) = cached.next_entry().await {
+        let path = entry.path();
+        if path == keep {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+        let Ok(modified) = metadata.modified() else {
+            continue;
+        };
+        if modified.elapsed().unwrap_or_default() > lifetime {
+            let _ = tokio::fs::remove_file(&path).await;
+        } else if path.extension().is_some_and(|extension| extension == "cbz") {
+            // the parts are still being written
+            repacks.push((modified, metadata.len(), path));
+        }
+    }
+
+    let keep_size = tokio::fs::metadata(keep)
+        .await
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    let mut size = keep_size + repacks.iter().map(|(_, len, _)| len).sum::<u64>();
+    repacks.sort_unstable();
+    for (_, len, path) in repacks {
+        if size <= cbz_cache_size() {
+            break;
+        }
+        if tokio::fs::remove_file(&path).await.is_ok() {
+            size -= len;
+        }
+    }
+}
+
+/// Repack a title as a CBZ of its pages in reading order, with its metadata in
+/// a `ComicInfo.xml`. Repacks are kept in the temp directory until the title
+/// changes or the cache gets too old or too large, see `prune_cbz_cache`.
+async fn repack_cbz(data: &AppState, title: &titles::Model) -> Result<PathBuf, ErrRsp> {
+    let cache_dir = PathBuf::from(&data.env.temp_path).join("cbz");
+    let path = cache_dir.join(format!("{}-{}.cbz", title.id, title.hash));
+    if let Ok(cached) = tokio::fs::OpenOptions::new().append(true).open(&path).await {
+        // the modification time is when it was last downloaded
+        let _ = cached.into_std().await.set_modified(SystemTime::now());
+        return Ok(path);
+    }
+
+    // only the images, not the folders nor the archive's own ComicInfo.xml
+    let pages = find_ordered_pages(&data.db, &title.id)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .map(|page| page.path)
+        .collect::<Vec<_>>();
+    let tags = find_tag_names(&data.db, &title.id).await?;
+    let comic_info = comic_info(title, &tags, pages.len());
+
+    tokio::fs::create_dir_all(&cache_dir)
+        .await
+        .map_err(|e| ErrRsp::internal(format!("Create cache error: {}", e)))?;
+    let source = PathBuf::from(&title.path);
+    let part = cache_dir.join(format!("{}.part", uuid::Uuid::new_v4()));
+    let part_path = part.clone();
+    let written = tokio::task::spawn_blocking(move || -> zip::result::ZipResult<()> {
+        let mut archive = ZipArchive::new(File::open(source)?)?;
+        let mut writer = ZipWriter::new(File::create(&part_path)?);
+        let width = pages.len().to_string().len().max(4);
+        for (index, page) in pages.iter().enumerate() {
+            let extension = FilePath::new(page)
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+                .unwrap_or_default();
+            let name = format!("{:0width$}.{}", index, extension, width = width);
+            writer.raw_copy_file_rename(archive.by_name(page)?, name)?;
+        }
+        writer.start_file(
+            "ComicInfo.xml",
+            FileOptions::default().compression_method(CompressionMethod::Deflated),
+        )?;
+        writer.write_all(comic_info.as_bytes())?;
+        writer.finish()?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| ErrRsp::internal(format!("Repack error: {}", e)))?;
+    if let Err(e) = written {
+        let _ = tokio::fs::remove_file(&part).await;
+        return Err(ErrRsp::internal(format!("Repack error: {}", e)));
+    }
+
+    // older repacks of the title are stale now, the current one may have been
+    // renamed in by a concurrent request already
+    if let Ok(mut cached) = tokio::fs::read_dir(&cache_dir).await {
+        let prefix = format!("{}-", title.id);
+        while let Ok(Some(entry)) = cached.next_entry().await {
+            if entry.path() != path && entry.file_name().to_string_lossy().starts_with(&prefix) {
+                let _ = tokio::fs::remove_file(entry.path()).await;
+            }
+        }
+    }
+    tokio::fs::rename(&part, &path)
+        .await
+        .map_err(|e| ErrRsp::internal(format!("Repack error: {}", e)))?;
+    prune_cbz_cache(&cache_dir, &path).await;
+
+    Ok(path)
+}
+
+/// Name of the file sent for a title in `format`.
+fn download_name(title: &titles::Model, format: DownloadFormat) -> String {
+    match format {
+        DownloadFormat::Original => FilePath::new(&title.path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| format!("{}.cbz", file_stem(&title.title))),
+        DownloadFormat::Cbz => format!("{}.cbz", file_stem(&title.title)),
+    }
+}
+
+/// The file to send for a title in `format`, repacked first if needed.
+async fn download_path(
+    data: &AppState,
+    title: &titles::Model,
+    format: DownloadFormat,
+) -> Result<PathBuf, ErrRsp> {
+    match format {
+        DownloadFormat::Original => Ok(PathBuf::from(&title.path)),
+        DownloadFormat::Cbz => repack_cbz(data, title).await,
+    }
+}
+
+/// Stream the archive of a title, see `get_title_download`.
+pub async fn title_download(
+    data: &AppState,
+    title: &titles::Model,
+    format: DownloadFormat,
+    headers: &HeaderMap,
+) -> Result<Response, ErrRsp> {
+    let path = download_path(data, title, format).await?;
+    let file_name = download_name(title, format);
+    let content_type = match file_name.to_ascii_lowercase().ends_with(".zip") {
+        true => "application/zip",
+        false => "application/vnd.comicbook+zip",
+    };
+    file_response(&path, &file_name, content_type, headers).await
+}
+
+/// Stream a zip of the archives of `titles`, named after them.
+///
+/// Titles are repacked as the stream gets to them, not before the response
+/// starts.
+async fn titles_zip(
+    data: Arc<AppState>,
+    titles: Vec<titles::Model>,
+    format: DownloadFormat,
+    zip_name: &str,
+) -> Result<Response, ErrRsp> {
+    let mut names = HashSet::new();
+    let mut entries = Vec::with_capacity(titles.len());
+    for title in titles {
+        let file_name = download_name(&title, format);
+        let extension = FilePath::new(&file_name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_string())
+            .unwrap_or_else(|| "cbz".to_string());
+        let stem = file_stem(&title.title);
+        let mut name = format!("{}.{}", stem, extension);
+        let mut copy = 1;
+        while !names.insert(name.clone()) {
+            copy += 1;
+            name = format!("{} ({}).{}", stem, copy, extension);
+        }
+        let data = data.clone();
+        let source: ZipStreamSource = Box::pin(async move {
+            download_path(&data, &title, format)
+                .await
+                .map_err(|e| io::Error::other(e.message().to_string()))
+        });
+        entries.push(ZipStreamEntry { name, source });
+    }
+
+    let mut response = (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/zip")],
+        zip_stream(entries),
+    )
+        .into_response();
+    response.headers_mut().insert(
+        header::CONTENT_DISPOSITION,
+        content_disposition(&format!("{}.zip", file_stem(zip_name))),
+    );
+    Ok(response)
+}
+
+/// Download the archive of a title, for offline reading.
+///
+/// `Range` requests are supported, to resume downloads.
+#[utoipa::path(get, path = "/api/file/title/{title_id}/download", params(DownloadQuery), responses(
+    (status = 200, description = "Fetch title archive successful", body = Vec<u8>),
+    (status = 206, description = "Fetch part of the title archive successful", body = Vec<u8>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Title not found", body = ErrorResponseBody),
+    (status = 416, description = "Range not satisfiable"),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_title_download(
+    State(data): State<Arc<AppState>>,
+    Path(title_id): Path<String>,
+    Query(query): Query<DownloadQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ErrRsp> {
+    let title = Titles::find_by_id(&title_id)
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("Title not found."))?;
+
+    title_download(&data, &title, query.format.unwrap_or_default(), &headers).await
+}
+
+/// Download the archives of every title of a category as one zip.
+#[utoipa::path(get, path = "/api/file/category/{category_id}/download", params(DownloadQuery), responses(
+    (status = 200, description = "Fetch category zip successful", body = Vec<u8>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Category not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_category_download(
+    State(data): State<Arc<AppState>>,
+    Path(category_id): Path<String>,
+    Query(query): Query<DownloadQuery>,
+) -> Result<Response, ErrRsp> {
+    let category = Categories::find_by_id(&category_id)
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("Category not found."))?;
+    let titles = Titles::find()
+        .filter(titles::Column::CategoryId.eq(&category.id))
+        .order_by_asc(titles::Column::Title)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    titles_zip(
+        data,
+        titles,
+        query.format.unwrap_or_default(),
+        &category.name,
+    )
+    .await
+}
+
+/// Download the archives of every title of a collection as one zip.
+#[utoipa::path(get, path = "/api/file/collection/{collection_id}/download", params(DownloadQuery), responses(
+    (status = 200, description = "Fetch collection zip successful", body = Vec<u8>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Collection not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_collection_download(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(collection_id): Path<String>,
+    Query(query): Query<DownloadQuery>,
+) -> Result<Response, ErrRsp> {
+    let collection = find_visible_collection(&data.db, &user.id, &collection_id).await?;
+    let titles = CollectionsTitles::find()
+        .find_also_related(Titles)
+        .filter(collections_titles::Column::CollectionId.eq(&collection.id))
+        .order_by_asc(collections_titles::Column::Position)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .filter_map(|(_, title)| title)
+        .collect();
+
+    titles_zip(
+        data,
+        titles,
+        query.format.unwrap_or_default(),
+        &collection.name,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ranges() {
+        let cases = [
+            ("bytes=0-99", 1000, Some((0, 99))),
+            ("bytes=100-", 1000, Some((100, 999))),
+            ("bytes=-100", 1000, Some((900, 999))),
+            ("bytes=-2000", 1000, Some((0, 999))),
+            ("bytes=900-2000", 1000, Some((900, 999))),
+            ("bytes=0-0, 5-9", 1000, Some((0, 0))),
+            ("bytes=1000-", 1000, None),
+            ("bytes=1000-1100", 1000, None),
+            ("bytes=500-100", 1000, None),
+            ("bytes=-0", 1000, None),
+            ("bytes=-", 1000, None),
+            ("bytes=a-b", 1000, None),
+            ("items=0-99", 1000, None),
+            ("bytes=0-", 0, None),
+            ("bytes=-10", 0, None),
+            ("bytes=0-0", 0, None),
+        ];
+        for (value, len, expected) in cases {
+            assert_eq!(parse_range(value, len), expected, "{} of {}", value, len);
+        }
+    }
+}
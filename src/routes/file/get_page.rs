@@ -1,24 +1,89 @@
-use std::{fs::File, io::Read, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, fs::File, io::Read, path::PathBuf, sync::Arc};
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{header, StatusCode},
     response::IntoResponse,
+    Extension,
 };
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::Deserialize;
+use utoipa::IntoParams;
 use zip::ZipArchive;
 
-use crate::{models::prelude::*, routes::ErrRsp, AppState};
+use super::{
+    cache_tiles, cached_tile, join_pages, render_tiles, split_page, tile_cache_prefix, PageHalf,
+    TilePart,
+};
+use crate::{
+    models::{prelude::*, reader::ReadingDirection},
+    routes::ErrRsp,
+    AppState,
+};
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct PageQuery {
+    /// Only this half of the page, for double spreads
+    pub half: Option<PageHalf>,
+    /// Id of the next page, to get both side by side
+    pub join: Option<String>,
+    /// Which side the next page goes when joining, the way the user reads the
+    /// title by default, like in `get_title`
+    pub direction: Option<ReadingDirection>,
+    /// This tile of the title's strip instead, for webtoons, see `get_title`
+    pub tile: Option<u32>,
+}
+
+fn read_from_zip(zip: &mut ZipArchive<File>, path: &str) -> Result<Vec<u8>, ErrRsp> {
+    let mut file = zip
+        .by_name(path)
+        .map_err(|e| ErrRsp::internal(format!("Read page from zip error: {}", e)))?;
+
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)
+        .map_err(|e| ErrRsp::internal(format!("Read page error: {}", e)))?;
+    Ok(buffer)
+}
 
-#[utoipa::path(get, path = "/api/file/page/{page_id}", responses(
+/// How the title is read, the user's preferences over the title's.
+async fn reading_direction(
+    data: &Arc<AppState>,
+    user: Option<&Extension<users::Model>>,
+    title_in_db: &titles::Model,
+) -> Result<ReadingDirection, ErrRsp> {
+    let user_reader = match user {
+        Some(Extension(user)) => ReaderPreferences::find()
+            .filter(reader_preferences::Column::UserId.eq(&user.id))
+            .filter(reader_preferences::Column::TitleId.eq(&title_in_db.id))
+            .one(&data.db)
+            .await
+            .map_err(ErrRsp::db)?
+            .map(|preference| preference.settings()),
+        None => None,
+    };
+    Ok(user_reader
+        .unwrap_or_default()
+        .or(title_in_db.reader_settings())
+        .resolve()
+        .reading_direction
+        .unwrap_or_default())
+}
+
+/// Get a page, or a half of it, or it and the next one side by side, see
+/// `SpreadMode`, or a tile of the strip of its title.
+#[utoipa::path(get, path = "/api/file/page/{page_id}", params(PageQuery), responses(
     (status = 200, description = "Fetch page successful.", body = Vec<u8>),
+    (status = 400, description = "The pages to join aren't of the same title", body = ErrorResponseBody),
     (status = 401, description = "Unauthorized", body = ErrorResponseBody),
-    (status = 404, description = "Page not found", body = ErrorResponseBody),
+    (status = 404, description = "Page or tile not found", body = ErrorResponseBody),
+    (status = 422, description = "The page can't be re-encoded", body = ErrorResponseBody),
     (status = 500, description = "Internal server error", body = ErrorResponseBody),
 ))]
 pub async fn get_page(
     State(data): State<Arc<AppState>>,
+    user: Option<Extension<users::Model>>,
     Path(page_id): Path<String>,
+    Query(query): Query<PageQuery>,
 ) -> Result<impl IntoResponse, ErrRsp> {
     let page_in_db = Pages::find()
         .filter(pages::Column::Id.contains(page_id))
@@ -27,6 +92,23 @@ pub async fn get_page(
         .map_err(ErrRsp::db)?
         .ok_or_else(|| ErrRsp::not_found("Page not found."))?;
 
+    let joined_page_in_db = match query.join {
+        Some(ref join) => {
+            let joined_page_in_db = Pages::find_by_id(join)
+                .one(&data.db)
+                .await
+                .map_err(ErrRsp::db)?
+                .ok_or_else(|| ErrRsp::not_found("Page not found."))?;
+            if joined_page_in_db.title_id != page_in_db.title_id {
+                return Err(ErrRsp::bad_request(
+                    "Only pages of the same title can be joined.",
+                ));
+            }
+            Some(joined_page_in_db)
+        }
+        None => None,
+    };
+
     let title_in_db = Titles::find()
         .filter(titles::Column::Id.contains(&page_in_db.title_id))
         .one(&data.db)
@@ -35,27 +117,122 @@ pub async fn get_page(
         .ok_or_else(|| ErrRsp::not_found("Title not found."))?;
 
     let mut zip = ZipArchive::new(
-        File::open(title_in_db.path)
+        File::open(&title_in_db.path)
             .map_err(|e| ErrRsp::internal(format!("Read title error: {}", e)))?,
     )
     .map_err(|e| ErrRsp::internal(format!("Read zip error: {}", e)))?;
 
-    let mut file = zip
-        .by_name(page_in_db.path.as_ref())
-        .map_err(|e| ErrRsp::internal(format!("Read page from zip error: {}", e)))?;
+    if let Some(tile) = query.tile {
+        let title_tiles = StripTiles::find()
+            .filter(strip_tiles::Column::TitleId.eq(&page_in_db.title_id))
+            .order_by_asc(strip_tiles::Column::Index)
+            .all(&data.db)
+            .await
+            .map_err(ErrRsp::db)?;
+        let cache_dir = PathBuf::from(&data.env.temp_path).join("tiles");
+        let cache_prefix = tile_cache_prefix(&title_in_db, &title_tiles);
+        if let Some((mime_type, buffer)) = cached_tile(&cache_dir, &cache_prefix, tile).await {
+            return Ok((StatusCode::OK, [(header::CONTENT_TYPE, mime_type)], buffer));
+        }
 
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)
-        .map_err(|e| ErrRsp::internal(format!("Read page error: {}", e)))?;
+        let tile_in_db = title_tiles
+            .iter()
+            .find(|tile_in_db| tile_in_db.index == tile)
+            .ok_or_else(|| ErrRsp::not_found("Tile not found."))?;
+        // the slices of a page are rendered together, so it's decoded once
+        let tiles_to_render = match tile_in_db.decode_segments().as_slice() {
+            [segment] => title_tiles
+                .iter()
+                .filter(|other| {
+                    matches!(other.decode_segments().as_slice(),
+                        [other_segment] if other_segment.page_id == segment.page_id)
+                })
+                .collect::<Vec<_>>(),
+            _ => vec![tile_in_db],
+        };
+
+        let segments = tiles_to_render
+            .iter()
+            .map(|tile_in_db| tile_in_db.decode_segments())
+            .collect::<Vec<_>>();
+        let paths = Pages::find()
+            .filter(pages::Column::Id.is_in(segments.iter().flatten().map(|s| &s.page_id)))
+            .all(&data.db)
+            .await
+            .map_err(ErrRsp::db)?
+            .into_iter()
+            .map(|page| (page.id, page.path))
+            .collect::<HashMap<_, _>>();
+        let mut buffers = HashMap::new();
+        for segment in segments.iter().flatten() {
+            if buffers.contains_key(&segment.page_id) {
+                continue;
+            }
+            let path = paths
+                .get(&segment.page_id)
+                .ok_or_else(|| ErrRsp::not_found("Page not found."))?;
+            buffers.insert(segment.page_id.clone(), read_from_zip(&mut zip, path)?);
+        }
+
+        let parts = segments
+            .into_iter()
+            .map(|segments| {
+                segments
+                    .into_iter()
+                    .map(|segment| TilePart {
+                        page_id: segment.page_id,
+                        top: segment.top,
+                        bottom: segment.bottom,
+                    })
+                    .collect()
+            })
+            .collect();
+        let width = tile_in_db.width;
+        let rendered = tokio::task::spawn_blocking(move || render_tiles(parts, &buffers, width))
+            .await
+            .map_err(|e| ErrRsp::internal(format!("Render tile error: {}", e)))??;
+        let rendered = tiles_to_render
+            .iter()
+            .zip(rendered)
+            .map(|(tile_in_db, (mime_type, buffer))| (tile_in_db.index, mime_type, buffer))
+            .collect::<Vec<_>>();
+        cache_tiles(&cache_dir, &title_in_db.id, &cache_prefix, &rendered).await;
+
+        let (_, mime_type, buffer) = rendered
+            .into_iter()
+            .find(|(index, _, _)| *index == tile)
+            .ok_or_else(|| ErrRsp::not_found("Tile not found."))?;
+        return Ok((StatusCode::OK, [(header::CONTENT_TYPE, mime_type)], buffer));
+    }
+
+    let buffer = read_from_zip(&mut zip, &page_in_db.path)?;
 
-    let mime_type = format!(
-        "image/{}",
-        PathBuf::from(page_in_db.path)
-            .extension()
-            .map(|s| s.to_str().unwrap_or(""))
-            .unwrap_or("")
-            .to_ascii_lowercase()
-    );
+    let (mime_type, buffer) = match (query.half, joined_page_in_db) {
+        (Some(half), _) => tokio::task::spawn_blocking(move || split_page(&buffer, half))
+            .await
+            .map_err(|e| ErrRsp::internal(format!("Split page error: {}", e)))??,
+        (None, Some(joined_page_in_db)) => {
+            let joined_buffer = read_from_zip(&mut zip, &joined_page_in_db.path)?;
+            let direction = match query.direction {
+                Some(direction) => direction,
+                None => reading_direction(&data, user.as_ref(), &title_in_db).await?,
+            };
+            tokio::task::spawn_blocking(move || join_pages(&buffer, &joined_buffer, direction))
+                .await
+                .map_err(|e| ErrRsp::internal(format!("Join pages error: {}", e)))??
+        }
+        (None, None) => {
+            let mime_type = format!(
+                "image/{}",
+                PathBuf::from(page_in_db.path)
+                    .extension()
+                    .map(|s| s.to_str().unwrap_or(""))
+                    .unwrap_or("")
+                    .to_ascii_lowercase()
+            );
+            (mime_type, buffer)
+        }
+    };
 
     Ok((StatusCode::OK, [(header::CONTENT_TYPE, mime_type)], buffer))
 }
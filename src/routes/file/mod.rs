@@ -1,5 +1,15 @@
+mod download;
 mod get_page;
+mod signed_pages;
+mod spreads;
+mod strips;
 mod thumbnail;
+mod zip_stream;
 
+pub use download::*;
 pub use get_page::*;
+pub use signed_pages::*;
+pub use spreads::*;
+pub use strips::*;
 pub use thumbnail::*;
+pub use zip_stream::*;
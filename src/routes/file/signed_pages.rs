@@ -0,0 +1,173 @@
+use super::{get_page, PageQuery};
+use crate::{
+    constants::{signed_page_lifetime, signed_pages_batch_size},
+    models::{auth::PageTokenClaims, prelude::*},
+    routes::{find_ordered_pages, ErrRsp},
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::TimeZone;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SignedPagesQuery {
+    /// Index of the first page, in reading order, 0 by default
+    pub from: Option<u64>,
+    /// How many pages, capped at 20, which is also the default
+    pub count: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SignedPage {
+    /// Index of the page, in reading order
+    pub index: u64,
+    pub id: String,
+    /// Works without auth until `expires_at`
+    pub url: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Size in bytes
+    pub size: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SignedPagesResponseBody {
+    pub data: Vec<SignedPage>,
+    /// Pages of the title
+    pub total: u64,
+    pub expires_at: String,
+}
+
+/// Expiry of the URLs signed now.
+///
+/// Rounded to the lifetime, so every batch in the same window signs the same
+/// URLs and clients can cache pages by URL.
+fn signed_page_expiry() -> chrono::DateTime<chrono::Utc> {
+    let lifetime = signed_page_lifetime().num_seconds();
+    let window = chrono::Utc::now().timestamp() / lifetime;
+    chrono::Utc
+        .timestamp_opt((window + 2) * lifetime, 0)
+        .single()
+        .unwrap_or_default()
+}
+
+/// Get signed, short-lived URLs of the pages `from..from + count` of a title,
+/// with their dimensions, to prefetch them.
+///
+/// The URLs are also in `Link: rel=preload` headers.
+#[utoipa::path(get, path = "/api/file/title/{title_id}/pages", params(SignedPagesQuery), responses(
+    (status = 200, description = "Sign pages successful", body = SignedPagesResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Title not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_signed_pages(
+    State(data): State<Arc<AppState>>,
+    Path(title_id): Path<String>,
+    Query(query): Query<SignedPagesQuery>,
+) -> Result<Response, ErrRsp> {
+    Titles::find_by_id(&title_id)
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("Title not found."))?;
+
+    let pages = find_ordered_pages(&data.db, &title_id)
+        .await
+        .map_err(ErrRsp::db)?;
+    let from = query.from.unwrap_or_default();
+    let count = query
+        .count
+        .unwrap_or(signed_pages_batch_size())
+        .min(signed_pages_batch_size());
+
+    let expires_at = signed_page_expiry();
+    let key = EncodingKey::from_secret(data.env.jwt_secret.as_ref());
+    let mut signed = Vec::new();
+    for (index, page) in pages
+        .iter()
+        .enumerate()
+        .skip(from as usize)
+        .take(count as usize)
+    {
+        let claims = PageTokenClaims {
+            page_id: page.id.clone(),
+            iat: (expires_at - signed_page_lifetime() * 2).timestamp() as usize,
+            exp: expires_at.timestamp() as usize,
+        };
+        let token = encode(&Header::default(), &claims, &key)
+            .map_err(|e| ErrRsp::internal(format!("Failed to sign page. JWT error: {}", e)))?;
+        signed.push(SignedPage {
+            index: index as u64,
+            id: page.id.clone(),
+            url: format!("{}/api/file/signed/{}", data.env.public_url, token),
+            width: page.width,
+            height: page.height,
+            size: page.size,
+        });
+    }
+
+    let links = signed
+        .iter()
+        .filter_map(|page| {
+            HeaderValue::from_str(&format!("<{}>; rel=preload; as=image", page.url)).ok()
+        })
+        .collect::<Vec<_>>();
+
+    let mut response = Json(SignedPagesResponseBody {
+        data: signed,
+        total: pages.len() as u64,
+        expires_at: expires_at.to_rfc3339(),
+    })
+    .into_response();
+    for link in links {
+        response.headers_mut().append(header::LINK, link);
+    }
+    Ok(response)
+}
+
+/// Get a page by a signed URL from `/api/file/title/{title_id}/pages`, no auth
+/// needed.
+#[utoipa::path(get, path = "/api/file/signed/{token}", responses(
+    (status = 200, description = "Fetch page successful.", body = Vec<u8>),
+    (status = 403, description = "Invalid or expired URL", body = ErrorResponseBody),
+    (status = 404, description = "Page not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_signed_page(
+    State(data): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Result<Response, ErrRsp> {
+    let claims = decode::<PageTokenClaims>(
+        &token,
+        &DecodingKey::from_secret(data.env.jwt_secret.as_ref()),
+        &Validation::default(),
+    )
+    .map_err(|_| ErrRsp::new(StatusCode::FORBIDDEN, "Invalid or expired URL."))?
+    .claims;
+
+    let mut response = get_page(
+        State(data),
+        None,
+        Path(claims.page_id),
+        Query(PageQuery::default()),
+    )
+    .await?
+    .into_response();
+    let max_age = (claims.exp as i64 - chrono::Utc::now().timestamp()).max(0);
+    if let Ok(cache_control) = HeaderValue::from_str(&format!("private, max-age={}", max_age)) {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, cache_control);
+    }
+    Ok(response)
+}
@@ -0,0 +1,208 @@
+use crate::{
+    constants::native_img_formats,
+    models::{prelude::*, reader::ReadingDirection},
+    routes::ErrRsp,
+};
+use axum::http::StatusCode;
+use image::{imageops::FilterType::Lanczos3, DynamicImage, GenericImage, ImageOutputFormat};
+use serde::{Deserialize, Serialize};
+use std::{io::Cursor, path::Path};
+use utoipa::ToSchema;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SpreadMode {
+    /// Pages as they are in the archive
+    #[default]
+    Original,
+    /// Double spreads as two pages
+    Split,
+    /// Two pages side by side, like an open book, the cover and double spreads
+    /// alone
+    Join,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PageHalf {
+    Left,
+    Right,
+}
+
+/// A page of the reader, maybe a half of a page or two of them.
+pub struct VirtualPage<'a> {
+    pub page: &'a pages::Model,
+    pub half: Option<PageHalf>,
+    /// Shown beside `page`, after it in reading order
+    pub joined_with: Option<&'a pages::Model>,
+}
+
+impl VirtualPage<'_> {
+    /// Dimensions once split or joined, the joined page is scaled to the
+    /// height of `page`.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        let (width, height) = (self.page.width?, self.page.height?);
+        match (self.half, self.joined_with) {
+            (Some(_), _) => Some((width / 2, height)),
+            (None, Some(joined)) => {
+                let (joined_width, joined_height) = (joined.width?, joined.height?);
+                let scaled_width =
+                    joined_width as u64 * height as u64 / joined_height.max(1) as u64;
+                Some((width + scaled_width as u32, height))
+            }
+            (None, None) => Some((width, height)),
+        }
+    }
+}
+
+/// Whether `image` can decode the page, so it can be split, joined or tiled.
+pub fn is_decodable(page: &pages::Model) -> bool {
+    Path::new(&page.path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|extension| native_img_formats().contains(&extension.as_str()))
+}
+
+/// Lay out the pages of a title, in reading order, for `mode`.
+///
+/// Pages in formats `image` can't decode, like JPEG XL, are left as they are.
+pub fn virtual_pages(
+    pages: &[pages::Model],
+    mode: SpreadMode,
+    direction: ReadingDirection,
+) -> Vec<VirtualPage<'_>> {
+    let single = |page| VirtualPage {
+        page,
+        half: None,
+        joined_with: None,
+    };
+    match mode {
+        SpreadMode::Original => pages.iter().map(single).collect(),
+        SpreadMode::Split => {
+            let halves = match direction {
+                ReadingDirection::LeftToRight => [PageHalf::Left, PageHalf::Right],
+                ReadingDirection::RightToLeft => [PageHalf::Right, PageHalf::Left],
+            };
+            pages
+                .iter()
+                .flat_map(
+                    |page| match page.double_spread == Some(true) && is_decodable(page) {
+                        true => halves
+                            .iter()
+                            .map(|half| VirtualPage {
+                                page,
+                                half: Some(*half),
+                                joined_with: None,
+                            })
+                            .collect::<Vec<_>>(),
+                        false => vec![single(page)],
+                    },
+                )
+                .collect()
+        }
+        SpreadMode::Join => {
+            // only the images can be joined, and only if they aren't spreads
+            // already and can be decoded
+            let joinable = |page: &pages::Model| {
+                page.width.is_some() && page.double_spread != Some(true) && is_decodable(page)
+            };
+            let mut virtual_pages = Vec::new();
+            let mut index = 0;
+            while index < pages.len() {
+                let page = &pages[index];
+                let next = pages.get(index + 1).filter(|next| joinable(next));
+                match next {
+                    // the cover is alone
+                    Some(next) if index > 0 && joinable(page) => {
+                        virtual_pages.push(VirtualPage {
+                            page,
+                            half: None,
+                            joined_with: Some(next),
+                        });
+                        index += 2;
+                    }
+                    _ => {
+                        virtual_pages.push(single(page));
+                        index += 1;
+                    }
+                }
+            }
+            virtual_pages
+        }
+    }
+}
+
+pub(super) fn decode(buffer: &[u8]) -> Result<DynamicImage, ErrRsp> {
+    image::load_from_memory(buffer).map_err(|e| {
+        ErrRsp::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("This page can't be re-encoded: {}", e),
+        )
+    })
+}
+
+/// Encode back to JPEG if it was one, to PNG otherwise.
+pub(super) fn encode(image: &DynamicImage, as_jpeg: bool) -> Result<(String, Vec<u8>), ErrRsp> {
+    let (mime_type, format) = match as_jpeg {
+        true => ("image/jpeg", ImageOutputFormat::Jpeg(90)),
+        false => ("image/png", ImageOutputFormat::Png),
+    };
+    let image = match as_jpeg {
+        true => DynamicImage::ImageRgb8(image.to_rgb8()),
+        false => image.clone(),
+    };
+    let mut buffer = Cursor::new(Vec::new());
+    image
+        .write_to(&mut buffer, format)
+        .map_err(|e| ErrRsp::internal(format!("Encode page error: {}", e)))?;
+    Ok((mime_type.to_string(), buffer.into_inner()))
+}
+
+pub(super) fn is_jpeg(buffer: &[u8]) -> bool {
+    matches!(image::guess_format(buffer), Ok(image::ImageFormat::Jpeg))
+}
+
+/// Crop `half` of a page, returning the MIME type and the encoded half.
+pub fn split_page(buffer: &[u8], half: PageHalf) -> Result<(String, Vec<u8>), ErrRsp> {
+    let image = decode(buffer)?;
+    let half_width = image.width() / 2;
+    let cropped = match half {
+        PageHalf::Left => image.crop_imm(0, 0, half_width, image.height()),
+        PageHalf::Right => {
+            image.crop_imm(half_width, 0, image.width() - half_width, image.height())
+        }
+    };
+    encode(&cropped, is_jpeg(buffer))
+}
+
+/// Put `second` beside `first`, on its left if reading right to left, scaled
+/// to the same height.
+pub fn join_pages(
+    first: &[u8],
+    second: &[u8],
+    direction: ReadingDirection,
+) -> Result<(String, Vec<u8>), ErrRsp> {
+    let first_image = decode(first)?;
+    let second_image = decode(second)?;
+    let height = first_image.height();
+    let second_image = match second_image.height() == height {
+        true => second_image,
+        false => {
+            let width =
+                second_image.width() as u64 * height as u64 / second_image.height().max(1) as u64;
+            second_image.resize_exact(width as u32, height, Lanczos3)
+        }
+    };
+
+    let (left, right) = match direction {
+        ReadingDirection::LeftToRight => (&first_image, &second_image),
+        ReadingDirection::RightToLeft => (&second_image, &first_image),
+    };
+    let mut joined = DynamicImage::new_rgba8(left.width() + right.width(), height);
+    let placed = joined
+        .copy_from(left, 0, 0)
+        .and_then(|_| joined.copy_from(right, left.width(), 0));
+    placed.map_err(|e| ErrRsp::internal(format!("Join pages error: {}", e)))?;
+
+    encode(&joined, is_jpeg(first) && is_jpeg(second))
+}
@@ -0,0 +1,130 @@
+use super::{decode, encode, is_jpeg};
+use crate::{models::prelude::*, routes::ErrRsp};
+use image::{imageops::FilterType::Lanczos3, DynamicImage, GenericImage};
+#[cfg(target_pointer_width = "64")]
+use murmur3::murmur3_x64_128 as murmur3_128;
+#[cfg(target_pointer_width = "32")]
+use murmur3::murmur3_x86_128 as murmur3_128;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    path::Path,
+};
+use tracing::warn;
+
+/// Rows of a page to put in a tile, see `strip_tiles::StripSegment`.
+pub struct TilePart {
+    pub page_id: String,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+/// Stack the parts of each tile top to bottom, scaled to `width`, returning
+/// the MIME type and the encoded tile of each.
+///
+/// A page is decoded once, however many of the tiles it's sliced into.
+pub fn render_tiles(
+    tiles: Vec<Vec<TilePart>>,
+    buffers: &HashMap<String, Vec<u8>>,
+    width: u32,
+) -> Result<Vec<(String, Vec<u8>)>, ErrRsp> {
+    let mut decoded = HashMap::new();
+    let mut rendered = Vec::with_capacity(tiles.len());
+    for parts in tiles {
+        let mut as_jpeg = true;
+        let mut rows = Vec::with_capacity(parts.len());
+        for part in parts {
+            let buffer = buffers
+                .get(&part.page_id)
+                .ok_or_else(|| ErrRsp::not_found("Page not found."))?;
+            as_jpeg &= is_jpeg(buffer);
+            let image: &DynamicImage = match decoded.entry(part.page_id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(decode(buffer)?),
+            };
+            let bottom = part.bottom.min(image.height());
+            let top = part.top.min(bottom);
+            let cropped = image.crop_imm(0, top, image.width(), bottom - top);
+            let height = (cropped.height() as f64 * width as f64 / cropped.width().max(1) as f64)
+                .round() as u32;
+            rows.push(match cropped.width() == width {
+                true => cropped,
+                false => cropped.resize_exact(width, height, Lanczos3),
+            });
+        }
+
+        let height = rows.iter().map(|row| row.height()).sum();
+        let mut tile = DynamicImage::new_rgba8(width, height);
+        let mut y = 0;
+        for row in rows.iter() {
+            tile.copy_from(row, 0, y)
+                .map_err(|e| ErrRsp::internal(format!("Render tile error: {}", e)))?;
+            y += row.height();
+        }
+        rendered.push(encode(&tile, as_jpeg)?);
+    }
+
+    Ok(rendered)
+}
+
+/// Prefix of the cached tiles of a title, they're stale once the title or the
+/// way its strip is cut changes.
+pub fn tile_cache_prefix(title: &titles::Model, tiles: &[strip_tiles::Model]) -> String {
+    let mut stored = String::new();
+    for tile in tiles {
+        stored.push_str(&format!(
+            "{} {} {} {}\n",
+            tile.index, tile.width, tile.height, tile.segments
+        ));
+    }
+    let tiles_hash = murmur3_128(&mut stored.as_bytes(), 0).unwrap_or_default();
+    format!("{}-{}-{:032x}-", title.id, title.hash, tiles_hash)
+}
+
+/// Tile `index` rendered before, with its MIME type.
+pub async fn cached_tile(cache_dir: &Path, prefix: &str, index: u32) -> Option<(String, Vec<u8>)> {
+    for extension in ["jpeg", "png"] {
+        let path = cache_dir.join(format!("{}{}.{}", prefix, index, extension));
+        if let Ok(buffer) = tokio::fs::read(path).await {
+            return Some((format!("image/{}", extension), buffer));
+        }
+    }
+    None
+}
+
+/// Keep rendered tiles for the next requests, and drop those of older scans of
+/// the title.
+pub async fn cache_tiles(
+    cache_dir: &Path,
+    title_id: &str,
+    prefix: &str,
+    tiles: &[(u32, String, Vec<u8>)],
+) {
+    if let Err(e) = tokio::fs::create_dir_all(cache_dir).await {
+        warn!("can't cache tiles: {}", e);
+        return;
+    }
+    if let Ok(mut cached) = tokio::fs::read_dir(cache_dir).await {
+        let title_prefix = format!("{}-", title_id);
+        while let Ok(Some(entry)) = cached.next_entry().await {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(&title_prefix) && !name.starts_with(prefix) {
+                let _ = tokio::fs::remove_file(entry.path()).await;
+            }
+        }
+    }
+
+    for (index, mime_type, buffer) in tiles {
+        let extension = mime_type.trim_start_matches("image/");
+        let path = cache_dir.join(format!("{}{}.{}", prefix, index, extension));
+        // written aside first, so a request never reads half a tile
+        let part = cache_dir.join(format!("{}.part", uuid::Uuid::new_v4()));
+        let written = match tokio::fs::write(&part, buffer).await {
+            Ok(_) => tokio::fs::rename(&part, &path).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            let _ = tokio::fs::remove_file(&part).await;
+            warn!("can't cache tile {} of title {}: {}", index, title_id, e);
+        }
+    }
+}
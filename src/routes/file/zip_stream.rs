@@ -0,0 +1,222 @@
+use axum::body::Body;
+use chrono::{Datelike, Timelike};
+use std::{future::Future, io, path::PathBuf, pin::Pin};
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+};
+use tokio_util::io::ReaderStream;
+use tracing::warn;
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const DATA_DESCRIPTOR: u32 = 0x08074b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const ZIP64_END: u32 = 0x06064b50;
+const ZIP64_LOCATOR: u32 = 0x07064b50;
+const END: u32 = 0x06054b50;
+/// Needed to extract zip64
+const VERSION: u16 = 45;
+/// Sizes in the data descriptor, UTF-8 names
+const FLAGS: u16 = 0x0808;
+
+/// The file of an entry, only resolved once the stream gets to it, so slow
+/// ones like repacks don't hold the response back.
+pub type ZipStreamSource = Pin<Box<dyn Future<Output = io::Result<PathBuf>> + Send>>;
+
+pub struct ZipStreamEntry {
+    /// Path in the zip
+    pub name: String,
+    pub source: ZipStreamSource,
+}
+
+/// Stream a zip of `entries`, stored as they are since they're archives
+/// already.
+///
+/// Sizes and offsets are always zip64, so it's never too large, and the CRC of
+/// an entry goes in a data descriptor after it, so nothing is read twice. A
+/// failure midway leaves the zip without its central directory, which
+/// clients see as a broken download.
+pub fn zip_stream(entries: Vec<ZipStreamEntry>) -> Body {
+    let (writer, reader) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        if let Err(e) = write_zip(writer, entries).await {
+            warn!("zip stream aborted: {}", e);
+        }
+    });
+    Body::from_stream(ReaderStream::new(reader))
+}
+
+/// DOS time and date, what zip has
+fn dos_now() -> (u16, u16) {
+    let now = chrono::Local::now();
+    let time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+    let year = (now.year().max(1980) - 1980) as u32;
+    let date = ((year << 9) | (now.month() << 5) | now.day()) as u16;
+    (time, date)
+}
+
+async fn write_zip<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    entries: Vec<ZipStreamEntry>,
+) -> io::Result<()> {
+    let (time, date) = dos_now();
+    let mut central = Vec::new();
+    let mut offset = 0u64;
+    let count = entries.len() as u64;
+
+    for entry in entries {
+        let name = entry.name.as_bytes();
+        let file = File::open(entry.source.await?).await?;
+
+        let mut local = Vec::with_capacity(30 + name.len() + 20);
+        local.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        local.extend_from_slice(&VERSION.to_le_bytes());
+        local.extend_from_slice(&FLAGS.to_le_bytes());
+        local.extend_from_slice(&0u16.to_le_bytes()); // stored
+        local.extend_from_slice(&time.to_le_bytes());
+        local.extend_from_slice(&date.to_le_bytes());
+        local.extend_from_slice(&0u32.to_le_bytes()); // crc, in the descriptor
+        local.extend_from_slice(&u32::MAX.to_le_bytes());
+        local.extend_from_slice(&u32::MAX.to_le_bytes());
+        local.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        local.extend_from_slice(&20u16.to_le_bytes());
+        local.extend_from_slice(name);
+        // zip64 extra, the sizes are in the descriptor
+        local.extend_from_slice(&1u16.to_le_bytes());
+        local.extend_from_slice(&16u16.to_le_bytes());
+        local.extend_from_slice(&0u64.to_le_bytes());
+        local.extend_from_slice(&0u64.to_le_bytes());
+        writer.write_all(&local).await?;
+
+        let (crc, size) = copy_with_crc(file, &mut writer).await?;
+
+        let mut descriptor = Vec::with_capacity(24);
+        descriptor.extend_from_slice(&DATA_DESCRIPTOR.to_le_bytes());
+        descriptor.extend_from_slice(&crc.to_le_bytes());
+        descriptor.extend_from_slice(&size.to_le_bytes());
+        descriptor.extend_from_slice(&size.to_le_bytes());
+        writer.write_all(&descriptor).await?;
+
+        central.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+        central.extend_from_slice(&((3 << 8) | VERSION).to_le_bytes()); // made on unix
+        central.extend_from_slice(&VERSION.to_le_bytes());
+        central.extend_from_slice(&FLAGS.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&time.to_le_bytes());
+        central.extend_from_slice(&date.to_le_bytes());
+        central.extend_from_slice(&crc.to_le_bytes());
+        central.extend_from_slice(&u32::MAX.to_le_bytes());
+        central.extend_from_slice(&u32::MAX.to_le_bytes());
+        central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        central.extend_from_slice(&28u16.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes()); // comment
+        central.extend_from_slice(&0u16.to_le_bytes()); // disk
+        central.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        central.extend_from_slice(&(0o100644u32 << 16).to_le_bytes());
+        central.extend_from_slice(&u32::MAX.to_le_bytes());
+        central.extend_from_slice(name);
+        central.extend_from_slice(&1u16.to_le_bytes());
+        central.extend_from_slice(&24u16.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&offset.to_le_bytes());
+
+        offset += (local.len() + descriptor.len()) as u64 + size;
+    }
+
+    let central_size = central.len() as u64;
+    let zip64_end_offset = offset + central_size;
+    writer.write_all(&central).await?;
+
+    let mut end = Vec::with_capacity(56 + 20 + 22);
+    end.extend_from_slice(&ZIP64_END.to_le_bytes());
+    end.extend_from_slice(&44u64.to_le_bytes());
+    end.extend_from_slice(&((3 << 8) | VERSION).to_le_bytes());
+    end.extend_from_slice(&VERSION.to_le_bytes());
+    end.extend_from_slice(&0u32.to_le_bytes());
+    end.extend_from_slice(&0u32.to_le_bytes());
+    end.extend_from_slice(&count.to_le_bytes());
+    end.extend_from_slice(&count.to_le_bytes());
+    end.extend_from_slice(&central_size.to_le_bytes());
+    end.extend_from_slice(&offset.to_le_bytes());
+
+    end.extend_from_slice(&ZIP64_LOCATOR.to_le_bytes());
+    end.extend_from_slice(&0u32.to_le_bytes());
+    end.extend_from_slice(&zip64_end_offset.to_le_bytes());
+    end.extend_from_slice(&1u32.to_le_bytes());
+
+    end.extend_from_slice(&END.to_le_bytes());
+    end.extend_from_slice(&0u16.to_le_bytes());
+    end.extend_from_slice(&0u16.to_le_bytes());
+    end.extend_from_slice(&(count.min(u16::MAX as u64) as u16).to_le_bytes());
+    end.extend_from_slice(&(count.min(u16::MAX as u64) as u16).to_le_bytes());
+    end.extend_from_slice(&u32::MAX.to_le_bytes());
+    end.extend_from_slice(&u32::MAX.to_le_bytes());
+    end.extend_from_slice(&0u16.to_le_bytes());
+    writer.write_all(&end).await?;
+
+    writer.shutdown().await
+}
+
+/// Copy `reader` to `writer`, returning the CRC-32 and the size of what was
+/// copied.
+async fn copy_with_crc<R: AsyncRead + Unpin, W: AsyncWrite + Unpin>(
+    mut reader: R,
+    writer: &mut W,
+) -> io::Result<(u32, u64)> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        writer.write_all(&buffer[..read]).await?;
+        size += read as u64;
+    }
+    Ok((hasher.finalize(), size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    #[tokio::test]
+    async fn write_zip_round_trips() {
+        let dir = std::env::temp_dir().join(format!("yomuyume-zip-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let files = [
+            ("Vol. 1.cbz", b"first archive".to_vec()),
+            ("Vol. 2.cbz", (0..=255u8).cycle().take(200_000).collect()),
+        ];
+        let mut entries = vec![];
+        for (name, content) in &files {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            entries.push(ZipStreamEntry {
+                name: format!("Title/{}", name),
+                source: Box::pin(async move { Ok(path) }),
+            });
+        }
+
+        let mut zip = vec![];
+        write_zip(&mut zip, entries).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(zip)).unwrap();
+        assert_eq!(archive.len(), files.len());
+        for (index, (name, content)) in files.iter().enumerate() {
+            let mut file = archive.by_index(index).unwrap();
+            assert_eq!(file.name(), format!("Title/{}", name));
+            assert_eq!(file.size(), content.len() as u64);
+            assert_eq!(file.crc32(), crc32fast::hash(content));
+            let mut read = vec![];
+            // reading to the end checks the CRC too
+            file.read_to_end(&mut read).unwrap();
+            assert_eq!(&read, content);
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use super::{filter_title_response, FilterResponseBody};
+use crate::{models::prelude::*, routes::ErrRsp, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::Deserialize;
+use std::sync::Arc;
+use utoipa::IntoParams;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ContinueQuery {
+    /// Maximum number of titles to return, 20 by default
+    pub limit: Option<u64>,
+}
+
+/// Get the titles the user has started but not finished, most recently read
+/// first.
+#[utoipa::path(get, path = "/api/index/continue", params(ContinueQuery), responses(
+    (status = 200, description = "Fetch titles successful", body = FilterResponseBody),
+    (status = 204, description = "Fetch titles successful, but none were found", body = FilterResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody)
+))]
+pub async fn get_continue(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Query(query): Query<ContinueQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let titles = Progresses::find()
+        .find_also_related(Titles)
+        .filter(progresses::Column::UserId.eq(&user.id))
+        .filter(progresses::Column::Completed.eq(false))
+        .order_by_desc(progresses::Column::LastReadAt)
+        .limit(query.limit.unwrap_or(20))
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let mut resp_data = vec![];
+    for (_, title) in titles {
+        if let Some(title) = title {
+            resp_data.push(filter_title_response(&data.db, title, &user.id).await?);
+        }
+    }
+
+    let status_code = match resp_data.is_empty() {
+        true => StatusCode::NO_CONTENT,
+        false => StatusCode::OK,
+    };
+
+    Ok((status_code, Json(FilterResponseBody { data: resp_data })))
+}
@@ -0,0 +1,79 @@
+use crate::{
+    models::prelude::*,
+    routes::{
+        smart_collection_response, smart_collection_titles, ErrRsp, SmartCollectionResponseBody,
+        SmartCollectionTitlesResponseBody,
+    },
+    AppState,
+};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Extension, Json};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::warn;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct UnavailableSmartCollection {
+    pub smart_collection: SmartCollectionResponseBody,
+    /// Why it can't be evaluated, like a collection in the filter that was
+    /// deleted or isn't shared anymore
+    pub reason: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HomeResponseBody {
+    /// The user's pinned smart collections, evaluated
+    pub pinned: Vec<SmartCollectionTitlesResponseBody>,
+    /// Pinned smart collections that can't be evaluated anymore, to fix or
+    /// unpin
+    pub unavailable: Vec<UnavailableSmartCollection>,
+}
+
+/// Get what goes on the user's home screen.
+#[utoipa::path(get, path = "/api/index/home", responses(
+    (status = 200, description = "Fetch home successful", body = HomeResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody)
+))]
+pub async fn get_home(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let smart_collections = SmartCollections::find()
+        .filter(smart_collections::Column::UserId.eq(&user.id))
+        .filter(smart_collections::Column::IsPinned.eq(true))
+        .order_by_asc(smart_collections::Column::Name)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let mut pinned = Vec::with_capacity(smart_collections.len());
+    let mut unavailable = Vec::new();
+    for smart_collection in smart_collections {
+        // one broken smart collection doesn't take the whole home down
+        match smart_collection_titles(&data.db, &user.id, smart_collection.clone()).await {
+            Ok(titles) => pinned.push(titles),
+            Err(e) if !e.status().is_server_error() => {
+                warn!(
+                    "smart collection {} can't be evaluated: {}",
+                    smart_collection.id,
+                    e.message()
+                );
+                unavailable.push(UnavailableSmartCollection {
+                    reason: e.message().to_string(),
+                    smart_collection: smart_collection_response(smart_collection)?,
+                });
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(HomeResponseBody {
+            pinned,
+            unavailable,
+        }),
+    ))
+}
@@ -0,0 +1,60 @@
+use crate::{models::prelude::*, routes::ErrRsp, AppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReviewResponseBody {
+    pub username: String,
+    /// From 1 to 10
+    pub rating: u32,
+    pub review: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReviewsResponseBody {
+    pub data: Vec<ReviewResponseBody>,
+}
+
+/// Get every rating of a title, most recently updated first.
+#[utoipa::path(get, path = "/api/index/title/{title_id}/reviews", responses(
+    (status = 200, description = "Fetch reviews successful", body = ReviewsResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody)
+))]
+pub async fn get_reviews(
+    State(data): State<Arc<AppState>>,
+    Path(title_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let ratings = Ratings::find()
+        .find_also_related(Users)
+        .filter(ratings::Column::TitleId.eq(&title_id))
+        .order_by_desc(ratings::Column::UpdatedAt)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let data = ratings
+        .into_iter()
+        .map(|(rating, user)| ReviewResponseBody {
+            username: user.map(|user| user.username).unwrap_or_default(),
+            rating: rating.rating,
+            review: rating.review,
+            created_at: rating.created_at,
+            updated_at: rating.updated_at,
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(ReviewsResponseBody { data })))
+}
@@ -0,0 +1,179 @@
+use super::filter_title_response;
+use crate::{
+    constants::similarity_threshold,
+    models::prelude::*,
+    routes::{ErrRsp, FilterTitleResponseBody},
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SimilarQuery {
+    /// Maximum number of titles to return, 20 by default
+    pub limit: Option<u64>,
+    /// Minimum similarity, from 0.0 to 1.0, 0.5 by default
+    pub threshold: Option<f32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SimilarTitleResponseBody {
+    /// From 0.0 to 1.0
+    pub similarity: f32,
+    pub title: FilterTitleResponseBody,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SimilarResponseBody {
+    pub data: Vec<SimilarTitleResponseBody>,
+}
+
+impl SimilarQuery {
+    fn limit(&self) -> u64 {
+        self.limit.unwrap_or(20)
+    }
+
+    /// The threshold in `titles_ssim` units, 0 to 1000.
+    fn threshold(&self) -> u16 {
+        (self
+            .threshold
+            .unwrap_or(similarity_threshold())
+            .clamp(0.0, 1.0)
+            * 1000.0) as u16
+    }
+}
+
+pub async fn similar_response(
+    db: &DatabaseConnection,
+    user_id: &str,
+    scored: Vec<(String, f32)>,
+) -> Result<Vec<SimilarTitleResponseBody>, ErrRsp> {
+    let mut resp_data = Vec::with_capacity(scored.len());
+    for (title_id, similarity) in scored {
+        let title = Titles::find_by_id(&title_id)
+            .one(db)
+            .await
+            .map_err(ErrRsp::db)?;
+        if let Some(title) = title {
+            resp_data.push(SimilarTitleResponseBody {
+                similarity,
+                title: filter_title_response(db, title, user_id).await?,
+            });
+        }
+    }
+    Ok(resp_data)
+}
+
+/// Get the titles most similar to a title.
+#[utoipa::path(get, path = "/api/index/title/{title_id}/similar", params(SimilarQuery), responses(
+    (status = 200, description = "Fetch similar titles successful", body = SimilarResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody)
+))]
+pub async fn get_similar(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(title_id): Path<String>,
+    Query(query): Query<SimilarQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let neighbours = TitlesSsim::find()
+        .filter(titles_ssim::Column::TitleIdA.eq(&title_id))
+        .filter(titles_ssim::Column::Ssim.gte(query.threshold()))
+        .order_by_desc(titles_ssim::Column::Ssim)
+        .limit(query.limit())
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .map(|pair| (pair.title_id_b, pair.ssim as f32 / 1000.0))
+        .collect();
+
+    let resp_data = similar_response(&data.db, &user.id, neighbours).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(SimilarResponseBody { data: resp_data }),
+    ))
+}
+
+/// Get recommendations for the user.
+///
+/// Titles are scored by their average similarity to the user's favorites and
+/// finished titles, pairs under `threshold` don't count. Titles the user has
+/// started are left out.
+#[utoipa::path(get, path = "/api/index/for_you", params(SimilarQuery), responses(
+    (status = 200, description = "Fetch recommendations successful", body = SimilarResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody)
+))]
+pub async fn get_for_you(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Query(query): Query<SimilarQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let progresses = Progresses::find()
+        .filter(progresses::Column::UserId.eq(&user.id))
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+    let favorites = Favorites::find()
+        .filter(favorites::Column::UserId.eq(&user.id))
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let seeds = progresses
+        .iter()
+        .filter(|progress| progress.completed)
+        .map(|progress| progress.title_id.clone())
+        .chain(favorites.into_iter().map(|favorite| favorite.title_id))
+        .collect::<HashSet<_>>();
+    let excluded = progresses
+        .into_iter()
+        .map(|progress| progress.title_id)
+        .chain(seeds.iter().cloned())
+        .collect::<HashSet<_>>();
+
+    if seeds.is_empty() {
+        return Ok((StatusCode::OK, Json(SimilarResponseBody { data: vec![] })));
+    }
+
+    let pairs = TitlesSsim::find()
+        .filter(titles_ssim::Column::TitleIdA.is_in(seeds.iter()))
+        .filter(titles_ssim::Column::Ssim.gte(query.threshold()))
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let mut scores: HashMap<String, f32> = HashMap::new();
+    for pair in pairs {
+        if !excluded.contains(&pair.title_id_b) {
+            *scores.entry(pair.title_id_b).or_default() += pair.ssim as f32 / 1000.0;
+        }
+    }
+
+    let mut scored = scores
+        .into_iter()
+        .map(|(title_id, score)| (title_id, score / seeds.len() as f32))
+        .collect::<Vec<_>>();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    scored.truncate(query.limit() as usize);
+
+    let resp_data = similar_response(&data.db, &user.id, scored).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(SimilarResponseBody { data: resp_data }),
+    ))
+}
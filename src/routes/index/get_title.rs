@@ -1,27 +1,56 @@
+use super::{find_ordered_pages, find_rating};
 use crate::{
-    models::prelude::*,
-    routes::{calculate_dimension, ErrRsp},
+    models::{
+        prelude::*,
+        reader::{ReaderSettings, ReadingDirection},
+    },
+    routes::{calculate_dimension, virtual_pages, ErrRsp, PageHalf, SpreadMode},
     AppState,
 };
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     Extension, Json,
 };
 use sea_orm::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
-use std::{path::PathBuf, sync::Arc};
-use utoipa::ToSchema;
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
 #[derive(Serialize, ToSchema, Debug)]
 #[skip_serializing_none]
 pub struct ResponsePage {
     pub id: String,
+    /// Index of the page in the archive, the one progress is saved with
+    pub index: i64,
     pub format: String,
     pub description: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Size in bytes
+    pub size: Option<i64>,
+    pub blurhash: Option<String>,
+    /// Two pages scanned as one, see `pages::Model::double_spread`
+    pub double_spread: Option<bool>,
+    /// Only this half of the page is shown, get it with `?half=`
+    pub half: Option<PageHalf>,
+    /// Id of the page shown beside this one, get both with `?join=`
+    pub joined_with: Option<String>,
+    /// Tile of the strip, starting at this page, get it with `?tile=`
+    pub tile: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TitleQuery {
+    /// How to lay out the double spreads, `original` by default
+    pub spreads: Option<SpreadMode>,
+    /// Order of the split halves, the reading direction by default
+    pub direction: Option<ReadingDirection>,
+    /// Tiles of the strip instead of pages, for webtoons that have one
+    pub strip: Option<bool>,
 }
 
 #[derive(Serialize, ToSchema, Debug)]
@@ -45,15 +74,27 @@ pub struct TitleResponseBody {
     pub pages: Vec<ResponsePage>,
     pub favorites: Option<i64>,
     pub bookmarks: Option<i64>,
+    /// Average rating, from 1.0 to 10.0
+    pub rating: Option<f32>,
+    pub rating_count: Option<i64>,
+    /// The user's own rating, from 1 to 10
+    pub user_rating: Option<u32>,
     pub is_favorite: Option<bool>,
     pub is_bookmark: Option<bool>,
     pub page_read: Option<i64>,
+    /// How to read the title, the user's preferences over the title's
+    pub reader: ReaderSettings,
+    /// The user's own preferences, see `/api/user/reader/{title_id}`
+    pub user_reader: Option<ReaderSettings>,
     pub date_added: String,
     pub date_updated: String,
 }
 
 /// Get everything about a title.
-#[utoipa::path(get, path = "/api/index/title/{title_id}", responses(
+///
+/// The pages can be laid out as a reader shows them, see `SpreadMode`, or as
+/// the tiles of the strip of a webtoon.
+#[utoipa::path(get, path = "/api/index/title/{title_id}", params(TitleQuery), responses(
     (status = 200, description = "Fetch title successful", body = TitleResponseBody),
     (status = 204, description = "No title found for the given id", body = TitleResponseBody),
     (status = 401, description = "Unauthorized", body = ErrorResponseBody),
@@ -62,6 +103,7 @@ pub struct TitleResponseBody {
 pub async fn get_title(
     State(data): State<Arc<AppState>>,
     Path(title_id): Path<Uuid>,
+    Query(query): Query<TitleQuery>,
     Extension(user): Extension<users::Model>,
 ) -> Result<impl IntoResponse, ErrRsp> {
     let title = Titles::find_by_id(title_id)
@@ -76,36 +118,128 @@ pub async fn get_title(
         .map_err(|e| ErrRsp::internal(format!("[2] DB error getting thumbnail: {}", e)))?
         .ok_or_else(|| ErrRsp::new(StatusCode::NO_CONTENT, "No thumbnail found."))?;
 
-    let pages = Pages::find()
-        .filter(pages::Column::TitleId.eq(&title.id))
-        .order_by_asc(pages::Column::Path)
-        .all(&data.db)
+    let user_reader = ReaderPreferences::find()
+        .filter(
+            Condition::all()
+                .add(reader_preferences::Column::UserId.eq(&user.id))
+                .add(reader_preferences::Column::TitleId.eq(&title.id)),
+        )
+        .one(&data.db)
         .await
-        .map_err(|e| ErrRsp::internal(format!("[3] DB error getting pages: {}", e)))?;
+        .map_err(|e| ErrRsp::internal(format!("[3] DB error getting reader preferences: {}", e)))?
+        .map(|preference| preference.settings());
+    let reader = user_reader
+        .unwrap_or_default()
+        .or(title.reader_settings())
+        .resolve();
 
-    // place the thumbnail.path at the front of the Vec<pages::Model>
-    // and convert it to Vec<ResponsePage>
-    let pages = pages
-        .into_iter()
-        .fold(Vec::new(), |mut list, page_model| {
-            if page_model.path == thumbnail.path {
-                list.insert(0, page_model);
-            } else {
-                list.push(page_model);
+    let page_models = find_ordered_pages(&data.db, &title.id)
+        .await
+        .map_err(|e| ErrRsp::internal(format!("[3] DB error getting pages: {}", e)))?;
+    let indices = page_models
+        .iter()
+        .enumerate()
+        .map(|(index, page)| (page.id.as_str(), index as i64))
+        .collect::<HashMap<_, _>>();
+    let format = |page: &pages::Model| {
+        PathBuf::from(&page.path)
+            .extension()
+            .map(|s| s.to_str().unwrap_or(""))
+            .unwrap_or("")
+            .to_ascii_lowercase()
+    };
+    let pages = virtual_pages(
+        &page_models,
+        query.spreads.unwrap_or_default(),
+        query
+            .direction
+            .or(reader.reading_direction)
+            .unwrap_or_default(),
+    )
+    .into_iter()
+    .map(|virtual_page| {
+        let (width, height) = virtual_page.dimensions().unzip();
+        let page = virtual_page.page;
+        let is_virtual = virtual_page.half.is_some() || virtual_page.joined_with.is_some();
+        // split and joined pages are re-encoded, see `get_page`
+        let format = match is_virtual {
+            true => {
+                let is_jpeg = |page| matches!(format(page).as_str(), "jpg" | "jpeg");
+                match is_jpeg(page) && virtual_page.joined_with.into_iter().all(is_jpeg) {
+                    true => "jpeg".to_string(),
+                    false => "png".to_string(),
+                }
             }
-            list
-        })
-        .into_iter()
-        .map(|page| ResponsePage {
-            id: page.id,
-            format: PathBuf::from(page.path)
-                .extension()
-                .map(|s| s.to_str().unwrap_or(""))
-                .unwrap_or("")
-                .to_ascii_lowercase(),
-            description: page.description,
-        })
-        .collect::<Vec<_>>();
+            false => format(page),
+        };
+        ResponsePage {
+            id: page.id.clone(),
+            index: indices.get(page.id.as_str()).copied().unwrap_or_default(),
+            format,
+            description: page.description.clone(),
+            width,
+            height,
+            size: page.size.filter(|_| !is_virtual),
+            blurhash: page.blurhash.clone().filter(|_| !is_virtual),
+            double_spread: match is_virtual {
+                true => Some(virtual_page.joined_with.is_some()),
+                false => page.double_spread,
+            },
+            half: virtual_page.half,
+            joined_with: virtual_page.joined_with.map(|joined| joined.id.clone()),
+            tile: None,
+        }
+    })
+    .collect::<Vec<_>>();
+
+    let tiles = match query.strip.unwrap_or(false) {
+        true => StripTiles::find()
+            .filter(strip_tiles::Column::TitleId.eq(&title.id))
+            .order_by_asc(strip_tiles::Column::Index)
+            .all(&data.db)
+            .await
+            .map_err(|e| ErrRsp::internal(format!("[3] DB error getting strip tiles: {}", e)))?,
+        false => vec![],
+    };
+    let pages = match tiles.is_empty() {
+        true => pages,
+        false => {
+            let page_models = page_models
+                .iter()
+                .map(|page| (page.id.as_str(), page))
+                .collect::<HashMap<_, _>>();
+            tiles
+                .into_iter()
+                .filter_map(|tile| {
+                    let segments = tile.decode_segments();
+                    let first = page_models.get(segments.first()?.page_id.as_str())?;
+                    // tiles are re-encoded, see `get_page`
+                    let is_jpeg = segments.iter().all(|segment| {
+                        page_models
+                            .get(segment.page_id.as_str())
+                            .is_some_and(|page| matches!(format(page).as_str(), "jpg" | "jpeg"))
+                    });
+                    Some(ResponsePage {
+                        id: first.id.clone(),
+                        index: indices.get(first.id.as_str()).copied().unwrap_or_default(),
+                        format: match is_jpeg {
+                            true => "jpeg".to_string(),
+                            false => "png".to_string(),
+                        },
+                        description: None,
+                        width: Some(tile.width),
+                        height: Some(tile.height),
+                        size: None,
+                        blurhash: None,
+                        double_spread: None,
+                        half: None,
+                        joined_with: None,
+                        tile: Some(tile.index),
+                    })
+                })
+                .collect()
+        }
+    };
 
     let is_favorite = Favorites::find()
         .filter(
@@ -160,6 +294,18 @@ pub async fn get_title(
         n => Some(n as i64),
     };
 
+    let rating = find_rating(&data.db, &title.id).await;
+    let user_rating = Ratings::find()
+        .filter(
+            Condition::all()
+                .add(ratings::Column::UserId.eq(&user.id))
+                .add(ratings::Column::TitleId.eq(&title.id)),
+        )
+        .one(&data.db)
+        .await
+        .map_err(|e| ErrRsp::internal(format!("[9] DB error getting rating: {}", e)))?
+        .map(|rating| rating.rating);
+
     let tag_ids = TitlesTags::find()
         .filter(titles_tags::Column::TitleId.eq(&title.id))
         .all(&data.db)
@@ -193,9 +339,14 @@ pub async fn get_title(
             pages,
             favorites,
             bookmarks,
+            rating: rating.map(|(average, _)| average),
+            rating_count: rating.map(|(_, count)| count),
+            user_rating,
             is_favorite,
             is_bookmark,
             page_read,
+            reader,
+            user_reader,
             date_added: title.date_added,
             date_updated: title.date_updated,
         }),
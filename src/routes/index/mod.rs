@@ -1,27 +1,100 @@
 mod get_categories;
+mod get_continue_reading;
+mod get_home;
+mod get_reviews;
+mod get_similar;
 mod get_title;
 mod post_filter;
+mod post_search;
 
-use crate::{models::prelude::*, routes::ErrRsp};
+use crate::{constants::extended_img_formats, models::prelude::*, routes::ErrRsp};
 
-use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter};
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+};
+use std::path::Path;
 
 pub use get_categories::*;
+pub use get_continue_reading::*;
+pub use get_home::*;
+pub use get_reviews::*;
+pub use get_similar::*;
 pub use get_title::*;
 pub use post_filter::*;
+pub use post_search::*;
+
+/// Whether a page of the archive is an image, not a folder nor a file like
+/// `ComicInfo.xml`.
+pub fn is_image_page(path: &str) -> bool {
+    !path.ends_with('/')
+        && Path::new(path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+            .is_some_and(|extension| extended_img_formats().contains(&extension.as_str()))
+}
+
+/// Image pages of a title in reading order, the thumbnail first and the rest
+/// by path.
+///
+/// Progress and bookmark page indexes, and the page numbers of the OPDS and
+/// Komga routes, are positions in this list.
+pub async fn find_ordered_pages(
+    db: &DatabaseConnection,
+    title_id: &str,
+) -> Result<Vec<pages::Model>, DbErr> {
+    let thumbnail_path = Thumbnails::find_by_id(title_id)
+        .one(db)
+        .await?
+        .map(|thumbnail| thumbnail.path);
 
-pub async fn find_page_count(db: &DatabaseConnection, title_id: &str) -> i64 {
     let pages = Pages::find()
-        .filter(pages::Column::TitleId.contains(title_id))
+        .filter(pages::Column::TitleId.eq(title_id))
+        .order_by_asc(pages::Column::Path)
         .all(db)
+        .await?;
+
+    Ok(pages
+        .into_iter()
+        .filter(|page_model| is_image_page(&page_model.path))
+        .fold(Vec::new(), |mut list, page_model| {
+            if Some(&page_model.path) == thumbnail_path.as_ref() {
+                list.insert(0, page_model);
+            } else {
+                list.push(page_model);
+            }
+            list
+        }))
+}
+
+/// Number of pages in `find_ordered_pages`, 0 if they can't be read.
+pub async fn find_page_count(db: &DatabaseConnection, title_id: &str) -> i64 {
+    find_ordered_pages(db, title_id)
         .await
-        .map_err(ErrRsp::db)
-        .unwrap_or(vec![]);
+        .map_or(0, |pages| pages.len() as i64)
+}
 
-    match pages.is_empty() {
-        true => 0,
-        false => pages.len() as i64,
-    }
+/// Names of the tags of a title.
+pub async fn find_tag_names(
+    db: &DatabaseConnection,
+    title_id: &str,
+) -> Result<Vec<String>, ErrRsp> {
+    let tag_ids = TitlesTags::find()
+        .filter(titles_tags::Column::TitleId.eq(title_id))
+        .all(db)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .map(|title_tag| title_tag.tag_id)
+        .collect::<Vec<_>>();
+
+    Ok(Tags::find()
+        .filter(tags::Column::Id.is_in(tag_ids))
+        .all(db)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .map(|tag| tag.name)
+        .collect())
 }
 
 pub async fn find_page_read(db: &DatabaseConnection, title_id: &str, user_id: &str) -> Option<i64> {
@@ -42,6 +115,24 @@ pub async fn find_page_read(db: &DatabaseConnection, title_id: &str, user_id: &s
     }
 }
 
+/// Average rating of a title and how many users rated it, `None` if nobody did.
+pub async fn find_rating(db: &DatabaseConnection, title_id: &str) -> Option<(f32, i64)> {
+    let ratings = Ratings::find()
+        .filter(ratings::Column::TitleId.eq(title_id))
+        .all(db)
+        .await
+        .map_err(ErrRsp::db)
+        .unwrap_or(vec![]);
+
+    match ratings.is_empty() {
+        true => None,
+        false => {
+            let sum = ratings.iter().map(|rating| rating.rating).sum::<u32>();
+            Some((sum as f32 / ratings.len() as f32, ratings.len() as i64))
+        }
+    }
+}
+
 pub async fn find_favorite_count(db: &DatabaseConnection, title_id: &str) -> Option<i64> {
     let favorites = Favorites::find()
         .filter(favorites::Column::TitleId.contains(title_id))
@@ -1,19 +1,21 @@
-use super::{find_favorite_count, find_page_count, find_page_read};
+use super::{find_favorite_count, find_page_count, find_page_read, find_rating};
 use crate::{
     models::prelude::*,
-    routes::{calculate_dimension, ErrRsp},
+    routes::{calculate_dimension, find_visible_collection, ErrRsp},
     AppState,
 };
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Extension, Json};
 use sea_orm::{
-    ColumnTrait, Condition, EntityTrait, Order, QueryFilter, QueryOrder, QuerySelect, QueryTrait,
+    sea_query::{Expr, Func, SimpleExpr},
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, Order, QueryFilter, QueryOrder,
+    QuerySelect, QueryTrait,
 };
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
-use std::{path::PathBuf, sync::Arc};
+use std::{cmp::Ordering, collections::HashMap, path::PathBuf, sync::Arc};
 use utoipa::ToSchema;
 
-#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct FilterRequest {
     /// Keywords to search for (search in title, description, author, tags)
     keywords: Option<Vec<String>>,
@@ -21,6 +23,8 @@ pub struct FilterRequest {
     category_ids: Option<Vec<String>>,
     /// Tags to filter by
     tag_ids: Option<Vec<i32>>,
+    /// Collections to filter by, the user's own or shared ones
+    collection_ids: Option<Vec<String>>,
     /// Maximum number of results to return
     limit: Option<u32>,
 
@@ -29,10 +33,32 @@ pub struct FilterRequest {
     is_bookmarked: Option<bool>,
     is_favorite: Option<bool>,
 
+    /// `alphabetical` (default), `add date`, `release date`, `update date`,
+    /// `last read` or `rating`
     sort_by: Option<String>,
+    /// `ascending` (default) or `descending`
     sort_order: Option<String>,
 }
 
+impl FilterRequest {
+    /// A filter on keywords only, like the search bar.
+    pub fn from_keywords(keywords: Vec<String>) -> Self {
+        Self {
+            keywords: Some(keywords),
+            category_ids: None,
+            tag_ids: None,
+            collection_ids: None,
+            limit: None,
+            is_reading: None,
+            is_finished: None,
+            is_bookmarked: None,
+            is_favorite: None,
+            sort_by: None,
+            sort_order: None,
+        }
+    }
+}
+
 #[derive(Serialize, ToSchema)]
 #[skip_serializing_none]
 pub struct FilterTitleResponseBody {
@@ -42,6 +68,9 @@ pub struct FilterTitleResponseBody {
     category_id: String,
     release: Option<String>,
     favorite_count: Option<i64>,
+    /// Average rating, from 1.0 to 10.0
+    rating: Option<f32>,
+    rating_count: Option<i64>,
     page_count: i64,
     page_read: Option<i64>,
 
@@ -57,30 +86,158 @@ pub struct FilterResponseBody {
     pub data: Vec<FilterTitleResponseBody>,
 }
 
-/// Filtering titles by various parameters.
-///
-/// And also sorting them by various options.
-#[utoipa::path(post, path = "/api/index/filter", responses(
-    (status = 200, description = "Fetch all items successful", body = FilterResponseBody),
-    (status = 204, description = "Fetch all items successful, but none were found", body = FilterResponseBody),
-    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
-    (status = 500, description = "Internal server error", body = ErrorResponseBody)
-))]
-pub async fn post_filter(
-    State(data): State<Arc<AppState>>,
-    Extension(user): Extension<users::Model>,
-    Json(query): Json<FilterRequest>,
-) -> Result<impl IntoResponse, ErrRsp> {
+/// Build the summary of a title shown in lists, as seen by `user_id`.
+pub async fn filter_title_response(
+    db: &DatabaseConnection,
+    title: titles::Model,
+    user_id: &str,
+) -> Result<FilterTitleResponseBody, ErrRsp> {
+    let page_count = find_page_count(db, &title.id).await;
+    let favorite_count = find_favorite_count(db, &title.id).await;
+    let rating = find_rating(db, &title.id).await;
+    let page_read = find_page_read(db, &title.id, user_id).await;
+    let thumbnail_model = Thumbnails::find_by_id(&title.id)
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::new(StatusCode::INTERNAL_SERVER_ERROR, "Thumbnail not found."))?;
+
+    let (width, height) = calculate_dimension(thumbnail_model.ratio);
+
+    Ok(FilterTitleResponseBody {
+        id: title.id,
+        title: title.title,
+        author: title.author,
+        category_id: title.category_id,
+        release: title.release,
+        favorite_count,
+        rating: rating.map(|(average, _)| average),
+        rating_count: rating.map(|(_, count)| count),
+        page_count,
+        page_read,
+
+        blurhash: thumbnail_model.blurhash,
+        width,
+        height,
+        format: PathBuf::from(thumbnail_model.path)
+            .extension()
+            .map(|s| s.to_str().unwrap_or(""))
+            .unwrap_or("")
+            .to_ascii_lowercase(),
+    })
+}
+
+/// Stable sort, so ties keep the alphabetical order of the query. Titles
+/// without a key go last in both directions.
+fn sort_titles_by<K: PartialOrd>(
+    titles: &mut [titles::Model],
+    descending: bool,
+    key: impl Fn(&titles::Model) -> Option<K>,
+) {
+    titles.sort_by(|a, b| match (key(a), key(b)) {
+        (Some(a), Some(b)) => {
+            let ordering = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+            match descending {
+                true => ordering.reverse(),
+                false => ordering,
+            }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+}
+
+/// Check that every category, tag and collection in a filter exists and is
+/// visible to `user_id`, listing the ones that aren't.
+pub async fn validate_filter(
+    db: &DatabaseConnection,
+    user_id: &str,
+    query: &FilterRequest,
+) -> Result<(), ErrRsp> {
+    if query.keywords.is_none()
+        && query.category_ids.is_none()
+        && query.tag_ids.is_none()
+        && query.collection_ids.is_none()
+    {
+        return Err(ErrRsp::bad_request(
+            "Invalid filter, it needs at least one of keywords, category_ids, tag_ids or collection_ids.",
+        ));
+    }
+
+    let mut errors = vec![];
+
+    if let Some(category_ids) = &query.category_ids {
+        let mut invalid = vec![];
+        for category_id in category_ids {
+            let category = Categories::find_by_id(category_id)
+                .one(db)
+                .await
+                .map_err(ErrRsp::db)?;
+            if category.is_none() {
+                invalid.push(category_id.to_string());
+            }
+        }
+        if !invalid.is_empty() {
+            errors.push(format!("invalid category ids: {}", invalid.join(", ")));
+        }
+    }
+
+    if let Some(tag_ids) = &query.tag_ids {
+        let mut invalid = vec![];
+        for tag_id in tag_ids {
+            let tag = match u32::try_from(*tag_id) {
+                Ok(tag_id) => Tags::find_by_id(tag_id).one(db).await.map_err(ErrRsp::db)?,
+                Err(_) => None,
+            };
+            if tag.is_none() {
+                invalid.push(tag_id.to_string());
+            }
+        }
+        if !invalid.is_empty() {
+            errors.push(format!("invalid tag ids: {}", invalid.join(", ")));
+        }
+    }
+
+    if let Some(collection_ids) = &query.collection_ids {
+        let mut invalid = vec![];
+        for collection_id in collection_ids {
+            if find_visible_collection(db, user_id, collection_id)
+                .await
+                .is_err()
+            {
+                invalid.push(collection_id.to_string());
+            }
+        }
+        if !invalid.is_empty() {
+            errors.push(format!("invalid collection ids: {}", invalid.join(", ")));
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(ErrRsp::bad_request(format!(
+            "Invalid filter, {}.",
+            errors.join("; ")
+        ))),
+    }
+}
+
+/// Run a filter as `user_id`, returning the matching titles in order.
+pub async fn filter_title_models(
+    db: &DatabaseConnection,
+    user_id: &str,
+    query: FilterRequest,
+) -> Result<Vec<titles::Model>, ErrRsp> {
     let keywords = query.keywords;
     let category_ids = query.category_ids;
     let tag_ids = query.tag_ids;
+    let collection_ids = query.collection_ids;
     let limit = query.limit;
 
-    if keywords.is_none() && category_ids.is_none() && tag_ids.is_none() {
-        return Ok((
-            StatusCode::NO_CONTENT,
-            Json(FilterResponseBody { data: vec![] }),
-        ));
+    if keywords.is_none() && category_ids.is_none() && tag_ids.is_none() && collection_ids.is_none()
+    {
+        return Ok(vec![]);
     }
 
     let mut condition = Condition::any();
@@ -107,7 +264,7 @@ pub async fn post_filter(
         }
         let title_tag_has_tag_id = TitlesTags::find()
             .filter(internal_cond)
-            .all(&data.db)
+            .all(db)
             .await
             .map_err(ErrRsp::db)?;
         for entity in title_tag_has_tag_id {
@@ -115,12 +272,26 @@ pub async fn post_filter(
         }
     }
 
+    if let Some(collection_ids) = collection_ids {
+        for collection_id in collection_ids {
+            let collection = find_visible_collection(db, user_id, &collection_id).await?;
+            let collection_titles = CollectionsTitles::find()
+                .filter(collections_titles::Column::CollectionId.eq(collection.id))
+                .all(db)
+                .await
+                .map_err(ErrRsp::db)?;
+            for entity in collection_titles {
+                condition = condition.add(titles::Column::Id.eq(entity.title_id));
+            }
+        }
+    }
+
     if let Some(is_reading) = query.is_reading {
         if is_reading {
             let progress_models = Progresses::find()
-                .filter(progresses::Column::UserId.eq(&user.id))
-                .filter(progresses::Column::Page.gt(0))
-                .all(&data.db)
+                .filter(progresses::Column::UserId.eq(user_id))
+                .filter(progresses::Column::Completed.eq(false))
+                .all(db)
                 .await
                 .map_err(ErrRsp::db)?;
             for entity in progress_models {
@@ -132,9 +303,9 @@ pub async fn post_filter(
     if let Some(is_finished) = query.is_finished {
         if is_finished {
             let progress_models = Progresses::find()
-                .filter(progresses::Column::UserId.eq(&user.id))
-                .filter(progresses::Column::Page.eq(0))
-                .all(&data.db)
+                .filter(progresses::Column::UserId.eq(user_id))
+                .filter(progresses::Column::Completed.eq(true))
+                .all(db)
                 .await
                 .map_err(ErrRsp::db)?;
             for entity in progress_models {
@@ -146,8 +317,8 @@ pub async fn post_filter(
     if let Some(is_bookmarked) = query.is_bookmarked {
         if is_bookmarked {
             let bookmark_models = Bookmarks::find()
-                .filter(bookmarks::Column::UserId.eq(&user.id))
-                .all(&data.db)
+                .filter(bookmarks::Column::UserId.eq(user_id))
+                .all(db)
                 .await
                 .map_err(ErrRsp::db)?;
             for entity in bookmark_models {
@@ -159,8 +330,8 @@ pub async fn post_filter(
     if let Some(is_favorite) = query.is_favorite {
         if is_favorite {
             let favorite_models = Favorites::find()
-                .filter(favorites::Column::UserId.eq(&user.id))
-                .all(&data.db)
+                .filter(favorites::Column::UserId.eq(user_id))
+                .all(db)
                 .await
                 .map_err(ErrRsp::db)?;
             for entity in favorite_models {
@@ -175,7 +346,8 @@ pub async fn post_filter(
             "add date" => titles::Column::DateAdded,
             "release date" => titles::Column::Release,
             "update date" => titles::Column::DateUpdated,
-            // "last read" => {},
+            // sorted after the query, ties stay alphabetical
+            "last read" | "rating" => titles::Column::Title,
             _ => titles::Column::Title,
         },
         None => titles::Column::Title,
@@ -190,51 +362,108 @@ pub async fn post_filter(
         None => Order::Asc,
     };
 
-    let title_models = Titles::find()
-        .apply_if(limit.map(|limit| limit as u64), QuerySelect::limit)
+    let sort_after_query = matches!(query.sort_by.as_deref(), Some("last read" | "rating"));
+    let descending = sort_order == Order::Desc;
+
+    let mut title_models = Titles::find()
+        .apply_if(
+            // when sorting after the query, the limit can only be applied then
+            limit
+                .filter(|_| !sort_after_query)
+                .map(|limit| limit as u64),
+            QuerySelect::limit,
+        )
         .filter(condition)
         .order_by(sort_by, sort_order)
-        .all(&data.db)
+        .all(db)
         .await
         .map_err(ErrRsp::db)?;
 
+    match query.sort_by.as_deref() {
+        Some("last read") => {
+            let last_read_at = Progresses::find()
+                .select_only()
+                .column(progresses::Column::TitleId)
+                .column(progresses::Column::LastReadAt)
+                .filter(progresses::Column::UserId.eq(user_id))
+                .into_tuple::<(String, String)>()
+                .all(db)
+                .await
+                .map_err(ErrRsp::db)?
+                .into_iter()
+                .collect::<HashMap<_, _>>();
+
+            // never read titles go last
+            sort_titles_by(&mut title_models, descending, |title| {
+                last_read_at.get(&title.id)
+            });
+        }
+        Some("rating") => {
+            let title_ids = title_models.iter().map(|title| title.id.clone());
+            let ratings = Ratings::find()
+                .select_only()
+                .column(ratings::Column::TitleId)
+                .column_as(
+                    SimpleExpr::from(Func::avg(Expr::col(ratings::Column::Rating))),
+                    "average",
+                )
+                .filter(ratings::Column::TitleId.is_in(title_ids))
+                .group_by(ratings::Column::TitleId)
+                .into_tuple::<(String, f64)>()
+                .all(db)
+                .await
+                .map_err(ErrRsp::db)?
+                .into_iter()
+                .collect::<HashMap<_, _>>();
+
+            // unrated titles go last
+            sort_titles_by(&mut title_models, descending, |title| {
+                ratings.get(&title.id)
+            });
+        }
+        _ => {}
+    }
+    if sort_after_query {
+        if let Some(limit) = limit {
+            title_models.truncate(limit as usize);
+        }
+    }
+
+    Ok(title_models)
+}
+
+/// Run a filter as `user_id`, the shared part of `post_filter` and smart
+/// collections.
+pub async fn filter_titles(
+    db: &DatabaseConnection,
+    user_id: &str,
+    query: FilterRequest,
+) -> Result<Vec<FilterTitleResponseBody>, ErrRsp> {
     let mut resp_data: Vec<FilterTitleResponseBody> = vec![];
 
-    for title in title_models {
-        let page_count = find_page_count(&data.db, &title.id).await;
-        let favorite_count = find_favorite_count(&data.db, &title.id).await;
-        let page_read = find_page_read(&data.db, &title.id, &user.id).await;
-        let thumbnail_model = Thumbnails::find_by_id(&title.id)
-            .one(&data.db)
-            .await
-            .map_err(ErrRsp::db)?
-            .ok_or_else(|| {
-                ErrRsp::new(StatusCode::INTERNAL_SERVER_ERROR, "Thumbnail not found.")
-            })?;
-
-        let (width, height) = calculate_dimension(thumbnail_model.ratio);
-
-        resp_data.push(FilterTitleResponseBody {
-            id: title.id,
-            title: title.title,
-            author: title.author,
-            category_id: title.category_id,
-            release: title.release,
-            favorite_count,
-            page_count,
-            page_read,
-
-            blurhash: thumbnail_model.blurhash,
-            width,
-            height,
-            format: PathBuf::from(thumbnail_model.path)
-                .extension()
-                .map(|s| s.to_str().unwrap_or(""))
-                .unwrap_or("")
-                .to_ascii_lowercase(),
-        });
+    for title in filter_title_models(db, user_id, query).await? {
+        resp_data.push(filter_title_response(db, title, user_id).await?);
     }
 
+    Ok(resp_data)
+}
+
+/// Filtering titles by various parameters.
+///
+/// And also sorting them by various options.
+#[utoipa::path(post, path = "/api/index/filter", responses(
+    (status = 200, description = "Fetch all items successful", body = FilterResponseBody),
+    (status = 204, description = "Fetch all items successful, but none were found", body = FilterResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody)
+))]
+pub async fn post_filter(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Json(query): Json<FilterRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let resp_data = filter_titles(&data.db, &user.id, query).await?;
+
     let status_code = match resp_data.is_empty() {
         true => StatusCode::NO_CONTENT,
         false => StatusCode::OK,
@@ -0,0 +1,110 @@
+use super::{
+    filter_title_models, similar_response, validate_filter, FilterRequest, SimilarResponseBody,
+};
+use crate::{embedder::similarity, models::prelude::*, routes::ErrRsp, AppState};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Extension, Json};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::Deserialize;
+use std::{collections::HashSet, sync::Arc};
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SearchRequest {
+    /// Free text describing what to look for
+    pub query: String,
+    /// Only rank the titles matching this filter, same as the body of
+    /// `POST /api/index/filter`
+    pub filter: Option<FilterRequest>,
+    /// Maximum number of titles to return, 20 by default
+    pub limit: Option<u64>,
+    /// Minimum similarity, from 0.0 to 1.0, none by default as the titles are
+    /// ranked by it anyway
+    pub threshold: Option<f32>,
+}
+
+/// Search titles by meaning rather than keywords.
+///
+/// The query is embedded with the same model as the titles, which are ranked
+/// by cosine similarity to it.
+#[utoipa::path(post, path = "/api/index/search", request_body = SearchRequest, responses(
+    (status = 200, description = "Search successful", body = SimilarResponseBody),
+    (status = 400, description = "Bad request, or invalid ids in the filter", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+    (status = 503, description = "Semantic search is not configured, or not ready before the first scan", body = ErrorResponseBody)
+))]
+pub async fn post_search(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Json(query): Json<SearchRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let embedder = data.embedder.as_ref().ok_or_else(|| {
+        ErrRsp::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Semantic search is not configured.",
+        )
+    })?;
+    if !embedder.is_ready() {
+        return Err(ErrRsp::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Semantic search isn't ready until the library has been scanned, try again later.",
+        ));
+    }
+
+    let text = query.query.trim();
+    if text.is_empty() {
+        return Err(ErrRsp::bad_request("Search query must not be empty."));
+    }
+
+    let candidates = match query.filter {
+        Some(filter) => {
+            validate_filter(&data.db, &user.id, &filter).await?;
+            let titles = filter_title_models(&data.db, &user.id, filter).await?;
+            Some(
+                titles
+                    .into_iter()
+                    .map(|title| title.id)
+                    .collect::<HashSet<_>>(),
+            )
+        }
+        None => None,
+    };
+
+    let query_vector = embedder
+        .encode(vec![text.to_string()])
+        .await
+        .map_err(|e| ErrRsp::internal(format!("Can't embed the search query: {}", e)))?
+        .pop()
+        .ok_or_else(|| ErrRsp::internal("Can't embed the search query."))?;
+
+    let threshold = query.threshold.map(|threshold| threshold.clamp(0.0, 1.0));
+
+    let embeddings = TitleEmbeddings::find()
+        .filter(title_embeddings::Column::ModelId.eq(embedder.model_id()))
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let mut scored = embeddings
+        .into_iter()
+        .filter(|embedding| {
+            candidates
+                .as_ref()
+                .is_none_or(|candidates| candidates.contains(&embedding.title_id))
+        })
+        .map(|embedding| {
+            let score = similarity(&query_vector, &embedding.decode_vector());
+            (embedding.title_id, score)
+        })
+        .filter(|(_, score)| threshold.into_iter().all(|threshold| *score >= threshold))
+        .collect::<Vec<_>>();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    scored.truncate(query.limit.unwrap_or(20) as usize);
+
+    let resp_data = similar_response(&data.db, &user.id, scored).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(SimilarResponseBody { data: resp_data }),
+    ))
+}
@@ -0,0 +1,263 @@
+use super::{komga_book, komga_size};
+use crate::{
+    models::prelude::*,
+    routes::{
+        apply_progress_update, find_ordered_pages, find_page_count, get_opds_title_file, get_page,
+        get_thumbnail, ErrRsp, PageQuery, ProgressUpdate, SyncStrategy,
+    },
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::Arc};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaPageDto {
+    /// 1-based
+    pub number: usize,
+    pub file_name: String,
+    pub media_type: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub size_bytes: Option<u64>,
+    pub size: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct KomgaPageNumberQuery {
+    /// Count pages from 0 instead of 1
+    pub zero_based: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaReadProgressUpdateDto {
+    /// 1-based
+    pub page: Option<i64>,
+    pub completed: Option<bool>,
+}
+
+async fn find_book_title(db: &DatabaseConnection, book_id: &str) -> Result<titles::Model, ErrRsp> {
+    Titles::find_by_id(book_id)
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("Book not found."))
+}
+
+/// Set the progress of a title from a Komga client, at the last page when
+/// completed without a page.
+pub async fn update_komga_progress(
+    db: &DatabaseConnection,
+    user_id: &str,
+    title_id: &str,
+    page: Option<i64>,
+    completed: bool,
+) -> Result<(), ErrRsp> {
+    let page = match page {
+        Some(page) => page,
+        None => (find_page_count(db, title_id).await - 1).max(0),
+    };
+    let stored = Progresses::find()
+        .filter(progresses::Column::TitleId.eq(title_id))
+        .filter(progresses::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let update = ProgressUpdate {
+        title_id: title_id.to_string(),
+        page,
+        scroll_offset: None,
+        completed: Some(completed),
+        device_id: Some("komga".to_string()),
+        updated_at: None,
+    };
+    apply_progress_update(db, user_id, stored, &update, SyncStrategy::LastWriterWins).await
+}
+
+/// Get a title as a Komga book.
+#[utoipa::path(get, path = "/komga/api/v1/books/{book_id}", responses(
+    (status = 200, description = "Fetch book successful", body = KomgaBookDto),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Book not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_book(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(book_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let title = find_book_title(&data.db, &book_id).await?;
+    let book = komga_book(&data.db, &user.id, &title).await?;
+    Ok((StatusCode::OK, Json(book)))
+}
+
+/// List the pages of a book.
+#[utoipa::path(get, path = "/komga/api/v1/books/{book_id}/pages", responses(
+    (status = 200, description = "Fetch pages successful", body = Vec<KomgaPageDto>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Book not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_book_pages(
+    State(data): State<Arc<AppState>>,
+    Path(book_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let title = find_book_title(&data.db, &book_id).await?;
+    let pages = find_ordered_pages(&data.db, &title.id)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, page)| {
+            let path = PathBuf::from(&page.path);
+            let size_bytes = page.size.map(|size| size.max(0) as u64);
+            KomgaPageDto {
+                number: index + 1,
+                file_name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                media_type: format!(
+                    "image/{}",
+                    path.extension()
+                        .map(|s| s.to_str().unwrap_or(""))
+                        .unwrap_or("")
+                        .to_ascii_lowercase()
+                ),
+                width: page.width,
+                height: page.height,
+                size_bytes,
+                size: size_bytes.map(komga_size).unwrap_or_default(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok((StatusCode::OK, Json(pages)))
+}
+
+/// Get a page of a book by its 1-based number.
+#[utoipa::path(get, path = "/komga/api/v1/books/{book_id}/pages/{page_number}", params(KomgaPageNumberQuery), responses(
+    (status = 200, description = "Fetch page successful", body = Vec<u8>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Book or page not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_book_page(
+    State(data): State<Arc<AppState>>,
+    Path((book_id, page_number)): Path<(String, usize)>,
+    Query(query): Query<KomgaPageNumberQuery>,
+) -> Result<Response, ErrRsp> {
+    let index = match query.zero_based.unwrap_or(false) {
+        true => Some(page_number),
+        false => page_number.checked_sub(1),
+    };
+    let page = find_ordered_pages(&data.db, &book_id)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .nth(index.ok_or_else(|| ErrRsp::not_found("Page not found."))?)
+        .ok_or_else(|| ErrRsp::not_found("Page not found."))?;
+
+    get_page(
+        State(data),
+        None,
+        Path(page.id),
+        Query(PageQuery::default()),
+    )
+    .await
+    .map(IntoResponse::into_response)
+}
+
+/// Get the cover of a book.
+#[utoipa::path(get, path = "/komga/api/v1/books/{book_id}/thumbnail", responses(
+    (status = 200, description = "Fetch thumbnail successful", body = Vec<u8>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Thumbnail not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_book_thumbnail(
+    state: State<Arc<AppState>>,
+    Path(book_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    get_thumbnail(state, Path(book_id)).await
+}
+
+/// Download the archive of a book.
+#[utoipa::path(get, path = "/komga/api/v1/books/{book_id}/file", responses(
+    (status = 200, description = "Fetch book archive successful", body = Vec<u8>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Book not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_book_file(
+    state: State<Arc<AppState>>,
+    Path(book_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, ErrRsp> {
+    get_opds_title_file(state, Path(book_id), headers).await
+}
+
+/// Set the read progress of a book.
+#[utoipa::path(patch, path = "/komga/api/v1/books/{book_id}/read-progress", request_body = KomgaReadProgressUpdateDto, responses(
+    (status = 204, description = "Update read progress successful"),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Book not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn patch_komga_read_progress(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(book_id): Path<String>,
+    Json(query): Json<KomgaReadProgressUpdateDto>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let title = find_book_title(&data.db, &book_id).await?;
+    let page = query.page.map(|page| page.saturating_sub(1).max(0));
+    let completed = match (page, query.completed) {
+        (_, Some(completed)) => completed,
+        // reaching the last page completes the book, like Komga does
+        (Some(page), None) => page.saturating_add(1) >= find_page_count(&data.db, &title.id).await,
+        (None, None) => {
+            return Err(ErrRsp::bad_request(
+                "Read progress needs at least one of page or completed.",
+            ))
+        }
+    };
+
+    update_komga_progress(&data.db, &user.id, &title.id, page, completed).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Mark a book as unread.
+#[utoipa::path(delete, path = "/komga/api/v1/books/{book_id}/read-progress", responses(
+    (status = 204, description = "Delete read progress successful"),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Book not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn delete_komga_read_progress(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(book_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let title = find_book_title(&data.db, &book_id).await?;
+    Progresses::delete_many()
+        .filter(progresses::Column::TitleId.eq(&title.id))
+        .filter(progresses::Column::UserId.eq(&user.id))
+        .exec(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
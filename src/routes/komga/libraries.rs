@@ -0,0 +1,73 @@
+use crate::{models::prelude::*, routes::ErrRsp, AppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sea_orm::{EntityTrait, QueryOrder};
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaLibraryDto {
+    pub id: String,
+    pub name: String,
+    pub root: String,
+    pub unavailable: bool,
+}
+
+fn komga_library(library_path: &str, category: categories::Model) -> KomgaLibraryDto {
+    KomgaLibraryDto {
+        root: format!("{}/{}", library_path.trim_end_matches('/'), category.name),
+        id: category.id,
+        name: category.name,
+        unavailable: false,
+    }
+}
+
+/// List the categories as Komga libraries.
+#[utoipa::path(get, path = "/komga/api/v1/libraries", responses(
+    (status = 200, description = "Fetch libraries successful", body = Vec<KomgaLibraryDto>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_libraries(
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let libraries = Categories::find()
+        .order_by_asc(categories::Column::Name)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .map(|category| komga_library(&data.env.library_path, category))
+        .collect::<Vec<_>>();
+
+    Ok((StatusCode::OK, Json(libraries)))
+}
+
+/// Get a category as a Komga library.
+#[utoipa::path(get, path = "/komga/api/v1/libraries/{library_id}", responses(
+    (status = 200, description = "Fetch library successful", body = KomgaLibraryDto),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Library not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_library(
+    State(data): State<Arc<AppState>>,
+    Path(library_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let category = Categories::find_by_id(library_id)
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("Library not found."))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(komga_library(&data.env.library_path, category)),
+    ))
+}
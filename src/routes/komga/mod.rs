@@ -0,0 +1,505 @@
+//! A subset of the Komga API, for the apps that have a Komga extension but
+//! no yomuyume one.
+//!
+//! Libraries are categories, and every title is a series holding a single
+//! book, both with the id of the title.
+
+mod books;
+mod libraries;
+mod referential;
+mod series;
+
+pub use books::*;
+pub use libraries::*;
+pub use referential::*;
+pub use series::*;
+
+use crate::{
+    constants::komga_max_page_size,
+    models::{
+        prelude::*,
+        reader::{ReadingDirection, ReadingLayout},
+    },
+    routes::{find_page_count, find_tag_names, release_date, ErrRsp},
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct KomgaPageQuery {
+    /// 0-based page
+    pub page: Option<u64>,
+    /// Page size, 20 by default, at most 1000
+    pub size: Option<u64>,
+    /// Return everything in one page
+    pub unpaged: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaSort {
+    pub sorted: bool,
+    pub unsorted: bool,
+    pub empty: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaPageable {
+    pub page_number: u64,
+    pub page_size: u64,
+    pub offset: u64,
+    pub paged: bool,
+    pub unpaged: bool,
+    pub sort: KomgaSort,
+}
+
+/// A Spring Data page, how Komga paginates.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[aliases(
+    KomgaSeriesPage = KomgaPage<KomgaSeriesDto>,
+    KomgaBookPage = KomgaPage<KomgaBookDto>,
+    KomgaCollectionPage = KomgaPage<KomgaCollectionDto>
+)]
+pub struct KomgaPage<T> {
+    pub content: Vec<T>,
+    pub pageable: KomgaPageable,
+    pub total_elements: u64,
+    pub total_pages: u64,
+    pub last: bool,
+    pub first: bool,
+    pub number: u64,
+    pub size: u64,
+    pub number_of_elements: u64,
+    pub sort: KomgaSort,
+    pub empty: bool,
+}
+
+impl<T> KomgaPage<T> {
+    /// Cut the page asked for by `query` out of `items`.
+    pub fn new(items: Vec<T>, query: &KomgaPageQuery, sorted: bool) -> Self {
+        let total = items.len() as u64;
+        let unpaged = query.unpaged.unwrap_or(false);
+        let (number, size) = match unpaged {
+            true => (0, total),
+            false => (
+                query.page.unwrap_or(0),
+                query.size.unwrap_or(20).clamp(1, komga_max_page_size()),
+            ),
+        };
+        let content = items
+            .into_iter()
+            .skip(usize::try_from(number.saturating_mul(size)).unwrap_or(usize::MAX))
+            .take(size as usize)
+            .collect::<Vec<_>>();
+        let total_pages = match size {
+            0 => 1,
+            size => total.div_ceil(size),
+        };
+        let sort = || KomgaSort {
+            sorted,
+            unsorted: !sorted,
+            empty: !sorted,
+        };
+
+        Self {
+            pageable: KomgaPageable {
+                page_number: number,
+                page_size: size,
+                offset: number.saturating_mul(size),
+                paged: !unpaged,
+                unpaged,
+                sort: sort(),
+            },
+            total_elements: total,
+            total_pages,
+            last: number.saturating_add(1) >= total_pages,
+            first: number == 0,
+            number,
+            size,
+            number_of_elements: content.len() as u64,
+            sort: sort(),
+            empty: content.is_empty(),
+            content,
+        }
+    }
+
+    /// Swap the content for what it maps to, keeping the page as is.
+    pub fn with_content<U>(self, content: Vec<U>) -> KomgaPage<U> {
+        KomgaPage {
+            content,
+            pageable: self.pageable,
+            total_elements: self.total_elements,
+            total_pages: self.total_pages,
+            last: self.last,
+            first: self.first,
+            number: self.number,
+            size: self.size,
+            number_of_elements: self.number_of_elements,
+            sort: self.sort,
+            empty: self.empty,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaAuthorDto {
+    pub name: String,
+    pub role: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaWebLinkDto {
+    pub label: String,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaAlternateTitleDto {
+    pub label: String,
+    pub title: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaSeriesMetadataDto {
+    pub status: String,
+    pub status_lock: bool,
+    pub title: String,
+    pub title_lock: bool,
+    pub title_sort: String,
+    pub title_sort_lock: bool,
+    pub summary: String,
+    pub summary_lock: bool,
+    pub reading_direction: String,
+    pub reading_direction_lock: bool,
+    pub publisher: String,
+    pub publisher_lock: bool,
+    pub age_rating: Option<u32>,
+    pub age_rating_lock: bool,
+    pub language: String,
+    pub language_lock: bool,
+    pub genres: Vec<String>,
+    pub genres_lock: bool,
+    pub tags: Vec<String>,
+    pub tags_lock: bool,
+    pub total_book_count: Option<u32>,
+    pub total_book_count_lock: bool,
+    pub sharing_labels: Vec<String>,
+    pub sharing_labels_lock: bool,
+    pub links: Vec<KomgaWebLinkDto>,
+    pub links_lock: bool,
+    pub alternate_titles: Vec<KomgaAlternateTitleDto>,
+    pub alternate_titles_lock: bool,
+    pub created: String,
+    pub last_modified: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaBookMetadataAggregationDto {
+    pub authors: Vec<KomgaAuthorDto>,
+    pub tags: Vec<String>,
+    pub release_date: Option<String>,
+    pub summary: String,
+    pub summary_number: String,
+    pub created: String,
+    pub last_modified: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaSeriesDto {
+    pub id: String,
+    pub library_id: String,
+    pub name: String,
+    pub url: String,
+    pub created: String,
+    pub last_modified: String,
+    pub file_last_modified: String,
+    pub books_count: u32,
+    pub books_read_count: u32,
+    pub books_unread_count: u32,
+    pub books_in_progress_count: u32,
+    pub metadata: KomgaSeriesMetadataDto,
+    pub books_metadata: KomgaBookMetadataAggregationDto,
+    pub deleted: bool,
+    pub oneshot: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaMediaDto {
+    pub status: String,
+    pub media_type: String,
+    pub pages_count: i64,
+    pub comment: String,
+    pub media_profile: String,
+    pub epub_divina_compatible: bool,
+    pub epub_is_kepub: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaBookMetadataDto {
+    pub title: String,
+    pub title_lock: bool,
+    pub summary: String,
+    pub summary_lock: bool,
+    pub number: String,
+    pub number_lock: bool,
+    pub number_sort: f32,
+    pub number_sort_lock: bool,
+    pub release_date: Option<String>,
+    pub release_date_lock: bool,
+    pub authors: Vec<KomgaAuthorDto>,
+    pub authors_lock: bool,
+    pub tags: Vec<String>,
+    pub tags_lock: bool,
+    pub isbn: String,
+    pub isbn_lock: bool,
+    pub links: Vec<KomgaWebLinkDto>,
+    pub links_lock: bool,
+    pub created: String,
+    pub last_modified: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaReadProgressDto {
+    /// 1-based
+    pub page: i64,
+    pub completed: bool,
+    pub read_date: String,
+    pub created: String,
+    pub last_modified: String,
+    pub device_id: String,
+    pub device_name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaBookDto {
+    pub id: String,
+    pub series_id: String,
+    pub series_title: String,
+    pub library_id: String,
+    pub name: String,
+    pub url: String,
+    pub number: u32,
+    pub created: String,
+    pub last_modified: String,
+    pub file_last_modified: String,
+    pub size_bytes: u64,
+    pub size: String,
+    pub media: KomgaMediaDto,
+    pub metadata: KomgaBookMetadataDto,
+    pub read_progress: Option<KomgaReadProgressDto>,
+    pub deleted: bool,
+    pub file_hash: String,
+    pub oneshot: bool,
+}
+
+/// Titles store their dates as unix timestamps, Komga has ISO 8601.
+fn komga_date(date: &str) -> String {
+    date.parse::<i64>()
+        .ok()
+        .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
+        .map(|date| date.to_rfc3339())
+        .unwrap_or_else(|| date.to_string())
+}
+
+/// Human readable size, like Komga's
+pub fn komga_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
+
+fn title_authors(title: &titles::Model) -> Vec<KomgaAuthorDto> {
+    title
+        .author
+        .iter()
+        .map(|author| KomgaAuthorDto {
+            name: author.clone(),
+            role: "writer".to_string(),
+        })
+        .collect()
+}
+
+/// The one book of the series of a title, as seen by `user_id`.
+pub async fn komga_book(
+    db: &DatabaseConnection,
+    user_id: &str,
+    title: &titles::Model,
+) -> Result<KomgaBookDto, ErrRsp> {
+    let page_count = find_page_count(db, &title.id).await;
+    let tags = find_tag_names(db, &title.id).await?;
+    let progress = Progresses::find()
+        .filter(progresses::Column::UserId.eq(user_id))
+        .filter(progresses::Column::TitleId.eq(&title.id))
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?;
+    let file = tokio::fs::metadata(&title.path).await.ok();
+    let size_bytes = file.as_ref().map_or(0, |file| file.len());
+    let file_last_modified = file
+        .and_then(|file| file.modified().ok())
+        .map(|modified| chrono::DateTime::<chrono::Utc>::from(modified).to_rfc3339())
+        .unwrap_or_else(|| komga_date(&title.date_updated));
+    let created = komga_date(&title.date_added);
+    let last_modified = komga_date(&title.date_updated);
+
+    Ok(KomgaBookDto {
+        id: title.id.clone(),
+        series_id: title.id.clone(),
+        series_title: title.title.clone(),
+        library_id: title.category_id.clone(),
+        name: title.title.clone(),
+        url: title.path.clone(),
+        number: 1,
+        created: created.clone(),
+        last_modified: last_modified.clone(),
+        file_last_modified,
+        size_bytes,
+        size: komga_size(size_bytes),
+        media: KomgaMediaDto {
+            status: "READY".to_string(),
+            media_type: "application/zip".to_string(),
+            pages_count: page_count,
+            comment: String::new(),
+            media_profile: "DIVINA".to_string(),
+            epub_divina_compatible: false,
+            epub_is_kepub: false,
+        },
+        metadata: KomgaBookMetadataDto {
+            title: title.title.clone(),
+            title_lock: false,
+            summary: title.description.clone().unwrap_or_default(),
+            summary_lock: false,
+            number: "1".to_string(),
+            number_lock: false,
+            number_sort: 1.0,
+            number_sort_lock: false,
+            release_date: release_date(title.release.as_deref()).map(|date| date.to_string()),
+            release_date_lock: false,
+            authors: title_authors(title),
+            authors_lock: false,
+            tags,
+            tags_lock: false,
+            isbn: String::new(),
+            isbn_lock: false,
+            links: vec![],
+            links_lock: false,
+            created,
+            last_modified,
+        },
+        read_progress: progress.map(|progress| KomgaReadProgressDto {
+            page: progress.page.saturating_add(1),
+            completed: progress.completed,
+            read_date: progress.last_read_at.clone(),
+            created: progress
+                .started_at
+                .unwrap_or_else(|| progress.last_read_at.clone()),
+            last_modified: progress.last_read_at,
+            device_id: progress.device_id.clone().unwrap_or_default(),
+            device_name: progress.device_id.unwrap_or_default(),
+        }),
+        deleted: false,
+        file_hash: title.hash.clone(),
+        oneshot: true,
+    })
+}
+
+/// The series of a title, as seen by `user_id`.
+/// Komga's reading direction of a title, empty if it isn't set.
+fn komga_reading_direction(title: &titles::Model) -> String {
+    let reading_direction = match (title.layout, title.reading_direction) {
+        (Some(ReadingLayout::Webtoon), _) => "WEBTOON",
+        (Some(ReadingLayout::Continuous), _) => "VERTICAL",
+        (_, Some(ReadingDirection::RightToLeft)) => "RIGHT_TO_LEFT",
+        (_, Some(ReadingDirection::LeftToRight)) => "LEFT_TO_RIGHT",
+        _ => "",
+    };
+    reading_direction.to_string()
+}
+
+pub async fn komga_series(
+    db: &DatabaseConnection,
+    user_id: &str,
+    title: &titles::Model,
+) -> Result<KomgaSeriesDto, ErrRsp> {
+    let book = komga_book(db, user_id, title).await?;
+    let (read, in_progress) = match &book.read_progress {
+        Some(progress) if progress.completed => (1, 0),
+        Some(_) => (0, 1),
+        None => (0, 0),
+    };
+
+    Ok(KomgaSeriesDto {
+        id: book.series_id,
+        library_id: book.library_id,
+        name: book.series_title,
+        url: book.url,
+        created: book.created,
+        last_modified: book.last_modified,
+        file_last_modified: book.file_last_modified,
+        books_count: 1,
+        books_read_count: read,
+        books_unread_count: 1 - read - in_progress,
+        books_in_progress_count: in_progress,
+        metadata: KomgaSeriesMetadataDto {
+            status: "ENDED".to_string(),
+            status_lock: false,
+            title: book.metadata.title.clone(),
+            title_lock: false,
+            title_sort: book.metadata.title,
+            title_sort_lock: false,
+            summary: book.metadata.summary.clone(),
+            summary_lock: false,
+            reading_direction: komga_reading_direction(title),
+            reading_direction_lock: false,
+            publisher: String::new(),
+            publisher_lock: false,
+            age_rating: None,
+            age_rating_lock: false,
+            language: String::new(),
+            language_lock: false,
+            genres: vec![],
+            genres_lock: false,
+            tags: book.metadata.tags.clone(),
+            tags_lock: false,
+            total_book_count: Some(1),
+            total_book_count_lock: false,
+            sharing_labels: vec![],
+            sharing_labels_lock: false,
+            links: vec![],
+            links_lock: false,
+            alternate_titles: vec![],
+            alternate_titles_lock: false,
+            created: book.metadata.created.clone(),
+            last_modified: book.metadata.last_modified.clone(),
+        },
+        books_metadata: KomgaBookMetadataAggregationDto {
+            authors: book.metadata.authors,
+            tags: book.metadata.tags,
+            release_date: book.metadata.release_date,
+            summary: book.metadata.summary,
+            summary_number: String::new(),
+            created: book.metadata.created,
+            last_modified: book.metadata.last_modified,
+        },
+        deleted: false,
+        oneshot: true,
+    })
+}
@@ -0,0 +1,174 @@
+use super::{KomgaAuthorDto, KomgaPage, KomgaPageQuery};
+use crate::{models::prelude::*, routes::ErrRsp, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use sea_orm::{ColumnTrait, Condition, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+use std::{collections::BTreeSet, sync::Arc};
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaCollectionDto {
+    pub id: String,
+    pub name: String,
+    pub ordered: bool,
+    pub series_ids: Vec<String>,
+    pub created_date: String,
+    pub last_modified_date: String,
+    pub filtered: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaUserDto {
+    pub id: String,
+    pub email: String,
+    pub roles: Vec<String>,
+    pub shared_all_libraries: bool,
+    pub shared_libraries_ids: Vec<String>,
+    pub labels_allow: Vec<String>,
+    pub labels_exclude: Vec<String>,
+    pub age_restriction: Option<String>,
+}
+
+/// List the tag names.
+#[utoipa::path(get, path = "/komga/api/v1/tags", responses(
+    (status = 200, description = "Fetch tags successful", body = Vec<String>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_tags(
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let tags = Tags::find()
+        .order_by_asc(tags::Column::Name)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .map(|tag| tag.name)
+        .collect::<Vec<_>>();
+
+    Ok((StatusCode::OK, Json(tags)))
+}
+
+/// Titles have no genres or publishers, these lists are always empty.
+#[utoipa::path(get, path = "/komga/api/v1/genres", responses(
+    (status = 200, description = "Fetch genres successful", body = Vec<String>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+))]
+pub async fn get_komga_empty_list() -> impl IntoResponse {
+    (StatusCode::OK, Json(Vec::<String>::new()))
+}
+
+/// List the authors of the titles.
+#[utoipa::path(get, path = "/komga/api/v1/authors", responses(
+    (status = 200, description = "Fetch authors successful", body = Vec<KomgaAuthorDto>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_authors(
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let authors = Titles::find()
+        .filter(titles::Column::Author.is_not_null())
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .filter_map(|title| title.author)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|name| KomgaAuthorDto {
+            name,
+            role: "writer".to_string(),
+        })
+        .collect::<Vec<_>>();
+
+    Ok((StatusCode::OK, Json(authors)))
+}
+
+/// List the collections the user can see, their own and the shared ones.
+#[utoipa::path(get, path = "/komga/api/v1/collections", params(KomgaPageQuery), responses(
+    (status = 200, description = "Fetch collections successful", body = KomgaCollectionPage),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_collections(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Query(page): Query<KomgaPageQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let collections = Collections::find()
+        .filter(
+            Condition::any()
+                .add(collections::Column::UserId.eq(&user.id))
+                .add(collections::Column::IsPublic.eq(true)),
+        )
+        .order_by_asc(collections::Column::Name)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let mut content = Vec::with_capacity(collections.len());
+    for collection in collections {
+        let series_ids = CollectionsTitles::find()
+            .filter(collections_titles::Column::CollectionId.eq(&collection.id))
+            .order_by_asc(collections_titles::Column::Position)
+            .all(&data.db)
+            .await
+            .map_err(ErrRsp::db)?
+            .into_iter()
+            .map(|collection_title| collection_title.title_id)
+            .collect();
+        content.push(KomgaCollectionDto {
+            id: collection.id,
+            name: collection.name,
+            ordered: true,
+            series_ids,
+            created_date: collection.created_at,
+            last_modified_date: collection.updated_at,
+            filtered: false,
+        });
+    }
+
+    Ok((StatusCode::OK, Json(KomgaPage::new(content, &page, true))))
+}
+
+/// Get the logged in user, which Komga clients use to check the credentials.
+#[utoipa::path(get, path = "/komga/api/v1/users/me", responses(
+    (status = 200, description = "Fetch user successful", body = KomgaUserDto),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+))]
+pub async fn get_komga_me(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+) -> impl IntoResponse {
+    let mut roles = vec![
+        "USER".to_string(),
+        "FILE_DOWNLOAD".to_string(),
+        "PAGE_STREAMING".to_string(),
+    ];
+    if data.env.admin_usernames.contains(&user.username) {
+        roles.insert(0, "ADMIN".to_string());
+    }
+
+    (
+        StatusCode::OK,
+        Json(KomgaUserDto {
+            id: user.id,
+            email: user.email,
+            roles,
+            shared_all_libraries: true,
+            shared_libraries_ids: vec![],
+            labels_allow: vec![],
+            labels_exclude: vec![],
+            age_restriction: None,
+        }),
+    )
+}
@@ -0,0 +1,404 @@
+use super::{
+    komga_book, komga_series, update_komga_progress, KomgaPage, KomgaPageQuery, KomgaSeriesDto,
+};
+use crate::{
+    models::prelude::*,
+    routes::{get_thumbnail, ErrRsp},
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use rand::seq::SliceRandom;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, Order, QueryFilter, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+use utoipa::{IntoParams, ToSchema};
+
+/// Filters of the series lists. Lists can be comma separated or repeated, like
+/// Spring takes them.
+#[derive(Debug, Default, IntoParams)]
+pub struct KomgaSeriesQuery {
+    /// Words to search for in the title and author
+    pub search: Option<String>,
+    pub library_id: Vec<String>,
+    pub collection_id: Vec<String>,
+    /// Tag names
+    pub tag: Vec<String>,
+    /// `name,role`
+    pub author: Vec<String>,
+    /// `READ`, `UNREAD` or `IN_PROGRESS`
+    pub read_status: Vec<String>,
+    /// `property,direction`, like `metadata.titleSort,asc`
+    pub sort: Option<String>,
+}
+
+impl KomgaSeriesQuery {
+    fn from_pairs(pairs: Vec<(String, String)>) -> Self {
+        let mut query = Self::default();
+        for (key, value) in pairs {
+            let list = match key.as_str() {
+                "search" => {
+                    query.search = Some(value);
+                    continue;
+                }
+                "sort" => {
+                    query.sort = Some(value);
+                    continue;
+                }
+                // the role comes after a comma
+                "author" => {
+                    query.author.push(value);
+                    continue;
+                }
+                "library_id" => &mut query.library_id,
+                "collection_id" => &mut query.collection_id,
+                "tag" => &mut query.tag,
+                "read_status" => &mut query.read_status,
+                _ => continue,
+            };
+            list.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .map(String::from),
+            );
+        }
+        query
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaTachiyomiReadProgressDto {
+    pub books_count: u32,
+    pub books_read_count: u32,
+    pub books_unread_count: u32,
+    pub books_in_progress_count: u32,
+    pub last_read_continuous_number_sort: f32,
+    pub max_number_sort: f32,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KomgaTachiyomiReadProgressUpdateDto {
+    pub last_book_number_sort_read: f32,
+}
+
+async fn find_series_title(
+    db: &DatabaseConnection,
+    series_id: &str,
+) -> Result<titles::Model, ErrRsp> {
+    Titles::find_by_id(series_id)
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("Series not found."))
+}
+
+/// Find the titles matching a series filter, in order.
+async fn find_series_titles(
+    db: &DatabaseConnection,
+    user_id: &str,
+    query: &KomgaSeriesQuery,
+    default_sort: &str,
+) -> Result<Vec<titles::Model>, ErrRsp> {
+    let mut condition = Condition::all();
+
+    if !query.library_id.is_empty() {
+        condition = condition.add(titles::Column::CategoryId.is_in(query.library_id.iter()));
+    }
+
+    for word in query
+        .search
+        .iter()
+        .flat_map(|search| search.split_whitespace())
+    {
+        condition = condition.add(
+            Condition::any()
+                .add(titles::Column::Title.contains(word))
+                .add(titles::Column::Author.contains(word)),
+        );
+    }
+
+    if !query.author.is_empty() {
+        let names = query
+            .author
+            .iter()
+            .map(|author| {
+                author
+                    .split_once(',')
+                    .map_or(author.as_str(), |(name, _)| name)
+            })
+            .collect::<Vec<_>>();
+        condition = condition.add(titles::Column::Author.is_in(names));
+    }
+
+    if !query.tag.is_empty() {
+        let tag_ids = Tags::find()
+            .filter(tags::Column::Name.is_in(query.tag.iter()))
+            .all(db)
+            .await
+            .map_err(ErrRsp::db)?
+            .into_iter()
+            .map(|tag| tag.id)
+            .collect::<Vec<_>>();
+        let title_ids = TitlesTags::find()
+            .filter(titles_tags::Column::TagId.is_in(tag_ids))
+            .all(db)
+            .await
+            .map_err(ErrRsp::db)?
+            .into_iter()
+            .map(|title_tag| title_tag.title_id)
+            .collect::<Vec<_>>();
+        condition = condition.add(titles::Column::Id.is_in(title_ids));
+    }
+
+    if !query.collection_id.is_empty() {
+        let collection_ids = Collections::find()
+            .filter(collections::Column::Id.is_in(query.collection_id.iter()))
+            .filter(
+                Condition::any()
+                    .add(collections::Column::UserId.eq(user_id))
+                    .add(collections::Column::IsPublic.eq(true)),
+            )
+            .all(db)
+            .await
+            .map_err(ErrRsp::db)?
+            .into_iter()
+            .map(|collection| collection.id)
+            .collect::<Vec<_>>();
+        let title_ids = CollectionsTitles::find()
+            .filter(collections_titles::Column::CollectionId.is_in(collection_ids))
+            .all(db)
+            .await
+            .map_err(ErrRsp::db)?
+            .into_iter()
+            .map(|collection_title| collection_title.title_id)
+            .collect::<Vec<_>>();
+        condition = condition.add(titles::Column::Id.is_in(title_ids));
+    }
+
+    let sort = query.sort.as_deref().unwrap_or(default_sort);
+    let (property, direction) = sort.split_once(',').unwrap_or((sort, "asc"));
+    let order = match direction.eq_ignore_ascii_case("desc") {
+        true => Order::Desc,
+        false => Order::Asc,
+    };
+    let column = match property {
+        "createdDate" | "created" => titles::Column::DateAdded,
+        "lastModifiedDate" | "lastModified" => titles::Column::DateUpdated,
+        "booksMetadata.releaseDate" | "metadata.releaseDate" => titles::Column::Release,
+        _ => titles::Column::Title,
+    };
+
+    let mut titles = Titles::find()
+        .filter(condition)
+        .order_by(column, order)
+        .order_by_asc(titles::Column::Title)
+        .all(db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    if property == "random" {
+        titles.shuffle(&mut rand::thread_rng());
+    }
+
+    if !query.read_status.is_empty() {
+        let completed = Progresses::find()
+            .filter(progresses::Column::UserId.eq(user_id))
+            .all(db)
+            .await
+            .map_err(ErrRsp::db)?
+            .into_iter()
+            .map(|progress| (progress.title_id, progress.completed))
+            .collect::<HashMap<_, _>>();
+        titles.retain(|title| {
+            let status = match completed.get(&title.id) {
+                Some(true) => "READ",
+                Some(false) => "IN_PROGRESS",
+                None => "UNREAD",
+            };
+            query.read_status.iter().any(|wanted| wanted == status)
+        });
+    }
+
+    Ok(titles)
+}
+
+/// Build a page of series out of the titles matching the query.
+async fn series_page(
+    data: &AppState,
+    user_id: &str,
+    page: KomgaPageQuery,
+    pairs: Vec<(String, String)>,
+    default_sort: &str,
+) -> Result<KomgaPage<KomgaSeriesDto>, ErrRsp> {
+    let query = KomgaSeriesQuery::from_pairs(pairs);
+    let titles = find_series_titles(&data.db, user_id, &query, default_sort).await?;
+    let titles = KomgaPage::new(titles, &page, true);
+
+    let mut content = Vec::with_capacity(titles.content.len());
+    for title in &titles.content {
+        content.push(komga_series(&data.db, user_id, title).await?);
+    }
+
+    Ok(titles.with_content(content))
+}
+
+/// List the titles as Komga series.
+#[utoipa::path(get, path = "/komga/api/v1/series", params(KomgaPageQuery, KomgaSeriesQuery), responses(
+    (status = 200, description = "Fetch series successful", body = KomgaSeriesPage),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_series_list(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Query(page): Query<KomgaPageQuery>,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let series = series_page(&data, &user.id, page, pairs, "metadata.titleSort,asc").await?;
+    Ok((StatusCode::OK, Json(series)))
+}
+
+/// List the titles as Komga series, most recently added first.
+#[utoipa::path(get, path = "/komga/api/v1/series/new", params(KomgaPageQuery, KomgaSeriesQuery), responses(
+    (status = 200, description = "Fetch new series successful", body = KomgaSeriesPage),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_series_new(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Query(page): Query<KomgaPageQuery>,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let series = series_page(&data, &user.id, page, pairs, "createdDate,desc").await?;
+    Ok((StatusCode::OK, Json(series)))
+}
+
+/// List the titles as Komga series, most recently updated first. Komga has
+/// this as both `updated` and `latest`.
+#[utoipa::path(get, path = "/komga/api/v1/series/updated", params(KomgaPageQuery, KomgaSeriesQuery), responses(
+    (status = 200, description = "Fetch updated series successful", body = KomgaSeriesPage),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_series_updated(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Query(page): Query<KomgaPageQuery>,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let series = series_page(&data, &user.id, page, pairs, "lastModified,desc").await?;
+    Ok((StatusCode::OK, Json(series)))
+}
+
+/// Get a title as a Komga series.
+#[utoipa::path(get, path = "/komga/api/v1/series/{series_id}", responses(
+    (status = 200, description = "Fetch series successful", body = KomgaSeriesDto),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Series not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_series(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(series_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let title = find_series_title(&data.db, &series_id).await?;
+    let series = komga_series(&data.db, &user.id, &title).await?;
+    Ok((StatusCode::OK, Json(series)))
+}
+
+/// List the books of a series, which is only the title itself.
+#[utoipa::path(get, path = "/komga/api/v1/series/{series_id}/books", params(KomgaPageQuery), responses(
+    (status = 200, description = "Fetch books successful", body = KomgaBookPage),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Series not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_series_books(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(series_id): Path<String>,
+    Query(page): Query<KomgaPageQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let title = find_series_title(&data.db, &series_id).await?;
+    let book = komga_book(&data.db, &user.id, &title).await?;
+    Ok((
+        StatusCode::OK,
+        Json(KomgaPage::new(vec![book], &page, true)),
+    ))
+}
+
+/// Get the cover of a series.
+#[utoipa::path(get, path = "/komga/api/v1/series/{series_id}/thumbnail", responses(
+    (status = 200, description = "Fetch thumbnail successful", body = Vec<u8>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Thumbnail not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_series_thumbnail(
+    state: State<Arc<AppState>>,
+    Path(series_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    get_thumbnail(state, Path(series_id)).await
+}
+
+/// Get the read progress of a series, for the Komga tracker of Tachiyomi.
+#[utoipa::path(get, path = "/komga/api/v2/series/{series_id}/read-progress/tachiyomi", responses(
+    (status = 200, description = "Fetch read progress successful", body = KomgaTachiyomiReadProgressDto),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Series not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_komga_tachiyomi_progress(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(series_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let title = find_series_title(&data.db, &series_id).await?;
+    let series = komga_series(&data.db, &user.id, &title).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(KomgaTachiyomiReadProgressDto {
+            books_count: series.books_count,
+            books_read_count: series.books_read_count,
+            books_unread_count: series.books_unread_count,
+            books_in_progress_count: series.books_in_progress_count,
+            last_read_continuous_number_sort: series.books_read_count as f32,
+            max_number_sort: 1.0,
+        }),
+    ))
+}
+
+/// Mark the books of a series read up to a number, for the Komga tracker of
+/// Tachiyomi.
+#[utoipa::path(put, path = "/komga/api/v2/series/{series_id}/read-progress/tachiyomi", request_body = KomgaTachiyomiReadProgressUpdateDto, responses(
+    (status = 204, description = "Update read progress successful"),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Series not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn put_komga_tachiyomi_progress(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(series_id): Path<String>,
+    Json(query): Json<KomgaTachiyomiReadProgressUpdateDto>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let title = find_series_title(&data.db, &series_id).await?;
+    if query.last_book_number_sort_read >= 1.0 {
+        update_komga_progress(&data.db, &user.id, &title.id, None, true).await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
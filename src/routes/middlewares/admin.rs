@@ -0,0 +1,27 @@
+use crate::{models::prelude::*, routes::ErrRsp, AppState};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+    Extension,
+};
+use std::sync::Arc;
+
+/// Only let through the users listed in `ADMIN_USERNAMES`, must run after `auth`.
+pub async fn admin(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<impl IntoResponse, ErrRsp> {
+    if !data.env.admin_usernames.contains(&user.username) {
+        return Err(ErrRsp::new(
+            StatusCode::FORBIDDEN,
+            "You're not allowed to do this.",
+        ));
+    }
+
+    Ok(next.run(req).await)
+}
@@ -1,31 +1,89 @@
 use crate::{
-    models::{auth::TokenClaims, prelude::*},
-    routes::ErrRsp,
+    config::AuthMode,
+    constants::{api_key_cache_lifetime, api_key_touch_interval},
+    models::{
+        auth::{TokenClaims, TokenClaimsPurpose},
+        prelude::*,
+    },
+    routes::{check_pass, ErrRsp},
     AppState,
 };
 use axum::{
     body::Body,
-    extract::State,
-    http::{header, Request, StatusCode},
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, Request, StatusCode},
     middleware::Next,
     response::IntoResponse,
 };
 use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
 use jsonwebtoken::{decode, DecodingKey, Validation};
 use sea_orm::*;
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, PoisonError},
+};
+use tracing::{info, warn};
 
 pub async fn auth(
     cookie_jar: CookieJar,
     State(data): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
     mut req: Request<Body>,
     next: Next,
 ) -> Result<impl IntoResponse, ErrRsp> {
+    let (user, purpose) = match data.env.auth_mode {
+        AuthMode::Jwt => jwt_auth(&cookie_jar, &data, req.headers()).await?,
+        AuthMode::Proxy => (
+            proxy_auth(&data, &peer, req.headers()).await?,
+            TokenClaimsPurpose::None,
+        ),
+    };
+
+    req.extensions_mut().insert(user);
+    req.extensions_mut().insert(purpose);
+    Ok(next.run(req).await)
+}
+
+/// Like `auth`, but also takes API keys and HTTP basic auth. Only for the OPDS
+/// and Komga routes and the files their feeds link to, a key mustn't be able to
+/// manage the account it belongs to.
+pub async fn feed_auth(
+    cookie_jar: CookieJar,
+    State(data): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let (user, purpose) = match data.env.auth_mode {
+        AuthMode::Jwt => match credential_auth(&data, req.headers()).await? {
+            Some(user) => (user, TokenClaimsPurpose::None),
+            None => jwt_auth(&cookie_jar, &data, req.headers()).await?,
+        },
+        AuthMode::Proxy => (
+            proxy_auth(&data, &peer, req.headers()).await?,
+            TokenClaimsPurpose::None,
+        ),
+    };
+
+    req.extensions_mut().insert(user);
+    req.extensions_mut().insert(purpose);
+    Ok(next.run(req).await)
+}
+
+/// Authenticate with the JWT from the `token` cookie or the `Authorization` header.
+async fn jwt_auth(
+    cookie_jar: &CookieJar,
+    data: &Arc<AppState>,
+    headers: &HeaderMap,
+) -> Result<(users::Model, TokenClaimsPurpose), ErrRsp> {
     let token = cookie_jar
         .get("token")
         .map(|cookie| cookie.value().to_string())
         .or_else(|| {
-            req.headers()
+            headers
                 .get(header::AUTHORIZATION)
                 .and_then(|auth_header| auth_header.to_str().ok())
                 .and_then(|auth_value| {
@@ -51,14 +109,232 @@ pub async fn auth(
         .await
         .map_err(ErrRsp::db)?;
 
+    let user = user.ok_or_else(|| {
+        ErrRsp::new(
+            StatusCode::UNAUTHORIZED,
+            "The user belonging to this token no longer exists.",
+        )
+    })?;
+
+    if let Some(fingerprint) = &claims.fingerprint {
+        if *fingerprint != TokenClaims::user_fingerprint(&user) {
+            return Err(ErrRsp::new(
+                StatusCode::UNAUTHORIZED,
+                "This token has already been used.",
+            ));
+        }
+    }
+
+    Ok((user, claims.purpose.unwrap_or_default()))
+}
+
+/// Authenticate with an API key from the `X-API-Key` header, or with HTTP
+/// basic auth, for the clients that can't keep a JWT around like OPDS readers.
+/// `None` if the request has neither.
+async fn credential_auth(
+    data: &Arc<AppState>,
+    headers: &HeaderMap,
+) -> Result<Option<users::Model>, ErrRsp> {
+    let api_key = headers
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok());
+    if let Some(api_key) = api_key {
+        return api_key_auth(data, api_key)
+            .await?
+            .map(Some)
+            .ok_or_else(|| ErrRsp::new(StatusCode::UNAUTHORIZED, "Invalid API key."));
+    }
+
+    let basic = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|auth_header| auth_header.to_str().ok())
+        .and_then(|auth_value| auth_value.strip_prefix("Basic "));
+    let Some(basic) = basic else {
+        return Ok(None);
+    };
+
+    let invalid = || ErrRsp::new(StatusCode::UNAUTHORIZED, "Invalid username or password.");
+    let credentials = STANDARD
+        .decode(basic.trim())
+        .ok()
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .ok_or_else(invalid)?;
+    let (username, password) = credentials.split_once(':').ok_or_else(invalid)?;
+
+    let user = Users::find()
+        .filter(users::Column::Username.eq(username))
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(invalid)?;
+
+    // Readers that only know basic auth can use an API key as the password,
+    // it's tried first since verified keys are cached
+    match api_key_auth(data, password).await? {
+        Some(key_user) if key_user.id == user.id => return Ok(Some(user)),
+        Some(_) => return Err(invalid()),
+        None => {}
+    }
+
+    match check_pass(&user.password, &password.to_string()) {
+        true => Ok(Some(user)),
+        false => Err(invalid()),
+    }
+}
+
+/// API keys verified lately, so a reader fetching page after page doesn't
+/// hash its key on every request.
+#[derive(Debug, Default)]
+pub struct ApiKeyCache(std::sync::Mutex<HashMap<String, VerifiedApiKey>>);
+
+#[derive(Debug)]
+pub struct VerifiedApiKey {
+    secret: String,
+    user_id: String,
+    verified_at: DateTime<Utc>,
+}
+
+impl ApiKeyCache {
+    /// The owner of the key, if it was verified less than
+    /// `api_key_cache_lifetime()` ago.
+    fn get(&self, id: &str, secret: &str) -> Option<String> {
+        let mut keys = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Utc::now();
+        keys.retain(|_, key| now - key.verified_at < api_key_cache_lifetime());
+        keys.get(id)
+            .filter(|key| key.secret == secret)
+            .map(|key| key.user_id.clone())
+    }
+
+    fn insert(&self, id: &str, secret: &str, user_id: &str) {
+        let mut keys = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        keys.insert(
+            id.to_string(),
+            VerifiedApiKey {
+                secret: secret.to_string(),
+                user_id: user_id.to_string(),
+                verified_at: Utc::now(),
+            },
+        );
+    }
+
+    /// Stop accepting a deleted key right away.
+    pub fn forget(&self, id: &str) {
+        let mut keys = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        keys.remove(id);
+    }
+}
+
+/// Find the owner of an API key, see `routes/user/api_keys.rs`. `None` if it
+/// isn't a valid key.
+async fn api_key_auth(data: &Arc<AppState>, api_key: &str) -> Result<Option<users::Model>, ErrRsp> {
+    let Some((id, secret)) = api_key.trim().split_once('.') else {
+        return Ok(None);
+    };
+
+    let user_id = match data.api_keys.get(id, secret) {
+        Some(user_id) => user_id,
+        None => {
+            let Some(api_key) = ApiKeys::find_by_id(id)
+                .one(&data.db)
+                .await
+                .map_err(ErrRsp::db)?
+            else {
+                return Ok(None);
+            };
+            if !check_pass(&api_key.secret_hash, &secret.to_string()) {
+                return Ok(None);
+            }
+            data.api_keys.insert(id, secret, &api_key.user_id);
+
+            let user_id = api_key.user_id.clone();
+            let now = Utc::now();
+            let recently_used = api_key
+                .last_used_at
+                .as_deref()
+                .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+                .is_some_and(|at| now - at.with_timezone(&Utc) < api_key_touch_interval());
+            if !recently_used {
+                let mut active_api_key: api_keys::ActiveModel = api_key.into();
+                active_api_key.last_used_at = Set(Some(now.to_rfc3339()));
+                active_api_key.update(&data.db).await.map_err(ErrRsp::db)?;
+            }
+            user_id
+        }
+    };
+
+    Users::find_by_id(user_id)
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)
+}
+
+/// Authenticate with the username header set by a trusted reverse proxy,
+/// creating the user on first sight, which also needs the email header.
+async fn proxy_auth(
+    data: &Arc<AppState>,
+    peer: &SocketAddr,
+    headers: &HeaderMap,
+) -> Result<users::Model, ErrRsp> {
+    if !data.env.proxy_trusted_ips.contains(&peer.ip()) {
+        warn!("rejected proxy auth from untrusted address {}", peer.ip());
+        return Err(ErrRsp::new(
+            StatusCode::UNAUTHORIZED,
+            "Requests must come through the authentication proxy.",
+        ));
+    }
+
+    let read_header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let username = read_header(&data.env.proxy_user_header).ok_or_else(|| {
+        ErrRsp::new(
+            StatusCode::UNAUTHORIZED,
+            format!("Missing {} header.", data.env.proxy_user_header),
+        )
+    })?;
+    let user = Users::find()
+        .filter(users::Column::Username.eq(&username))
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
     if let Some(user) = user {
-        req.extensions_mut().insert(user);
-        req.extensions_mut()
-            .insert(claims.purpose.unwrap_or_default());
-        return Ok(next.run(req).await);
+        return Ok(user);
     }
-    Err(ErrRsp::new(
-        StatusCode::UNAUTHORIZED,
-        "The user belonging to this token no longer exists.",
-    ))
+
+    // accounts need an email, to be told apart and reached
+    let email = read_header(&data.env.proxy_email_header)
+        .map(|email| email.to_ascii_lowercase())
+        .ok_or_else(|| {
+            ErrRsp::new(
+                StatusCode::UNAUTHORIZED,
+                format!("Missing {} header.", data.env.proxy_email_header),
+            )
+        })?;
+
+    // The proxy vouches for the identity, so the account is verified right
+    // away, and the empty password hash makes password login impossible.
+    let now = chrono::Utc::now().to_string();
+    let user = users::ActiveModel {
+        id: Set(uuid::Uuid::new_v4().to_string()),
+        username: Set(username.clone()),
+        email: Set(email),
+        created_at: Set(now.clone()),
+        updated_at: Set(now),
+        password: Set(String::new()),
+        is_verified: Set(true),
+        ..Default::default()
+    }
+    .insert(&data.db)
+    .await
+    .map_err(|e| ErrRsp::internal(format!("Can't insert user to DB: {}", e)))?;
+
+    info!("created user {} from proxy headers", username);
+    Ok(user)
 }
@@ -1,17 +1,26 @@
+pub mod admin;
 pub mod auth;
 pub mod file;
 pub mod index;
+pub mod komga;
 pub mod middlewares;
+pub mod opds;
 pub mod user;
 pub mod utils;
 
-pub use self::{auth::*, file::*, index::*, user::*, utils::*};
-pub use middlewares::auth::auth;
+pub use self::{admin::*, auth::*, file::*, index::*, komga::*, opds::*, user::*, utils::*};
+pub use middlewares::{
+    admin::admin,
+    auth::{auth, feed_auth, ApiKeyCache},
+};
 use sea_orm::DbErr;
 
 use crate::{
     constants::{blurhash_dimension_cap, ratio_percision},
-    models::categories::Model as Categories,
+    models::{
+        categories::Model as Categories,
+        reader::{ReaderSettings, ReadingDirection, ReadingLayout, ReadingZoom},
+    },
 };
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use axum::{
@@ -28,6 +37,7 @@ pub struct ErrorResponseBody {
     pub message: String,
 }
 
+#[derive(Debug)]
 pub struct ErrRsp {
     status: StatusCode,
     body: Json<ErrorResponseBody>,
@@ -69,6 +79,14 @@ impl ErrRsp {
             "You're not logged in, please provide a token.",
         )
     }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn message(&self) -> &str {
+        &self.body.message
+    }
 }
 impl IntoResponse for ErrRsp {
     fn into_response(self) -> Response {
@@ -122,6 +140,14 @@ impl GenericRsp {
         (
             name = "file",
             description = "all the routes related to file fetching."
+        ),
+        (
+            name = "opds",
+            description = "OPDS 1.2 catalog with the Page Streaming Extension."
+        ),
+        (
+            name = "komga",
+            description = "Komga compatible API, enabled with KOMGA_COMPAT."
         )
     ),
     paths(
@@ -141,20 +167,106 @@ impl GenericRsp {
         user::post_verify,
         user::put_bookmark,
         user::put_favorite,
+        user::put_rating,
+        user::delete_rating,
+        user::put_reader_preference,
+        user::delete_reader_preference,
+        user::get_bookmarks,
+        user::post_bookmark,
+        user::put_bookmark_note,
+        user::delete_page_bookmark,
+        user::get_bookmark_page,
+        user::get_collections,
+        user::post_collection,
+        user::get_collection,
+        user::put_collection,
+        user::delete_collection,
+        user::put_collection_title,
+        user::delete_collection_title,
+        user::put_collection_order,
+        user::get_smart_collections,
+        user::post_smart_collection,
+        user::get_smart_collection,
+        user::put_smart_collection,
+        user::delete_smart_collection,
         user::put_progress,
+        user::get_progress,
+        user::get_progresses,
+        user::post_sync_progress,
+        user::get_history,
+        user::delete_history,
+        user::get_user_stats,
+        user::get_verify_link,
+        user::get_reset_link,
+        user::post_reset_link,
+        user::get_delete_link,
+        user::post_delete_link,
+        user::get_api_keys,
+        user::post_api_key,
+        user::delete_api_key,
 
         index::get_categories,
         index::post_filter,
+        index::post_search,
         index::get_title,
+        index::get_continue,
+        index::get_reviews,
+        index::get_home,
+        index::get_similar,
+        index::get_for_you,
 
         utils::get_status,
         utils::post_status,
         utils::get_tags,
         utils::get_scanning_progress,
         utils::get_ssim_eval,
+        utils::post_ssim_eval,
+        utils::get_stats,
 
         file::get_page,
         file::get_thumbnail,
+        file::get_title_download,
+        file::get_signed_pages,
+        file::get_signed_page,
+        file::get_category_download,
+        file::get_collection_download,
+
+        admin::get_ssim_report,
+        admin::get_ssim_export,
+        admin::get_duplicates,
+
+        opds::get_opds_root,
+        opds::get_opds_categories,
+        opds::get_opds_category,
+        opds::get_opds_new,
+        opds::get_opds_updated,
+        opds::get_opds_search_description,
+        opds::get_opds_search,
+        opds::get_opds_title_file,
+        opds::get_opds_page,
+
+        komga::get_komga_libraries,
+        komga::get_komga_library,
+        komga::get_komga_series_list,
+        komga::get_komga_series_new,
+        komga::get_komga_series_updated,
+        komga::get_komga_series,
+        komga::get_komga_series_books,
+        komga::get_komga_series_thumbnail,
+        komga::get_komga_tachiyomi_progress,
+        komga::put_komga_tachiyomi_progress,
+        komga::get_komga_book,
+        komga::get_komga_book_pages,
+        komga::get_komga_book_page,
+        komga::get_komga_book_thumbnail,
+        komga::get_komga_book_file,
+        komga::patch_komga_read_progress,
+        komga::delete_komga_read_progress,
+        komga::get_komga_tags,
+        komga::get_komga_empty_list,
+        komga::get_komga_authors,
+        komga::get_komga_collections,
+        komga::get_komga_me,
     ),
     components(schemas(
         // Auth
@@ -166,14 +278,55 @@ impl GenericRsp {
         DeleteRequest,
         ModifyRequest,
         ResetRequest,
+        ResetFormRequest,
+        DeleteFormRequest,
+        BookmarkRequest,
+        BookmarkNoteRequest,
+        BookmarkResponseBody,
+        TitleBookmarksResponseBody,
+        BookmarksResponseBody,
+        CollectionRequest,
+        ModifyCollectionRequest,
+        CollectionOrderRequest,
+        CollectionResponseBody,
+        CollectionsResponseBody,
+        CollectionDetailResponseBody,
+        RatingRequest,
+        SmartCollectionRequest,
+        ModifySmartCollectionRequest,
+        SmartCollectionResponseBody,
+        SmartCollectionsResponseBody,
+        SmartCollectionTitlesResponseBody,
+        ProgressResponseBody,
+        ProgressesResponseBody,
+        ProgressSyncRequest,
+        ProgressUpdate,
+        SyncStrategy,
+        HistoryEntry,
+        HistoryResponseBody,
+        ApiKeyRequest,
+        ApiKeyResponseBody,
+        ApiKeysResponseBody,
+        ApiKeyCreatedResponseBody,
 
         // Index
         Categories,
         CategoriesResponseBody,
         TitleResponseBody,
         FilterRequest,
+        SearchRequest,
         FilterResponseBody,
         FilterTitleResponseBody,
+        ReviewResponseBody,
+        ReviewsResponseBody,
+        HomeResponseBody,
+        UnavailableSmartCollection,
+        SimilarTitleResponseBody,
+        SimilarResponseBody,
+        ReaderSettings,
+        ReadingDirection,
+        ReadingLayout,
+        ReadingZoom,
 
         // Utils
         StatusRequest,
@@ -183,6 +336,51 @@ impl GenericRsp {
         ScanningProgressResponseBody,
         SsimEvalBody,
         SsimEvalTitle,
+        SsimFeedbackRequest,
+        StatsResponseBody,
+        PeriodStats,
+        RankedStats,
+        CategoryStats,
+
+        // File
+        DownloadFormat,
+        SignedPage,
+        SignedPagesResponseBody,
+        SpreadMode,
+        PageHalf,
+
+        // Admin
+        SsimReportResponseBody,
+        SsimThresholdStats,
+        SsimDatasetResponseBody,
+        SsimDatasetEntry,
+        DuplicatesResponseBody,
+        DuplicateGroup,
+        DuplicateTitle,
+
+        // Komga
+        KomgaLibraryDto,
+        KomgaSeriesPage,
+        KomgaBookPage,
+        KomgaCollectionPage,
+        KomgaPageable,
+        KomgaSort,
+        KomgaSeriesDto,
+        KomgaSeriesMetadataDto,
+        KomgaBookMetadataAggregationDto,
+        KomgaBookDto,
+        KomgaMediaDto,
+        KomgaBookMetadataDto,
+        KomgaReadProgressDto,
+        KomgaReadProgressUpdateDto,
+        KomgaTachiyomiReadProgressDto,
+        KomgaTachiyomiReadProgressUpdateDto,
+        KomgaPageDto,
+        KomgaAuthorDto,
+        KomgaWebLinkDto,
+        KomgaAlternateTitleDto,
+        KomgaCollectionDto,
+        KomgaUserDto,
 
         // Other
         GenericResponseBody,
@@ -200,6 +398,61 @@ fn check_pass(real: &str, input: &String) -> bool {
     }
 }
 
+fn check_pass_strength(password: &str) -> Result<(), ErrRsp> {
+    let has_uppercase = password.chars().any(|c| c.is_uppercase());
+    let has_lowercase = password.chars().any(|c| c.is_lowercase());
+    let has_numeric = password.chars().any(|c| c.is_numeric());
+    let has_special = password.chars().any(|c| c.is_ascii_punctuation());
+    let has_valid_length = password.len() >= 8 && password.len() <= 100;
+    if !(has_uppercase && has_lowercase && has_numeric && has_special && has_valid_length) {
+        return Err(ErrRsp::bad_request(
+            "Password must be between 8 and 100 characters long and contain at least one uppercase letter, one lowercase letter, one number and one special character.",
+        ));
+    }
+    Ok(())
+}
+
+/// Escape text for XML content and attribute values.
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent-encode a query parameter or header value.
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Release dates are free-form, this reads `YYYY-MM-DD` or a leading year.
+fn release_date(release: Option<&str>) -> Option<chrono::NaiveDate> {
+    let release = release?.trim();
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(release, "%Y-%m-%d") {
+        return Some(date);
+    }
+    release
+        .get(..4)
+        .and_then(|year| year.parse::<i32>().ok())
+        .and_then(|year| chrono::NaiveDate::from_ymd_opt(year, 1, 1))
+}
+
 fn calculate_dimension(ratio: u32) -> (u32, u32) {
     let max_dimension = blurhash_dimension_cap();
     let ratio = ratio as f32 / ratio_percision() as f32;
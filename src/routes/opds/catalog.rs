@@ -0,0 +1,228 @@
+use super::{title_entry, Entry, Feed, Link, OpdsPageQuery, ACQUISITION_FEED, NAVIGATION_FEED};
+use crate::{constants::opds_page_size, models::prelude::*, routes::ErrRsp, AppState};
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Extension,
+};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Select,
+};
+use std::sync::Arc;
+
+/// Fill an acquisition feed with a page of `select`.
+async fn title_feed(
+    db: &DatabaseConnection,
+    user_id: &str,
+    feed: Feed,
+    href: &str,
+    select: Select<Titles>,
+    page: u64,
+) -> Result<Feed, ErrRsp> {
+    let paginator = select.paginate(db, opds_page_size());
+    let total = paginator.num_items().await.map_err(ErrRsp::db)?;
+    // past the last page, without letting the offset overflow
+    let titles = match page
+        .checked_mul(opds_page_size())
+        .is_some_and(|start| start < total)
+    {
+        true => paginator.fetch_page(page).await.map_err(ErrRsp::db)?,
+        false => vec![],
+    };
+
+    let mut entries = Vec::with_capacity(titles.len());
+    for title in titles {
+        entries.push(title_entry(db, user_id, title).await?);
+    }
+
+    Ok(feed.entries(entries).paginate(href, page, total))
+}
+
+fn navigation_entry(id: &str, title: &str, content: &str, href: &str, kind: &str) -> Entry {
+    Entry::new(
+        format!("urn:yomuyume:{}", id),
+        title,
+        chrono::Utc::now().to_rfc3339(),
+    )
+    .content(content)
+    .link(Link::new("subsection", href, kind).title(title))
+}
+
+/// The root of the OPDS catalog.
+#[utoipa::path(get, path = "/opds", responses(
+    (status = 200, description = "Fetch OPDS catalog successful", content_type = "application/atom+xml", body = String),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+))]
+pub async fn get_opds_root(State(data): State<Arc<AppState>>) -> impl IntoResponse {
+    Feed::new(
+        "urn:yomuyume:root",
+        &data.env.app_name,
+        "/opds",
+        NAVIGATION_FEED,
+    )
+    .entry(navigation_entry(
+        "categories",
+        "Categories",
+        "Browse the library by category.",
+        "/opds/categories",
+        NAVIGATION_FEED,
+    ))
+    .entry(
+        navigation_entry(
+            "new",
+            "New",
+            "Recently added titles.",
+            "/opds/new",
+            ACQUISITION_FEED,
+        )
+        .link(Link::new(
+            "http://opds-spec.org/sort/new",
+            "/opds/new",
+            ACQUISITION_FEED,
+        )),
+    )
+    .entry(navigation_entry(
+        "updated",
+        "Updated",
+        "Recently updated titles.",
+        "/opds/updated",
+        ACQUISITION_FEED,
+    ))
+}
+
+/// List the categories of the library.
+#[utoipa::path(get, path = "/opds/categories", responses(
+    (status = 200, description = "Fetch OPDS categories successful", content_type = "application/atom+xml", body = String),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_opds_categories(
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let categories = Categories::find()
+        .order_by_asc(categories::Column::Name)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let entries = categories
+        .into_iter()
+        .map(|category| {
+            navigation_entry(
+                &format!("category:{}", category.id),
+                &category.name,
+                &category.description.unwrap_or_default(),
+                &format!("/opds/categories/{}", category.id),
+                ACQUISITION_FEED,
+            )
+        })
+        .collect();
+
+    Ok(Feed::new(
+        "urn:yomuyume:categories",
+        "Categories",
+        "/opds/categories",
+        NAVIGATION_FEED,
+    )
+    .entries(entries))
+}
+
+/// List the titles of a category, alphabetically.
+#[utoipa::path(get, path = "/opds/categories/{category_id}", params(OpdsPageQuery), responses(
+    (status = 200, description = "Fetch OPDS category successful", content_type = "application/atom+xml", body = String),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Category not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_opds_category(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(category_id): Path<String>,
+    Query(query): Query<OpdsPageQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let category = Categories::find_by_id(&category_id)
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("Category not found."))?;
+
+    let href = format!("/opds/categories/{}", category.id);
+    let feed = Feed::new(
+        format!("urn:yomuyume:category:{}", category.id),
+        category.name,
+        &href,
+        ACQUISITION_FEED,
+    );
+    let select = Titles::find()
+        .filter(titles::Column::CategoryId.eq(&category.id))
+        .order_by_asc(titles::Column::Title);
+
+    title_feed(
+        &data.db,
+        &user.id,
+        feed,
+        &href,
+        select,
+        query.page.unwrap_or(0),
+    )
+    .await
+}
+
+/// List the titles, most recently added first.
+#[utoipa::path(get, path = "/opds/new", params(OpdsPageQuery), responses(
+    (status = 200, description = "Fetch OPDS new titles successful", content_type = "application/atom+xml", body = String),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_opds_new(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Query(query): Query<OpdsPageQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let feed = Feed::new("urn:yomuyume:new", "New", "/opds/new", ACQUISITION_FEED);
+    let select = Titles::find()
+        .order_by_desc(titles::Column::DateAdded)
+        .order_by_asc(titles::Column::Title);
+
+    title_feed(
+        &data.db,
+        &user.id,
+        feed,
+        "/opds/new",
+        select,
+        query.page.unwrap_or(0),
+    )
+    .await
+}
+
+/// List the titles, most recently updated first.
+#[utoipa::path(get, path = "/opds/updated", params(OpdsPageQuery), responses(
+    (status = 200, description = "Fetch OPDS updated titles successful", content_type = "application/atom+xml", body = String),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_opds_updated(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Query(query): Query<OpdsPageQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let feed = Feed::new(
+        "urn:yomuyume:updated",
+        "Updated",
+        "/opds/updated",
+        ACQUISITION_FEED,
+    );
+    let select = Titles::find()
+        .order_by_desc(titles::Column::DateUpdated)
+        .order_by_asc(titles::Column::Title);
+
+    title_feed(
+        &data.db,
+        &user.id,
+        feed,
+        "/opds/updated",
+        select,
+        query.page.unwrap_or(0),
+    )
+    .await
+}
@@ -0,0 +1,349 @@
+mod catalog;
+mod search;
+mod stream;
+
+pub use catalog::*;
+pub use search::*;
+pub use stream::*;
+
+use crate::{
+    constants::opds_page_size,
+    models::prelude::*,
+    routes::{find_page_count, xml_escape, ErrRsp},
+    AppState,
+};
+use axum::{
+    extract::State,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Deserialize;
+use std::{path::PathBuf, sync::Arc};
+use utoipa::IntoParams;
+
+pub const NAVIGATION_FEED: &str = "application/atom+xml;profile=opds-catalog;kind=navigation";
+pub const ACQUISITION_FEED: &str = "application/atom+xml;profile=opds-catalog;kind=acquisition";
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct OpdsPageQuery {
+    /// 0-based page of the feed
+    pub page: Option<u64>,
+}
+
+/// Ask for basic auth when unauthorized, OPDS readers have no other way to
+/// log in.
+pub async fn opds_challenge(State(data): State<Arc<AppState>>, mut response: Response) -> Response {
+    if response.status() == StatusCode::UNAUTHORIZED {
+        let challenge = format!("Basic realm=\"{}\"", data.env.app_name.replace('"', "'"));
+        if let Ok(challenge) = HeaderValue::from_str(&challenge) {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, challenge);
+        }
+    }
+    response
+}
+
+/// Titles store their dates as unix timestamps, Atom wants RFC 3339.
+fn atom_date(date: &str) -> String {
+    date.parse::<i64>()
+        .ok()
+        .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
+        .map(|date| date.to_rfc3339())
+        .unwrap_or_else(|| date.to_string())
+}
+
+fn image_mime(path: &str) -> String {
+    format!(
+        "image/{}",
+        PathBuf::from(path)
+            .extension()
+            .map(|s| s.to_str().unwrap_or(""))
+            .unwrap_or("")
+            .to_ascii_lowercase()
+    )
+}
+
+pub struct Link {
+    rel: &'static str,
+    href: String,
+    kind: String,
+    title: Option<String>,
+    /// Extension attributes, like `pse:count`
+    attributes: Vec<(&'static str, String)>,
+}
+
+impl Link {
+    pub fn new<S: Into<String>, T: Into<String>>(rel: &'static str, href: S, kind: T) -> Self {
+        Self {
+            rel,
+            href: href.into(),
+            kind: kind.into(),
+            title: None,
+            attributes: vec![],
+        }
+    }
+
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn attribute<S: Into<String>>(mut self, name: &'static str, value: S) -> Self {
+        self.attributes.push((name, value.into()));
+        self
+    }
+
+    fn render(&self, xml: &mut String) {
+        xml.push_str(&format!(
+            "<link rel=\"{}\" href=\"{}\" type=\"{}\"",
+            self.rel,
+            xml_escape(&self.href),
+            xml_escape(&self.kind)
+        ));
+        if let Some(title) = &self.title {
+            xml.push_str(&format!(" title=\"{}\"", xml_escape(title)));
+        }
+        for (name, value) in &self.attributes {
+            xml.push_str(&format!(" {}=\"{}\"", name, xml_escape(value)));
+        }
+        xml.push_str("/>\n");
+    }
+}
+
+pub struct Entry {
+    id: String,
+    title: String,
+    updated: String,
+    author: Option<String>,
+    content: Option<String>,
+    links: Vec<Link>,
+}
+
+impl Entry {
+    pub fn new<S: Into<String>, T: Into<String>>(id: S, title: T, updated: String) -> Self {
+        Self {
+            id: id.into(),
+            title: title.into(),
+            updated,
+            author: None,
+            content: None,
+            links: vec![],
+        }
+    }
+
+    pub fn content<S: Into<String>>(mut self, content: S) -> Self {
+        self.content = Some(content.into());
+        self
+    }
+
+    pub fn link(mut self, link: Link) -> Self {
+        self.links.push(link);
+        self
+    }
+
+    fn render(&self, xml: &mut String) {
+        xml.push_str("<entry>\n");
+        xml.push_str(&format!("<id>{}</id>\n", xml_escape(&self.id)));
+        xml.push_str(&format!("<title>{}</title>\n", xml_escape(&self.title)));
+        xml.push_str(&format!("<updated>{}</updated>\n", self.updated));
+        if let Some(author) = &self.author {
+            xml.push_str(&format!(
+                "<author><name>{}</name></author>\n",
+                xml_escape(author)
+            ));
+        }
+        if let Some(content) = &self.content {
+            xml.push_str(&format!(
+                "<content type=\"text\">{}</content>\n",
+                xml_escape(content)
+            ));
+        }
+        for link in &self.links {
+            link.render(xml);
+        }
+        xml.push_str("</entry>\n");
+    }
+}
+
+/// An OPDS 1.2 catalog feed, with the Page Streaming Extension namespace.
+pub struct Feed {
+    id: String,
+    title: String,
+    kind: &'static str,
+    links: Vec<Link>,
+    entries: Vec<Entry>,
+    /// Total entries and the index of the first one on this page
+    total: Option<(u64, u64)>,
+}
+
+impl Feed {
+    /// `href` is the path of the feed itself, `kind` is `NAVIGATION_FEED` or
+    /// `ACQUISITION_FEED`.
+    pub fn new<S: Into<String>, T: Into<String>>(
+        id: S,
+        title: T,
+        href: &str,
+        kind: &'static str,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            title: title.into(),
+            kind,
+            links: vec![
+                Link::new("self", href, kind),
+                Link::new("start", "/opds", NAVIGATION_FEED),
+                Link::new(
+                    "search",
+                    "/opds/search.xml",
+                    "application/opensearchdescription+xml",
+                ),
+            ],
+            entries: vec![],
+            total: None,
+        }
+    }
+
+    pub fn entry(mut self, entry: Entry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    pub fn entries(mut self, entries: Vec<Entry>) -> Self {
+        self.entries.extend(entries);
+        self
+    }
+
+    /// Add the links to the other pages of `href`, holding `total` entries.
+    pub fn paginate(mut self, href: &str, page: u64, total: u64) -> Self {
+        let page_size = opds_page_size();
+        let last = total.saturating_sub(1) / page_size;
+        let separator = if href.contains('?') { '&' } else { '?' };
+        let page_href = |page: u64| format!("{}{}page={}", href, separator, page);
+
+        self.links.push(Link::new("first", page_href(0), self.kind));
+        if page > 0 {
+            self.links
+                .push(Link::new("previous", page_href(page - 1), self.kind));
+        }
+        if page < last {
+            self.links
+                .push(Link::new("next", page_href(page + 1), self.kind));
+        }
+        self.links
+            .push(Link::new("last", page_href(last), self.kind));
+        self.total = Some((total, page.saturating_mul(page_size)));
+        self
+    }
+
+    fn render(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(concat!(
+            "<feed xmlns=\"http://www.w3.org/2005/Atom\"",
+            " xmlns:opds=\"http://opds-spec.org/2010/catalog\"",
+            " xmlns:pse=\"http://vaemendis.net/opds-pse/ns\"",
+            " xmlns:opensearch=\"http://a9.com/-/spec/opensearch/1.1/\"",
+            " xmlns:dcterms=\"http://purl.org/dc/terms/\">\n"
+        ));
+        xml.push_str(&format!("<id>{}</id>\n", xml_escape(&self.id)));
+        xml.push_str(&format!("<title>{}</title>\n", xml_escape(&self.title)));
+        xml.push_str(&format!(
+            "<updated>{}</updated>\n",
+            chrono::Utc::now().to_rfc3339()
+        ));
+        if let Some((total, start)) = self.total {
+            xml.push_str(&format!(
+                "<opensearch:totalResults>{}</opensearch:totalResults>\n",
+                total
+            ));
+            xml.push_str(&format!(
+                "<opensearch:itemsPerPage>{}</opensearch:itemsPerPage>\n",
+                opds_page_size()
+            ));
+            xml.push_str(&format!(
+                "<opensearch:startIndex>{}</opensearch:startIndex>\n",
+                start.saturating_add(1)
+            ));
+        }
+        for link in &self.links {
+            link.render(&mut xml);
+        }
+        for entry in &self.entries {
+            entry.render(&mut xml);
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+}
+
+impl IntoResponse for Feed {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, self.kind)],
+            self.render(),
+        )
+            .into_response()
+    }
+}
+
+/// Build the acquisition entry of a title, with its cover, download and page
+/// streaming links as seen by `user_id`.
+pub async fn title_entry(
+    db: &DatabaseConnection,
+    user_id: &str,
+    title: titles::Model,
+) -> Result<Entry, ErrRsp> {
+    let page_count = find_page_count(db, &title.id).await;
+    let progress = Progresses::find()
+        .filter(progresses::Column::UserId.eq(user_id))
+        .filter(progresses::Column::TitleId.eq(&title.id))
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?;
+    let thumbnail = Thumbnails::find_by_id(&title.id)
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let mut entry = Entry::new(
+        format!("urn:yomuyume:title:{}", title.id),
+        title.title,
+        atom_date(&title.date_updated),
+    );
+    entry.author = title.author;
+    entry.content = title.description;
+
+    if let Some(thumbnail) = thumbnail {
+        let href = format!("/api/file/thumbnail/{}", title.id);
+        let mime = image_mime(&thumbnail.path);
+        entry = entry
+            .link(Link::new("http://opds-spec.org/image", &href, &mime))
+            .link(Link::new(
+                "http://opds-spec.org/image/thumbnail",
+                href,
+                mime,
+            ));
+    }
+
+    let mut stream = Link::new(
+        "http://vaemendis.net/opds-pse/stream",
+        format!("/opds/titles/{}/pages/{{pageNumber}}", title.id),
+        "image/jpeg",
+    )
+    .attribute("pse:count", page_count.to_string());
+    if let Some(progress) = progress {
+        stream = stream
+            .attribute("pse:lastRead", progress.page.to_string())
+            .attribute("pse:lastReadDate", atom_date(&progress.last_read_at));
+    }
+
+    Ok(entry
+        .link(Link::new(
+            "http://opds-spec.org/acquisition",
+            format!("/opds/titles/{}/file", title.id),
+            "application/vnd.comicbook+zip",
+        ))
+        .link(stream))
+}
@@ -0,0 +1,104 @@
+use super::{title_entry, Feed, ACQUISITION_FEED};
+use crate::{
+    constants::opds_page_size,
+    models::prelude::*,
+    routes::{filter_title_models, percent_encode, xml_escape, ErrRsp, FilterRequest},
+    AppState,
+};
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use utoipa::IntoParams;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct OpdsSearchQuery {
+    /// Words to search for in the title, description and author
+    pub q: Option<String>,
+    /// 0-based page of the feed
+    pub page: Option<u64>,
+}
+
+/// The OpenSearch description of the catalog search.
+#[utoipa::path(get, path = "/opds/search.xml", responses(
+    (status = 200, description = "Fetch OpenSearch description successful", content_type = "application/opensearchdescription+xml", body = String),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+))]
+pub async fn get_opds_search_description(State(data): State<Arc<AppState>>) -> impl IntoResponse {
+    // OpenSearch templates have no base to resolve against, unlike the feeds
+    let xml = format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<OpenSearchDescription xmlns=\"http://a9.com/-/spec/opensearch/1.1/\">\n",
+            "<ShortName>{}</ShortName>\n",
+            "<Description>Search the titles of {}</Description>\n",
+            "<InputEncoding>UTF-8</InputEncoding>\n",
+            "<OutputEncoding>UTF-8</OutputEncoding>\n",
+            "<Url type=\"{}\" template=\"{}/opds/search?q={{searchTerms}}\"/>\n",
+            "</OpenSearchDescription>\n"
+        ),
+        xml_escape(&data.env.app_name),
+        xml_escape(&data.env.app_name),
+        xml_escape(ACQUISITION_FEED),
+        xml_escape(&data.env.public_url),
+    );
+
+    (
+        StatusCode::OK,
+        [(
+            header::CONTENT_TYPE,
+            "application/opensearchdescription+xml",
+        )],
+        xml,
+    )
+}
+
+/// Search titles by keywords, for the OpenSearch description.
+#[utoipa::path(get, path = "/opds/search", params(OpdsSearchQuery), responses(
+    (status = 200, description = "Search successful", content_type = "application/atom+xml", body = String),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_opds_search(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Query(query): Query<OpdsSearchQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let terms = query.q.unwrap_or_default();
+    let keywords = terms
+        .split_whitespace()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    if keywords.is_empty() {
+        return Err(ErrRsp::bad_request("Search query must not be empty."));
+    }
+
+    let titles =
+        filter_title_models(&data.db, &user.id, FilterRequest::from_keywords(keywords)).await?;
+    let total = titles.len() as u64;
+    let page = query.page.unwrap_or(0);
+
+    let mut entries = vec![];
+    for title in titles
+        .into_iter()
+        .skip(usize::try_from(page.saturating_mul(opds_page_size())).unwrap_or(usize::MAX))
+        .take(opds_page_size() as usize)
+    {
+        entries.push(title_entry(&data.db, &user.id, title).await?);
+    }
+
+    let href = format!("/opds/search?q={}", percent_encode(&terms));
+    Ok(Feed::new(
+        format!("urn:yomuyume:search:{}", terms),
+        format!("Search: {}", terms),
+        &href,
+        ACQUISITION_FEED,
+    )
+    .entries(entries)
+    .paginate(&href, page, total))
+}
@@ -0,0 +1,63 @@
+use crate::{
+    models::prelude::*,
+    routes::{find_ordered_pages, get_page, title_download, DownloadFormat, ErrRsp, PageQuery},
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
+use sea_orm::EntityTrait;
+use std::sync::Arc;
+
+/// Download the archive of a title.
+#[utoipa::path(get, path = "/opds/titles/{title_id}/file", responses(
+    (status = 200, description = "Fetch title archive successful", content_type = "application/vnd.comicbook+zip", body = Vec<u8>),
+    (status = 206, description = "Fetch part of the title archive successful", body = Vec<u8>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Title not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_opds_title_file(
+    State(data): State<Arc<AppState>>,
+    Path(title_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, ErrRsp> {
+    let title = Titles::find_by_id(&title_id)
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("Title not found."))?;
+
+    title_download(&data, &title, DownloadFormat::Original, &headers).await
+}
+
+/// Stream a page of a title by its 0-based number, for the OPDS Page
+/// Streaming Extension.
+#[utoipa::path(get, path = "/opds/titles/{title_id}/pages/{page_number}", responses(
+    (status = 200, description = "Fetch page successful", body = Vec<u8>),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Title or page not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_opds_page(
+    State(data): State<Arc<AppState>>,
+    Path((title_id, page_number)): Path<(String, usize)>,
+) -> Result<Response, ErrRsp> {
+    let page = find_ordered_pages(&data.db, &title_id)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .nth(page_number)
+        .ok_or_else(|| ErrRsp::not_found("Page not found."))?;
+
+    get_page(
+        State(data),
+        None,
+        Path(page.id),
+        Query(PageQuery::default()),
+    )
+    .await
+    .map(IntoResponse::into_response)
+}
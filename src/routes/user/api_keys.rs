@@ -0,0 +1,156 @@
+use crate::{
+    models::prelude::*,
+    routes::{ErrRsp, GenericRsp},
+    AppState,
+};
+use argon2::{password_hash::SaltString, Argon2, PasswordHasher};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use rand_core::OsRng;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ApiKeyRequest {
+    /// To tell the keys apart, like the device using it
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeyResponseBody {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeysResponseBody {
+    pub data: Vec<ApiKeyResponseBody>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeyCreatedResponseBody {
+    pub id: String,
+    pub name: String,
+    /// Only shown once. Send it in the `X-API-Key` header, or as the password
+    /// of HTTP basic auth, to the OPDS and Komga routes.
+    pub key: String,
+    pub created_at: String,
+}
+
+/// List the user's API keys, without the keys themselves.
+#[utoipa::path(get, path = "/api/user/api_keys", responses(
+    (status = 200, description = "Fetch API keys successful", body = ApiKeysResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_api_keys(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let data = ApiKeys::find()
+        .filter(api_keys::Column::UserId.eq(&user.id))
+        .order_by_asc(api_keys::Column::CreatedAt)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .into_iter()
+        .map(|api_key| ApiKeyResponseBody {
+            id: api_key.id,
+            name: api_key.name,
+            created_at: api_key.created_at,
+            last_used_at: api_key.last_used_at,
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(ApiKeysResponseBody { data })))
+}
+
+/// Create an API key, for the clients that can't log in like OPDS readers.
+#[utoipa::path(post, path = "/api/user/api_keys", request_body = ApiKeyRequest, responses(
+    (status = 200, description = "Create API key successful", body = ApiKeyCreatedResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn post_api_key(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Json(query): Json<ApiKeyRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let name = query.name.trim();
+    if !(1..=100).contains(&name.chars().count()) {
+        return Err(ErrRsp::bad_request(
+            "API key name must be between 1 and 100 characters long.",
+        ));
+    }
+
+    let secret = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect::<String>();
+    let salt = SaltString::generate(&mut OsRng);
+    let secret_hash = Argon2::default()
+        .hash_password(secret.as_bytes(), &salt)
+        .map_err(|e| ErrRsp::internal(format!("Error while hashing API key: {}", e)))?
+        .to_string();
+
+    let api_key = api_keys::ActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        user_id: Set(user.id),
+        name: Set(name.to_string()),
+        secret_hash: Set(secret_hash),
+        created_at: Set(chrono::Utc::now().to_rfc3339()),
+        last_used_at: Set(None),
+    }
+    .insert(&data.db)
+    .await
+    .map_err(ErrRsp::db)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ApiKeyCreatedResponseBody {
+            key: format!("{}.{}", api_key.id, secret),
+            id: api_key.id,
+            name: api_key.name,
+            created_at: api_key.created_at,
+        }),
+    ))
+}
+
+/// Revoke an API key.
+#[utoipa::path(delete, path = "/api/user/api_keys/{api_key_id}", responses(
+    (status = 200, description = "Delete API key successful", body = GenericResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "API key not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn delete_api_key(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(api_key_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let result = ApiKeys::delete_many()
+        .filter(api_keys::Column::Id.eq(&api_key_id))
+        .filter(api_keys::Column::UserId.eq(&user.id))
+        .exec(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    if result.rows_affected == 0 {
+        return Err(ErrRsp::not_found("API key not found."));
+    }
+    data.api_keys.forget(&api_key_id);
+
+    Ok(GenericRsp::create("Delete API key successful."))
+}
@@ -0,0 +1,495 @@
+use crate::{
+    models::prelude::*,
+    routes::{filter_title_response, ErrRsp, FilterTitleResponseBody, GenericRsp},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, Condition,
+    ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::{collections::HashSet, sync::Arc};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CollectionRequest {
+    pub name: String,
+    pub description: Option<String>,
+    /// Share the collection with every user on the server, `false` by default
+    pub is_public: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ModifyCollectionRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub is_public: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CollectionOrderRequest {
+    /// Every title of the collection, in the new order
+    pub title_ids: Vec<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CollectionResponseBody {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_public: bool,
+    /// Whether the collection belongs to the user, shared ones are read-only
+    pub is_owner: bool,
+    pub owner: String,
+    pub title_count: u64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CollectionsResponseBody {
+    pub data: Vec<CollectionResponseBody>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CollectionDetailResponseBody {
+    pub collection: CollectionResponseBody,
+    /// In the collection's order
+    pub titles: Vec<FilterTitleResponseBody>,
+}
+
+fn check_name(name: &str) -> Result<String, ErrRsp> {
+    let name = name.trim();
+    match name.chars().count() {
+        1..=100 => Ok(name.to_string()),
+        _ => Err(ErrRsp::bad_request(
+            "Collection name must be between 1 and 100 characters long.",
+        )),
+    }
+}
+
+/// Find a collection the user can see, their own or a shared one.
+pub async fn find_visible_collection(
+    db: &DatabaseConnection,
+    user_id: &str,
+    collection_id: &str,
+) -> Result<collections::Model, ErrRsp> {
+    Collections::find_by_id(collection_id)
+        .filter(
+            Condition::any()
+                .add(collections::Column::UserId.eq(user_id))
+                .add(collections::Column::IsPublic.eq(true)),
+        )
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("Collection not found."))
+}
+
+async fn find_own_collection(
+    db: &DatabaseConnection,
+    user_id: &str,
+    collection_id: &str,
+) -> Result<collections::Model, ErrRsp> {
+    Collections::find_by_id(collection_id)
+        .filter(collections::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("Collection not found."))
+}
+
+async fn collection_response(
+    db: &DatabaseConnection,
+    collection: collections::Model,
+    user_id: &str,
+) -> Result<CollectionResponseBody, ErrRsp> {
+    let title_count = CollectionsTitles::find()
+        .filter(collections_titles::Column::CollectionId.eq(&collection.id))
+        .count(db)
+        .await
+        .map_err(ErrRsp::db)?;
+    let owner = Users::find_by_id(&collection.user_id)
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?
+        .map(|user| user.username)
+        .unwrap_or_default();
+
+    Ok(CollectionResponseBody {
+        is_owner: collection.user_id == user_id,
+        id: collection.id,
+        name: collection.name,
+        description: collection.description,
+        is_public: collection.is_public,
+        owner,
+        title_count,
+        created_at: collection.created_at,
+        updated_at: collection.updated_at,
+    })
+}
+
+async fn collection_titles(
+    db: &impl ConnectionTrait,
+    collection_id: &str,
+) -> Result<Vec<collections_titles::Model>, ErrRsp> {
+    CollectionsTitles::find()
+        .filter(collections_titles::Column::CollectionId.eq(collection_id))
+        .order_by_asc(collections_titles::Column::Position)
+        .all(db)
+        .await
+        .map_err(ErrRsp::db)
+}
+
+async fn touch_collection(
+    db: &impl ConnectionTrait,
+    collection: collections::Model,
+) -> Result<collections::Model, ErrRsp> {
+    let mut active_model: collections::ActiveModel = collection.into();
+    active_model.updated_at = Set(chrono::Utc::now().to_rfc3339());
+    active_model.update(db).await.map_err(ErrRsp::db)
+}
+
+/// Get the user's collections, then the ones shared by other users.
+#[utoipa::path(get, path = "/api/user/collections", responses(
+    (status = 200, description = "Fetch collections successful", body = CollectionsResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_collections(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let mut collections = Collections::find()
+        .filter(
+            Condition::any()
+                .add(collections::Column::UserId.eq(&user.id))
+                .add(collections::Column::IsPublic.eq(true)),
+        )
+        .order_by_asc(collections::Column::Name)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+    collections.sort_by_key(|collection| collection.user_id != user.id);
+
+    let mut resp_data = Vec::with_capacity(collections.len());
+    for collection in collections {
+        resp_data.push(collection_response(&data.db, collection, &user.id).await?);
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(CollectionsResponseBody { data: resp_data }),
+    ))
+}
+
+/// Create an empty collection.
+#[utoipa::path(post, path = "/api/user/collections", request_body = CollectionRequest, responses(
+    (status = 200, description = "Create collection successful", body = CollectionResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn post_collection(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Json(query): Json<CollectionRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let name = check_name(&query.name)?;
+
+    let existing = Collections::find()
+        .filter(collections::Column::UserId.eq(&user.id))
+        .filter(collections::Column::Name.eq(&name))
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+    if existing.is_some() {
+        return Err(ErrRsp::bad_request(
+            "A collection with this name already exists.",
+        ));
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let collection = collections::ActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        user_id: Set(user.id.clone()),
+        name: Set(name),
+        description: Set(query.description),
+        is_public: Set(query.is_public.unwrap_or(false)),
+        created_at: Set(now.clone()),
+        updated_at: Set(now),
+    }
+    .insert(&data.db)
+    .await
+    .map_err(ErrRsp::db)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(collection_response(&data.db, collection, &user.id).await?),
+    ))
+}
+
+/// Get a collection and its titles.
+#[utoipa::path(get, path = "/api/user/collections/{collection_id}", responses(
+    (status = 200, description = "Fetch collection successful", body = CollectionDetailResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Collection not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_collection(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(collection_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let collection = find_visible_collection(&data.db, &user.id, &collection_id).await?;
+
+    let mut titles = vec![];
+    for entry in collection_titles(&data.db, &collection.id).await? {
+        let title = Titles::find_by_id(&entry.title_id)
+            .one(&data.db)
+            .await
+            .map_err(ErrRsp::db)?;
+        if let Some(title) = title {
+            titles.push(filter_title_response(&data.db, title, &user.id).await?);
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(CollectionDetailResponseBody {
+            collection: collection_response(&data.db, collection, &user.id).await?,
+            titles,
+        }),
+    ))
+}
+
+/// Rename, describe or share one of the user's collections.
+#[utoipa::path(put, path = "/api/user/collections/{collection_id}", request_body = ModifyCollectionRequest, responses(
+    (status = 200, description = "Modify collection successful", body = CollectionResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Collection not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn put_collection(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(collection_id): Path<String>,
+    Json(query): Json<ModifyCollectionRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let collection = find_own_collection(&data.db, &user.id, &collection_id).await?;
+
+    let mut active_model: collections::ActiveModel = collection.into();
+    if let Some(name) = query.name {
+        let name = check_name(&name)?;
+        let existing = Collections::find()
+            .filter(collections::Column::UserId.eq(&user.id))
+            .filter(collections::Column::Name.eq(&name))
+            .filter(collections::Column::Id.ne(&collection_id))
+            .one(&data.db)
+            .await
+            .map_err(ErrRsp::db)?;
+        if existing.is_some() {
+            return Err(ErrRsp::bad_request(
+                "A collection with this name already exists.",
+            ));
+        }
+        active_model.name = Set(name);
+    }
+    if let Some(description) = query.description {
+        active_model.description = Set(Some(description).filter(|d| !d.trim().is_empty()));
+    }
+    if let Some(is_public) = query.is_public {
+        active_model.is_public = Set(is_public);
+    }
+    active_model.updated_at = Set(chrono::Utc::now().to_rfc3339());
+    let collection = active_model.update(&data.db).await.map_err(ErrRsp::db)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(collection_response(&data.db, collection, &user.id).await?),
+    ))
+}
+
+/// Delete one of the user's collections, the titles themselves are kept.
+#[utoipa::path(delete, path = "/api/user/collections/{collection_id}", responses(
+    (status = 200, description = "Delete collection successful", body = GenericResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Collection not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn delete_collection(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(collection_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let collection = find_own_collection(&data.db, &user.id, &collection_id).await?;
+
+    Collections::delete_by_id(collection.id)
+        .exec(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    Ok(GenericRsp::create("Delete collection successful."))
+}
+
+/// Add a title at the end of one of the user's collections.
+#[utoipa::path(put, path = "/api/user/collections/{collection_id}/titles/{title_id}", responses(
+    (status = 200, description = "Add title successful", body = GenericResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Collection not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn put_collection_title(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path((collection_id, title_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let collection = find_own_collection(&data.db, &user.id, &collection_id).await?;
+
+    let title = Titles::find_by_id(&title_id)
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::bad_request("Invalid title id."))?;
+
+    // adding two titles at once mustn't give them the same position
+    let txn = data.db.begin().await.map_err(ErrRsp::db)?;
+    let already_added = || ErrRsp::bad_request("Title already in the collection.");
+    let entries = collection_titles(&txn, &collection.id).await?;
+    if entries.iter().any(|entry| entry.title_id == title.id) {
+        return Err(already_added());
+    }
+
+    let result = CollectionsTitles::insert(collections_titles::ActiveModel {
+        id: NotSet,
+        collection_id: Set(collection.id.clone()),
+        title_id: Set(title.id),
+        position: Set(entries.len() as i64),
+        added_at: Set(chrono::Utc::now().to_rfc3339()),
+    })
+    .on_conflict(
+        OnConflict::columns([
+            collections_titles::Column::CollectionId,
+            collections_titles::Column::TitleId,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
+    .exec(&txn)
+    .await;
+    match result {
+        Ok(_) => {}
+        Err(DbErr::RecordNotInserted) => return Err(already_added()),
+        Err(e) => return Err(ErrRsp::db(e)),
+    }
+    touch_collection(&txn, collection).await?;
+    txn.commit().await.map_err(ErrRsp::db)?;
+
+    Ok(GenericRsp::create("Add title successful."))
+}
+
+/// Remove a title from one of the user's collections.
+#[utoipa::path(delete, path = "/api/user/collections/{collection_id}/titles/{title_id}", responses(
+    (status = 200, description = "Remove title successful", body = GenericResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Collection or title not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn delete_collection_title(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path((collection_id, title_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let collection = find_own_collection(&data.db, &user.id, &collection_id).await?;
+
+    let txn = data.db.begin().await.map_err(ErrRsp::db)?;
+    let entries = collection_titles(&txn, &collection.id).await?;
+    if !entries.iter().any(|entry| entry.title_id == title_id) {
+        return Err(ErrRsp::not_found("Title not in the collection."));
+    }
+
+    // keep the positions contiguous
+    let mut position = 0;
+    for entry in entries {
+        if entry.title_id == title_id {
+            CollectionsTitles::delete_by_id(entry.id)
+                .exec(&txn)
+                .await
+                .map_err(ErrRsp::db)?;
+            continue;
+        }
+        if entry.position != position {
+            let mut active_model: collections_titles::ActiveModel = entry.into();
+            active_model.position = Set(position);
+            active_model.update(&txn).await.map_err(ErrRsp::db)?;
+        }
+        position += 1;
+    }
+    touch_collection(&txn, collection).await?;
+    txn.commit().await.map_err(ErrRsp::db)?;
+
+    Ok(GenericRsp::create("Remove title successful."))
+}
+
+/// Reorder the titles of one of the user's collections.
+#[utoipa::path(put, path = "/api/user/collections/{collection_id}/order", request_body = CollectionOrderRequest, responses(
+    (status = 200, description = "Reorder collection successful", body = GenericResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Collection not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn put_collection_order(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(collection_id): Path<String>,
+    Json(query): Json<CollectionOrderRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let collection = find_own_collection(&data.db, &user.id, &collection_id).await?;
+
+    let txn = data.db.begin().await.map_err(ErrRsp::db)?;
+    let entries = collection_titles(&txn, &collection.id).await?;
+    let current = entries
+        .iter()
+        .map(|entry| entry.title_id.as_str())
+        .collect::<HashSet<_>>();
+    let requested = query
+        .title_ids
+        .iter()
+        .map(String::as_str)
+        .collect::<HashSet<_>>();
+    if requested.len() != query.title_ids.len() || requested != current {
+        return Err(ErrRsp::bad_request(
+            "`title_ids` must list every title of the collection exactly once.",
+        ));
+    }
+
+    for entry in entries {
+        let position = query
+            .title_ids
+            .iter()
+            .position(|title_id| *title_id == entry.title_id)
+            .unwrap_or_default() as i64;
+        if entry.position != position {
+            let mut active_model: collections_titles::ActiveModel = entry.into();
+            active_model.position = Set(position);
+            active_model.update(&txn).await.map_err(ErrRsp::db)?;
+        }
+    }
+    touch_collection(&txn, collection).await?;
+    txn.commit().await.map_err(ErrRsp::db)?;
+
+    Ok(GenericRsp::create("Reorder collection successful."))
+}
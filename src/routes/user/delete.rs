@@ -1,9 +1,7 @@
-use super::{check_pass, sendmail};
+use super::{action_link, check_pass, purpose_token};
 use crate::{
-    models::{
-        auth::{TokenClaims, TokenClaimsPurpose},
-        prelude::*,
-    },
+    mailer::MailTemplate,
+    models::{auth::TokenClaimsPurpose, prelude::*},
     routes::{ErrRsp, GenericRsp},
     AppState,
 };
@@ -31,50 +29,21 @@ pub async fn get_delete(
     State(data): State<Arc<AppState>>,
     Extension(user): Extension<users::Model>,
 ) -> Result<impl IntoResponse, ErrRsp> {
-    if data.env.smtp_host.is_none() {
-        return Err(ErrRsp::internal(
-            "SMTP is not configured, please contact the server administrator.",
-        ));
-    }
-
-    let now = chrono::Utc::now();
-    let token_claims = TokenClaims {
-        sub: user.id,
-        iat: now.timestamp() as usize,
-        exp: (now + chrono::Duration::hours(1)).timestamp() as usize,
-        purpose: Some(TokenClaimsPurpose::DeleteAccount),
-    };
-    let token = jsonwebtoken::encode(
-        &jsonwebtoken::Header::default(),
-        &token_claims,
-        &jsonwebtoken::EncodingKey::from_secret(data.env.jwt_secret.as_ref()),
-    )
-    .map_err(|e| ErrRsp::internal(format!("Failed to generate token. JWT error: {}", e)))?;
+    let mailer = data.mailer.as_ref().ok_or_else(|| {
+        ErrRsp::internal("Mail is not configured, please contact the server administrator.")
+    })?;
 
-    let email = format!(
-        "Hello, {}!\n\n\
-        // You have requested to delete your account. Please copy the following token into the app to continue:\n\n\
-        {}\n\n\
-        If you did not request to delete your account, please ignore this email.\n\n\
-        Best regards,\n\
-        The {} team",
-        &user.username,
-        token,
-        &data.env.app_name,
-    );
+    let token = purpose_token(&data.env, &user, TokenClaimsPurpose::DeleteAccount)?;
+    let link = action_link(&data.env, "delete", &token);
 
-    match sendmail(
-        &data.env,
+    match mailer.send(
+        MailTemplate::DeleteAccount,
         &user.username,
         &user.email,
-        &format!("{} - Delete your password", &data.env.app_name),
-        &email,
+        &[("token", &token), ("link", &link)],
     ) {
         Ok(_) => Ok(GenericRsp::create("Token sent to user's email.")),
-        Err(e) => Err(ErrRsp::internal(format!(
-            "Failed to send email. SMTP error: {}",
-            e
-        ))),
+        Err(e) => Err(ErrRsp::internal(format!("Failed to send email: {}", e))),
     }
 }
 
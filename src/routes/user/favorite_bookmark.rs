@@ -56,6 +56,7 @@ pub async fn put_favorite(
     Ok(GenericRsp::create("Add favorite successful."))
 }
 
+/// Bookmark a whole title, use `POST /api/user/bookmarks` to bookmark a page.
 #[utoipa::path(put, path = "/api/user/bookmark/:id", responses(
     (status = 200, description = "Add bookmark successful", body = GenericResponseBody),
     (status = 400, description = "Bad request", body = ErrorResponseBody),
@@ -77,7 +78,8 @@ pub async fn put_bookmark(
         .filter(
             Condition::all()
                 .add(bookmarks::Column::TitleId.eq(&title.id))
-                .add(bookmarks::Column::UserId.eq(&user.id)),
+                .add(bookmarks::Column::UserId.eq(&user.id))
+                .add(bookmarks::Column::PageId.is_null()),
         )
         .one(&data.db)
         .await
@@ -91,6 +93,9 @@ pub async fn put_bookmark(
         id: NotSet,
         title_id: Set(title.id),
         user_id: Set(user.id),
+        page_id: Set(None),
+        note: Set(None),
+        created_at: Set(chrono::Utc::now().to_rfc3339()),
     }
     .insert(&data.db)
     .await
@@ -129,6 +134,7 @@ pub async fn delete_favorite(
     Ok(GenericRsp::create("Delete favorite successful."))
 }
 
+/// Remove the bookmark on a whole title, page bookmarks are kept.
 #[utoipa::path(delete, path = "/api/user/bookmark/:id", responses(
     (status = 200, description = "Delete bookmark successful", body = GenericResponseBody),
     (status = 400, description = "Bad request", body = ErrorResponseBody),
@@ -150,7 +156,8 @@ pub async fn delete_bookmark(
         .filter(
             Condition::all()
                 .add(bookmarks::Column::TitleId.contains(&title.id))
-                .add(bookmarks::Column::UserId.contains(&user.id)),
+                .add(bookmarks::Column::UserId.contains(&user.id))
+                .add(bookmarks::Column::PageId.is_null()),
         )
         .exec(&data.db)
         .await
@@ -0,0 +1,29 @@
+use crate::{
+    models::prelude::*,
+    routes::{reading_stats, ErrRsp, StatsQuery},
+    AppState,
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use std::sync::Arc;
+
+/// The user's reading statistics over a date range.
+#[utoipa::path(get, path = "/api/user/stats", params(StatsQuery), responses(
+    (status = 200, description = "Fetch statistics successful", body = StatsResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_user_stats(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Query(query): Query<StatsQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let stats = reading_stats(&data.db, Some(&user.id), &query).await?;
+
+    Ok((StatusCode::OK, Json(stats)))
+}
@@ -0,0 +1,164 @@
+use crate::{
+    constants::reading_session_gap,
+    models::prelude::*,
+    routes::{ErrRsp, GenericRsp},
+    AppState,
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, QueryTrait, Set,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct HistoryQuery {
+    /// Only show the sessions of this title
+    pub title_id: Option<String>,
+    /// Maximum number of sessions to return, 50 by default
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HistoryEntry {
+    pub title_id: String,
+    pub title: String,
+    pub started_at: String,
+    pub ended_at: String,
+    /// 0-based index of the first page read
+    pub first_page: i64,
+    /// 0-based index of the last page read
+    pub last_page: i64,
+    pub pages_read: i64,
+    /// In seconds
+    pub duration: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HistoryResponseBody {
+    pub data: Vec<HistoryEntry>,
+}
+
+/// Record that the user was at `page` of a title at `at`.
+///
+/// Extends the user's last session on the title if it ended less than
+/// `reading_session_gap()` ago, starts a new one otherwise.
+pub async fn record_reading(
+    db: &DatabaseConnection,
+    user_id: &str,
+    title_id: &str,
+    page: i64,
+    at: DateTime<Utc>,
+) -> Result<(), DbErr> {
+    let last_session = ReadingSessions::find()
+        .filter(reading_sessions::Column::UserId.eq(user_id))
+        .filter(reading_sessions::Column::TitleId.eq(title_id))
+        .order_by_desc(reading_sessions::Column::EndedAt)
+        .one(db)
+        .await?;
+
+    if let Some(session) = last_session {
+        let started_at = DateTime::parse_from_rfc3339(&session.started_at)
+            .map(|time| time.with_timezone(&Utc))
+            .unwrap_or(at);
+        let ended_at = DateTime::parse_from_rfc3339(&session.ended_at)
+            .map(|time| time.with_timezone(&Utc))
+            .unwrap_or(at);
+
+        if at >= ended_at && at - ended_at <= reading_session_gap() {
+            let pages_read = session.pages_read + (page - session.last_page).max(0);
+            let mut active_model: reading_sessions::ActiveModel = session.into();
+            active_model.last_page = Set(page);
+            active_model.pages_read = Set(pages_read);
+            active_model.ended_at = Set(at.to_rfc3339());
+            active_model.duration = Set((at - started_at).num_seconds().max(0));
+            active_model.update(db).await?;
+            return Ok(());
+        }
+    }
+
+    reading_sessions::ActiveModel {
+        id: NotSet,
+        user_id: Set(user_id.to_string()),
+        title_id: Set(title_id.to_string()),
+        started_at: Set(at.to_rfc3339()),
+        ended_at: Set(at.to_rfc3339()),
+        first_page: Set(page),
+        last_page: Set(page),
+        pages_read: Set(0),
+        duration: Set(0),
+    }
+    .insert(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Get the user's reading sessions, most recent first.
+#[utoipa::path(get, path = "/api/user/history", params(HistoryQuery), responses(
+    (status = 200, description = "Fetch history successful", body = HistoryResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_history(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let sessions = ReadingSessions::find()
+        .find_also_related(Titles)
+        .filter(reading_sessions::Column::UserId.eq(&user.id))
+        .apply_if(query.title_id, |select, title_id| {
+            select.filter(reading_sessions::Column::TitleId.eq(title_id))
+        })
+        .order_by_desc(reading_sessions::Column::EndedAt)
+        .limit(query.limit.unwrap_or(50))
+        .offset(query.offset)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let data = sessions
+        .into_iter()
+        .map(|(session, title)| HistoryEntry {
+            title_id: session.title_id,
+            title: title.map(|title| title.title).unwrap_or_default(),
+            started_at: session.started_at,
+            ended_at: session.ended_at,
+            first_page: session.first_page,
+            last_page: session.last_page,
+            pages_read: session.pages_read,
+            duration: session.duration,
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(HistoryResponseBody { data })))
+}
+
+/// Clear the user's reading history, progresses are left untouched.
+#[utoipa::path(delete, path = "/api/user/history", responses(
+    (status = 200, description = "Clear history successful", body = GenericResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn delete_history(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    ReadingSessions::delete_many()
+        .filter(reading_sessions::Column::UserId.eq(&user.id))
+        .exec(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    Ok(GenericRsp::create("History cleared."))
+}
@@ -0,0 +1,254 @@
+use super::check_pass;
+use crate::{
+    models::{
+        auth::{TokenClaims, TokenClaimsPurpose},
+        prelude::*,
+    },
+    routes::check_pass_strength,
+    AppState,
+};
+use argon2::{password_hash::SaltString, Argon2, PasswordHasher};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse},
+    Form,
+};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use rand_core::OsRng;
+use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ResetFormRequest {
+    pub password: String,
+    pub confirm_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DeleteFormRequest {
+    pub password: String,
+}
+
+/// A bare HTML page, these endpoints are opened straight from an email client.
+fn page(
+    status: StatusCode,
+    app_name: &str,
+    title: &str,
+    content: &str,
+) -> (StatusCode, Html<String>) {
+    (
+        status,
+        Html(format!(
+            "<!DOCTYPE html>\
+            <html>\
+            <head><meta charset=\"utf-8\"><meta name=\"viewport\" content=\"width=device-width\">\
+            <title>{title} - {app_name}</title></head>\
+            <body style=\"font-family: sans-serif; max-width: 32rem; margin: 4rem auto; padding: 0 1rem\">\
+            <h1>{title}</h1>{content}</body>\
+            </html>",
+        )),
+    )
+}
+
+fn message_page(
+    status: StatusCode,
+    app_name: &str,
+    title: &str,
+    message: &str,
+) -> (StatusCode, Html<String>) {
+    page(status, app_name, title, &format!("<p>{}</p>", message))
+}
+
+/// Decode an emailed token and load its user, making sure it's meant for
+/// `purpose` and hasn't been used yet.
+async fn link_user(
+    data: &Arc<AppState>,
+    token: &str,
+    purpose: TokenClaimsPurpose,
+) -> Result<users::Model, &'static str> {
+    let claims = decode::<TokenClaims>(
+        token,
+        &DecodingKey::from_secret(data.env.jwt_secret.as_ref()),
+        &Validation::default(),
+    )
+    .map_err(|_| "This link is invalid or has expired.")?
+    .claims;
+
+    if claims.purpose != Some(purpose) {
+        return Err("This link is invalid or has expired.");
+    }
+
+    let user = Users::find_by_id(&claims.sub)
+        .one(&data.db)
+        .await
+        .map_err(|_| "Something went wrong, please try again later.")?
+        .ok_or("The user belonging to this link no longer exists.")?;
+
+    match claims.fingerprint {
+        Some(fingerprint) if fingerprint == TokenClaims::user_fingerprint(&user) => Ok(user),
+        _ => Err("This link has already been used."),
+    }
+}
+
+/// Verify the account from the link sent by `GET /api/user/verify`.
+#[utoipa::path(get, path = "/api/user/link/verify/{token}", responses(
+    (status = 200, description = "Account verified, an HTML page", body = String),
+    (status = 400, description = "Invalid, expired or used link, an HTML page", body = String),
+))]
+pub async fn get_verify_link(
+    State(data): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> impl IntoResponse {
+    let app_name = &data.env.app_name;
+    let title = "Verify your account";
+
+    let user = match link_user(&data, &token, TokenClaimsPurpose::VerifyRegister).await {
+        Ok(user) => user,
+        Err(e) => return message_page(StatusCode::BAD_REQUEST, app_name, title, e),
+    };
+
+    let mut user: users::ActiveModel = user.into();
+    user.is_verified = Set(true);
+    if user.save(&data.db).await.is_err() {
+        let e = "Something went wrong, please try again later.";
+        return message_page(StatusCode::INTERNAL_SERVER_ERROR, app_name, title, e);
+    }
+
+    let message = "Your account has been verified, you can close this page.";
+    message_page(StatusCode::OK, app_name, title, message)
+}
+
+/// Show the password reset form from the link sent by `GET /api/user/reset/{email}`.
+#[utoipa::path(get, path = "/api/user/link/reset/{token}", responses(
+    (status = 200, description = "The reset form, an HTML page", body = String),
+    (status = 400, description = "Invalid, expired or used link, an HTML page", body = String),
+))]
+pub async fn get_reset_link(
+    State(data): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> impl IntoResponse {
+    let app_name = &data.env.app_name;
+    let title = "Reset your password";
+
+    if let Err(e) = link_user(&data, &token, TokenClaimsPurpose::ResetPassword).await {
+        return message_page(StatusCode::BAD_REQUEST, app_name, title, e);
+    }
+
+    let form = "<form method=\"post\">\
+        <p><label>New password<br><input type=\"password\" name=\"password\" required></label></p>\
+        <p><label>Confirm new password<br><input type=\"password\" name=\"confirm_password\" required></label></p>\
+        <p><button type=\"submit\">Reset password</button></p>\
+        </form>";
+    page(StatusCode::OK, app_name, title, form)
+}
+
+/// Submit the password reset form.
+#[utoipa::path(post, path = "/api/user/link/reset/{token}", request_body(
+    content = ResetFormRequest, content_type = "application/x-www-form-urlencoded"
+), responses(
+    (status = 200, description = "Password reset, an HTML page", body = String),
+    (status = 400, description = "Invalid link or password, an HTML page", body = String),
+))]
+pub async fn post_reset_link(
+    State(data): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    Form(form): Form<ResetFormRequest>,
+) -> impl IntoResponse {
+    let app_name = &data.env.app_name;
+    let title = "Reset your password";
+
+    let user = match link_user(&data, &token, TokenClaimsPurpose::ResetPassword).await {
+        Ok(user) => user,
+        Err(e) => return message_page(StatusCode::BAD_REQUEST, app_name, title, e),
+    };
+
+    if form.password != form.confirm_password {
+        let e = "The passwords don't match, please go back and try again.";
+        return message_page(StatusCode::BAD_REQUEST, app_name, title, e);
+    }
+
+    if let Err(e) = check_pass_strength(&form.password) {
+        return message_page(StatusCode::BAD_REQUEST, app_name, title, &e.body.message);
+    }
+
+    let salt = SaltString::generate(&mut OsRng);
+    let hashed_password = match Argon2::default().hash_password(form.password.as_bytes(), &salt) {
+        Ok(hash) => hash.to_string(),
+        Err(_) => {
+            let e = "Something went wrong, please try again later.";
+            return message_page(StatusCode::INTERNAL_SERVER_ERROR, app_name, title, e);
+        }
+    };
+
+    let mut user: users::ActiveModel = user.into();
+    user.password = Set(hashed_password);
+    if user.save(&data.db).await.is_err() {
+        let e = "Something went wrong, please try again later.";
+        return message_page(StatusCode::INTERNAL_SERVER_ERROR, app_name, title, e);
+    }
+
+    let message = "Your password has been reset, you can now log in with it.";
+    message_page(StatusCode::OK, app_name, title, message)
+}
+
+/// Show the account deletion confirmation from the link sent by `GET /api/user/delete`.
+#[utoipa::path(get, path = "/api/user/link/delete/{token}", responses(
+    (status = 200, description = "The confirmation form, an HTML page", body = String),
+    (status = 400, description = "Invalid, expired or used link, an HTML page", body = String),
+))]
+pub async fn get_delete_link(
+    State(data): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> impl IntoResponse {
+    let app_name = &data.env.app_name;
+    let title = "Delete your account";
+
+    if let Err(e) = link_user(&data, &token, TokenClaimsPurpose::DeleteAccount).await {
+        return message_page(StatusCode::BAD_REQUEST, app_name, title, e);
+    }
+
+    let form = "<p>This will permanently delete your account and everything in it.</p>\
+        <form method=\"post\">\
+        <p><label>Password<br><input type=\"password\" name=\"password\" required></label></p>\
+        <p><button type=\"submit\">Delete my account</button></p>\
+        </form>";
+    page(StatusCode::OK, app_name, title, form)
+}
+
+/// Submit the account deletion confirmation.
+#[utoipa::path(post, path = "/api/user/link/delete/{token}", request_body(
+    content = DeleteFormRequest, content_type = "application/x-www-form-urlencoded"
+), responses(
+    (status = 200, description = "Account deleted, an HTML page", body = String),
+    (status = 400, description = "Invalid link or password, an HTML page", body = String),
+))]
+pub async fn post_delete_link(
+    State(data): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    Form(form): Form<DeleteFormRequest>,
+) -> impl IntoResponse {
+    let app_name = &data.env.app_name;
+    let title = "Delete your account";
+
+    let user = match link_user(&data, &token, TokenClaimsPurpose::DeleteAccount).await {
+        Ok(user) => user,
+        Err(e) => return message_page(StatusCode::BAD_REQUEST, app_name, title, e),
+    };
+
+    if !check_pass(&user.password, &form.password) {
+        let e = "Invalid password, please go back and try again.";
+        return message_page(StatusCode::BAD_REQUEST, app_name, title, e);
+    }
+
+    let user: users::ActiveModel = user.into();
+    if user.delete(&data.db).await.is_err() {
+        let e = "Something went wrong, please try again later.";
+        return message_page(StatusCode::INTERNAL_SERVER_ERROR, app_name, title, e);
+    }
+
+    let message = "Your account has been deleted.";
+    message_page(StatusCode::OK, app_name, title, message)
+}
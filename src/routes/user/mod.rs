@@ -1,100 +1,72 @@
+mod api_keys;
+mod collections;
 mod delete;
 mod favorite_bookmark;
 mod get_check;
+mod get_stats;
+mod history;
+mod magic_link;
 mod modify;
+mod page_bookmark;
 mod put_progress;
+mod rating;
+mod reader;
 mod reset;
+mod smart_collections;
+mod sync_progress;
 mod verify;
 
 use super::check_pass;
-use crate::config::Config;
-use lettre::{
-    self,
-    message::{header::ContentType, Mailbox},
-    transport::smtp::{authentication::Credentials, response::Response},
-    Message, SmtpTransport, Transport,
+use crate::{
+    config::Config,
+    models::{
+        auth::{TokenClaims, TokenClaimsPurpose},
+        prelude::*,
+    },
+    routes::ErrRsp,
 };
-use tracing::warn;
 
+pub use api_keys::*;
+pub use collections::*;
 pub use delete::*;
 pub use favorite_bookmark::*;
 pub use get_check::*;
+pub use get_stats::*;
+pub use history::*;
+pub use magic_link::*;
 pub use modify::*;
+pub use page_bookmark::*;
 pub use put_progress::*;
+pub use rating::*;
+pub use reader::*;
 pub use reset::*;
+pub use smart_collections::*;
+pub use sync_progress::*;
 pub use verify::*;
 
-pub fn sendmail(
+/// Issue the short-lived, single-use token sent by email for `purpose`.
+fn purpose_token(
     env: &Config,
-    receiver_name: &str,
-    receiver_email: &str,
-    subject: &str,
-    content: &str,
-) -> Result<Response, String> {
-    let from_name = match &env.smtp_from_name {
-        Some(name) => name,
-        None => {
-            let err = "Invalid smtp from name";
-            warn!(err);
-            return Err(err.to_string());
-        }
+    user: &users::Model,
+    purpose: TokenClaimsPurpose,
+) -> Result<String, ErrRsp> {
+    let now = chrono::Utc::now();
+    let token_claims = TokenClaims {
+        sub: user.id.clone(),
+        iat: now.timestamp() as usize,
+        exp: (now + chrono::Duration::hours(1)).timestamp() as usize,
+        purpose: Some(purpose),
+        fingerprint: Some(TokenClaims::user_fingerprint(user)),
     };
+    jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &token_claims,
+        &jsonwebtoken::EncodingKey::from_secret(env.jwt_secret.as_ref()),
+    )
+    .map_err(|e| ErrRsp::internal(format!("Failed to generate token. JWT error: {}", e)))
+}
 
-    let from_email = match &env.smtp_from_email {
-        Some(email) => email,
-        None => {
-            let err = "Invalid smtp from email";
-            warn!(err);
-            return Err(err.to_string());
-        }
-    };
-
-    let smtp_username = match &env.smtp_username {
-        Some(smtp_username) => String::from(smtp_username),
-        None => {
-            let err = "Invalid smtp username";
-            warn!(err);
-            return Err(err.to_string());
-        }
-    };
-
-    let smtp_password = match &env.smtp_password {
-        Some(smtp_password) => String::from(smtp_password),
-        None => {
-            let err = "Invalid smtp password";
-            warn!(err);
-            return Err(err.to_string());
-        }
-    };
-
-    let from: Mailbox = format!("{} <{}>", from_name, from_email)
-        .parse()
-        .map_err(|_| format!("Invalid from address: {}", from_email))?;
-
-    let to: Mailbox = format!("{} <{}>", receiver_name, receiver_email)
-        .parse()
-        .map_err(|_| format!("Invalid to address: {}", receiver_email))?;
-
-    let email = Message::builder()
-        .from(from)
-        .to(to)
-        .header(ContentType::TEXT_PLAIN)
-        .subject(subject)
-        .body(content.to_string())
-        .map_err(|_| "Failed to build email")?;
-
-    let creds = Credentials::new(smtp_username, smtp_password);
-
-    let host = env.smtp_host.as_ref().ok_or_else(|| {
-        let err = "Invalid smtp host";
-        warn!(err);
-        err
-    })?;
-
-    let mailer = SmtpTransport::relay(host)
-        .map_err(|_| "Failed to create mailer")?
-        .credentials(creds)
-        .build();
-
-    mailer.send(&email).map_err(|e| e.to_string())
+/// Link to the landing page of an emailed action, see `magic_link.rs`.
+fn action_link(env: &Config, action: &str, token: &str) -> String {
+    format!("{}/api/user/link/{}/{}", env.public_url, action, token)
 }
@@ -0,0 +1,294 @@
+use crate::{
+    models::prelude::*,
+    routes::{find_ordered_pages, ErrRsp, GenericRsp},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Extension, Json,
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, Set,
+};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+const MAX_NOTE_LENGTH: usize = 1000;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BookmarkRequest {
+    pub page_id: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BookmarkNoteRequest {
+    /// `null` removes the note
+    pub note: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BookmarkResponseBody {
+    pub id: i64,
+    /// For `GET /api/file/page/{page_id}`, `None` if the page is gone
+    pub page_id: Option<String>,
+    /// 0-based index of the page in the title, `None` if the page is gone
+    pub page: Option<i64>,
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TitleBookmarksResponseBody {
+    pub title_id: String,
+    pub title: String,
+    pub bookmarks: Vec<BookmarkResponseBody>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BookmarksResponseBody {
+    pub data: Vec<TitleBookmarksResponseBody>,
+}
+
+fn check_note(note: &Option<String>) -> Result<Option<String>, ErrRsp> {
+    let note = note
+        .as_deref()
+        .map(str::trim)
+        .filter(|note| !note.is_empty());
+    match note {
+        Some(note) if note.chars().count() > MAX_NOTE_LENGTH => Err(ErrRsp::bad_request(format!(
+            "Note must be at most {} characters long.",
+            MAX_NOTE_LENGTH
+        ))),
+        note => Ok(note.map(str::to_string)),
+    }
+}
+
+async fn find_user_bookmark(
+    db: &DatabaseConnection,
+    user_id: &str,
+    bookmark_id: i64,
+) -> Result<bookmarks::Model, ErrRsp> {
+    Bookmarks::find_by_id(bookmark_id)
+        .filter(bookmarks::Column::UserId.eq(user_id))
+        .filter(bookmarks::Column::PageId.is_not_null())
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("Bookmark not found."))
+}
+
+fn to_bookmark_response(
+    bookmark: bookmarks::Model,
+    pages: &[pages::Model],
+) -> BookmarkResponseBody {
+    let page = pages
+        .iter()
+        .position(|page| Some(&page.id) == bookmark.page_id.as_ref());
+
+    BookmarkResponseBody {
+        id: bookmark.id,
+        page_id: page.and(bookmark.page_id),
+        page: page.map(|page| page as i64),
+        note: bookmark.note,
+        created_at: bookmark.created_at,
+    }
+}
+
+async fn bookmark_response(
+    db: &DatabaseConnection,
+    bookmark: bookmarks::Model,
+) -> Result<BookmarkResponseBody, ErrRsp> {
+    let pages = find_ordered_pages(db, &bookmark.title_id)
+        .await
+        .map_err(ErrRsp::db)?;
+    Ok(to_bookmark_response(bookmark, &pages))
+}
+
+/// Get the user's page bookmarks, grouped by title.
+///
+/// Titles with the most recent bookmark come first, bookmarks are in page
+/// order.
+#[utoipa::path(get, path = "/api/user/bookmarks", responses(
+    (status = 200, description = "Fetch bookmarks successful", body = BookmarksResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_bookmarks(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let bookmarks = Bookmarks::find()
+        .find_also_related(Titles)
+        .filter(bookmarks::Column::UserId.eq(&user.id))
+        .filter(bookmarks::Column::PageId.is_not_null())
+        .order_by_desc(bookmarks::Column::CreatedAt)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    // group first so the pages of each title are loaded once
+    let mut groups: Vec<(titles::Model, Vec<bookmarks::Model>)> = vec![];
+    for (bookmark, title) in bookmarks {
+        let Some(title) = title else {
+            continue;
+        };
+        match groups.iter_mut().find(|(group, _)| group.id == title.id) {
+            Some((_, bookmarks)) => bookmarks.push(bookmark),
+            None => groups.push((title, vec![bookmark])),
+        }
+    }
+
+    let mut resp_data: Vec<TitleBookmarksResponseBody> = vec![];
+    for (title, bookmarks) in groups {
+        let pages = find_ordered_pages(&data.db, &title.id)
+            .await
+            .map_err(ErrRsp::db)?;
+        resp_data.push(TitleBookmarksResponseBody {
+            title_id: title.id,
+            title: title.title,
+            bookmarks: bookmarks
+                .into_iter()
+                .map(|bookmark| to_bookmark_response(bookmark, &pages))
+                .collect(),
+        });
+    }
+    for group in &mut resp_data {
+        // pages that are gone go last
+        group
+            .bookmarks
+            .sort_by_key(|bookmark| bookmark.page.unwrap_or(i64::MAX));
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(BookmarksResponseBody { data: resp_data }),
+    ))
+}
+
+/// Bookmark a page, with an optional note.
+#[utoipa::path(post, path = "/api/user/bookmarks", request_body = BookmarkRequest, responses(
+    (status = 200, description = "Add bookmark successful", body = BookmarkResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn post_bookmark(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Json(query): Json<BookmarkRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let note = check_note(&query.note)?;
+
+    let page = Pages::find_by_id(&query.page_id)
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::bad_request("Invalid page id."))?;
+
+    let existing = Bookmarks::find()
+        .filter(bookmarks::Column::UserId.eq(&user.id))
+        .filter(bookmarks::Column::PageId.eq(&page.id))
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+    if existing.is_some() {
+        return Err(ErrRsp::bad_request("Page already bookmarked."));
+    }
+
+    let bookmark = bookmarks::ActiveModel {
+        id: NotSet,
+        user_id: Set(user.id),
+        title_id: Set(page.title_id),
+        page_id: Set(Some(page.id)),
+        note: Set(note),
+        created_at: Set(chrono::Utc::now().to_rfc3339()),
+    }
+    .insert(&data.db)
+    .await
+    .map_err(ErrRsp::db)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(bookmark_response(&data.db, bookmark).await?),
+    ))
+}
+
+/// Change the note of a page bookmark.
+#[utoipa::path(put, path = "/api/user/bookmarks/{bookmark_id}", request_body = BookmarkNoteRequest, responses(
+    (status = 200, description = "Update bookmark successful", body = BookmarkResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Bookmark not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn put_bookmark_note(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(bookmark_id): Path<i64>,
+    Json(query): Json<BookmarkNoteRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let note = check_note(&query.note)?;
+    let bookmark = find_user_bookmark(&data.db, &user.id, bookmark_id).await?;
+
+    let mut active_model: bookmarks::ActiveModel = bookmark.into();
+    active_model.note = Set(note);
+    let bookmark = active_model.update(&data.db).await.map_err(ErrRsp::db)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(bookmark_response(&data.db, bookmark).await?),
+    ))
+}
+
+/// Remove a page bookmark.
+#[utoipa::path(delete, path = "/api/user/bookmarks/{bookmark_id}", responses(
+    (status = 200, description = "Delete bookmark successful", body = GenericResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Bookmark not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn delete_page_bookmark(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(bookmark_id): Path<i64>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let bookmark = find_user_bookmark(&data.db, &user.id, bookmark_id).await?;
+
+    Bookmarks::delete_by_id(bookmark.id)
+        .exec(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    Ok(GenericRsp::create("Delete bookmark successful."))
+}
+
+/// Jump to the bookmarked page, redirects to `GET /api/file/page/{page_id}`.
+#[utoipa::path(get, path = "/api/user/bookmarks/{bookmark_id}/page", responses(
+    (status = 303, description = "Redirect to the page"),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Bookmark or page not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_bookmark_page(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(bookmark_id): Path<i64>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let bookmark = find_user_bookmark(&data.db, &user.id, bookmark_id).await?;
+    let page_id = bookmark.page_id.unwrap_or_default();
+
+    let page = Pages::find_by_id(&page_id)
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("The bookmarked page no longer exists."))?;
+
+    Ok(Redirect::to(&format!("/api/file/page/{}", page.id)))
+}
@@ -1,20 +1,34 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     response::IntoResponse,
     Extension,
 };
-use sea_orm::{ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, EntityTrait, QueryFilter, Set};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::Deserialize;
 use tracing::warn;
+use utoipa::IntoParams;
 
+use super::{apply_progress_update, ProgressUpdate, SyncStrategy};
 use crate::{
     models::prelude::*,
     routes::{ErrRsp, GenericRsp},
     AppState,
 };
 
-#[utoipa::path(put, path = "/api/user/progress/:title_id/:page", responses(
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct PutProgressQuery {
+    /// Mark the title as finished, or not anymore, kept as it is by default
+    pub completed: Option<bool>,
+    pub device_id: Option<String>,
+}
+
+/// Set the page the user is at in a title.
+///
+/// Completion and the device are handled like in `POST /api/user/progress/sync`,
+/// use it to also set the scroll offset.
+#[utoipa::path(put, path = "/api/user/progress/:title_id/:page", params(PutProgressQuery), responses(
     (status = 200, description = "Set progress successfully", body = GenericResponseBody),
     (status = 400, description = "Bad request", body = ErrorResponseBody),
     (status = 401, description = "Unauthorized", body = ErrorResponseBody),
@@ -23,6 +37,7 @@ pub async fn put_progress(
     State(data): State<Arc<AppState>>,
     Extension(user): Extension<users::Model>,
     Path((title_id, page)): Path<(String, i64)>,
+    Query(query): Query<PutProgressQuery>,
 ) -> Result<impl IntoResponse, ErrRsp> {
     let progress_model = Progresses::find()
         .filter(progresses::Column::TitleId.eq(&title_id))
@@ -36,40 +51,27 @@ pub async fn put_progress(
             );
             ErrRsp::internal(format!("Can't find progress: {}", e))
         })?;
+    let existed = progress_model.is_some();
 
-    // Update if exist
-    if let Some(progress_model) = progress_model {
-        let mut active_model: progresses::ActiveModel = progress_model.into();
-        active_model.last_read_at = Set(chrono::Utc::now().to_rfc3339());
-        active_model.page = Set(page);
-        active_model.update(&data.db).await.map_err(|e| {
-            warn!(
-                "update progress failed | title {} | user {}: {}",
-                title_id, user.id, e
-            );
-            ErrRsp::internal(format!("Can't update progress: {}", e))
-        })?;
-
-        return Ok(GenericRsp::create("Progress updated."));
-    }
+    let update = ProgressUpdate {
+        title_id,
+        page,
+        scroll_offset: None,
+        completed: query.completed,
+        device_id: query.device_id,
+        updated_at: None,
+    };
+    apply_progress_update(
+        &data.db,
+        &user.id,
+        progress_model,
+        &update,
+        SyncStrategy::LastWriterWins,
+    )
+    .await?;
 
-    progresses::ActiveModel {
-        id: NotSet,
-        user_id: Set(user.id.clone()),
-        title_id: Set(title_id.clone()),
-        last_read_at: Set(chrono::Utc::now().to_rfc3339()),
-        page: Set(page),
+    match existed {
+        true => Ok(GenericRsp::create("Progress updated.")),
+        false => Ok(GenericRsp::create("Progress set.")),
     }
-    .insert(&data.db)
-    .await
-    .map_err(|e| {
-        warn!(
-            "insert progress failed | title {} | user {}: {}",
-            title_id, user.id, e
-        );
-        ErrRsp::internal(format!("Can't insert progress: {}", e))
-    })?;
-
-    // just return the OK status
-    Ok(GenericRsp::create("Progress set."))
 }
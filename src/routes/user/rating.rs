@@ -0,0 +1,116 @@
+use crate::{
+    models::prelude::*,
+    routes::{ErrRsp, GenericRsp},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use sea_orm::{
+    sea_query::OnConflict, ActiveValue::NotSet, ColumnTrait, Condition, EntityTrait, QueryFilter,
+    Set,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+const MAX_REVIEW_LENGTH: usize = 5000;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RatingRequest {
+    /// From 1 to 10
+    pub rating: u32,
+    /// `null` removes the review
+    pub review: Option<String>,
+}
+
+/// Rate a title, and optionally review it. Rating it again replaces both.
+#[utoipa::path(put, path = "/api/user/rating/{title_id}", request_body = RatingRequest, responses(
+    (status = 200, description = "Rate title successful", body = GenericResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody)
+))]
+pub async fn put_rating(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(title_id): Path<String>,
+    Json(query): Json<RatingRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    if !(1..=10).contains(&query.rating) {
+        return Err(ErrRsp::bad_request("Rating must be between 1 and 10."));
+    }
+    let review = query
+        .review
+        .as_deref()
+        .map(str::trim)
+        .filter(|review| !review.is_empty())
+        .map(str::to_string);
+    if review
+        .as_ref()
+        .is_some_and(|review| review.chars().count() > MAX_REVIEW_LENGTH)
+    {
+        return Err(ErrRsp::bad_request(format!(
+            "Review must be at most {} characters long.",
+            MAX_REVIEW_LENGTH
+        )));
+    }
+
+    let title = Titles::find_by_id(title_id)
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::bad_request("Invalid title id."))?;
+
+    // rating twice at once mustn't add two ratings, the pair is unique
+    let now = chrono::Utc::now().to_rfc3339();
+    Ratings::insert(ratings::ActiveModel {
+        id: NotSet,
+        user_id: Set(user.id),
+        title_id: Set(title.id),
+        rating: Set(query.rating),
+        review: Set(review),
+        created_at: Set(now.clone()),
+        updated_at: Set(now),
+    })
+    .on_conflict(
+        OnConflict::columns([ratings::Column::UserId, ratings::Column::TitleId])
+            .update_columns([
+                ratings::Column::Rating,
+                ratings::Column::Review,
+                ratings::Column::UpdatedAt,
+            ])
+            .to_owned(),
+    )
+    .exec(&data.db)
+    .await
+    .map_err(ErrRsp::db)?;
+
+    Ok(GenericRsp::create("Rating set."))
+}
+
+/// Remove the user's rating and review of a title.
+#[utoipa::path(delete, path = "/api/user/rating/{title_id}", responses(
+    (status = 200, description = "Delete rating successful", body = GenericResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody)
+))]
+pub async fn delete_rating(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(title_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    Ratings::delete_many()
+        .filter(
+            Condition::all()
+                .add(ratings::Column::TitleId.eq(&title_id))
+                .add(ratings::Column::UserId.eq(&user.id)),
+        )
+        .exec(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    Ok(GenericRsp::create("Delete rating successful."))
+}
@@ -0,0 +1,106 @@
+use crate::{
+    models::{prelude::*, reader::ReaderSettings},
+    routes::{ErrRsp, GenericRsp},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, Condition, EntityTrait, QueryFilter, Set,
+};
+use std::sync::Arc;
+
+/// Override how a title is read, for the user. Setting them again replaces
+/// all of them, `null` ones follow the title.
+#[utoipa::path(put, path = "/api/user/reader/{title_id}", request_body = ReaderSettings, responses(
+    (status = 200, description = "Set reader preferences successful", body = GenericResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody)
+))]
+pub async fn put_reader_preference(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(title_id): Path<String>,
+    Json(query): Json<ReaderSettings>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let title = Titles::find_by_id(title_id)
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::bad_request("Invalid title id."))?;
+
+    let preference_model = ReaderPreferences::find()
+        .filter(
+            Condition::all()
+                .add(reader_preferences::Column::TitleId.eq(&title.id))
+                .add(reader_preferences::Column::UserId.eq(&user.id)),
+        )
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    if query.is_empty() {
+        if let Some(preference_model) = preference_model {
+            ReaderPreferences::delete_by_id(preference_model.id)
+                .exec(&data.db)
+                .await
+                .map_err(ErrRsp::db)?;
+        }
+        return Ok(GenericRsp::create("Reader preferences removed."));
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    if let Some(preference_model) = preference_model {
+        let mut active_model: reader_preferences::ActiveModel = preference_model.into();
+        active_model.reading_direction = Set(query.reading_direction);
+        active_model.layout = Set(query.layout);
+        active_model.zoom = Set(query.zoom);
+        active_model.updated_at = Set(now);
+        active_model.update(&data.db).await.map_err(ErrRsp::db)?;
+
+        return Ok(GenericRsp::create("Reader preferences updated."));
+    }
+
+    reader_preferences::ActiveModel {
+        id: NotSet,
+        user_id: Set(user.id),
+        title_id: Set(title.id),
+        reading_direction: Set(query.reading_direction),
+        layout: Set(query.layout),
+        zoom: Set(query.zoom),
+        updated_at: Set(now),
+    }
+    .insert(&data.db)
+    .await
+    .map_err(ErrRsp::db)?;
+
+    Ok(GenericRsp::create("Reader preferences set."))
+}
+
+/// Read a title the way it's set to be read again.
+#[utoipa::path(delete, path = "/api/user/reader/{title_id}", responses(
+    (status = 200, description = "Delete reader preferences successful", body = GenericResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody)
+))]
+pub async fn delete_reader_preference(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(title_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    ReaderPreferences::delete_many()
+        .filter(
+            Condition::all()
+                .add(reader_preferences::Column::TitleId.eq(&title_id))
+                .add(reader_preferences::Column::UserId.eq(&user.id)),
+        )
+        .exec(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    Ok(GenericRsp::create("Reader preferences removed."))
+}
@@ -1,9 +1,7 @@
-use super::sendmail;
+use super::{action_link, purpose_token};
 use crate::{
-    models::{
-        auth::{TokenClaims, TokenClaimsPurpose},
-        prelude::*,
-    },
+    mailer::MailTemplate,
+    models::{auth::TokenClaimsPurpose, prelude::*},
     routes::{ErrRsp, GenericRsp},
     AppState,
 };
@@ -34,11 +32,9 @@ pub async fn get_reset(
     State(data): State<Arc<AppState>>,
     Path(email): Path<String>,
 ) -> Result<impl IntoResponse, ErrRsp> {
-    if data.env.smtp_host.is_none() {
-        return Err(ErrRsp::internal(
-            "SMTP is not configured, please contact the server administrator.",
-        ));
-    }
+    let mailer = data.mailer.as_ref().ok_or_else(|| {
+        ErrRsp::internal("Mail is not configured, please contact the server administrator.")
+    })?;
 
     if !email_address::EmailAddress::is_valid(&email) {
         return Err(ErrRsp::bad_request("Invalid email."));
@@ -55,46 +51,17 @@ pub async fn get_reset(
         return Err(ErrRsp::bad_request("User is not verified."));
     }
 
-    let token = {
-        let now = chrono::Utc::now();
-        let token_claims = TokenClaims {
-            sub: user.id.clone(),
-            iat: now.timestamp() as usize,
-            exp: (now + chrono::Duration::hours(1)).timestamp() as usize,
-            purpose: Some(TokenClaimsPurpose::ResetPassword),
-        };
-        jsonwebtoken::encode(
-            &jsonwebtoken::Header::default(),
-            &token_claims,
-            &jsonwebtoken::EncodingKey::from_secret(data.env.jwt_secret.as_ref()),
-        )
-    }
-    .map_err(|e| ErrRsp::internal(format!("Failed to generate token. JWT error: {}", e)))?;
-
-    let email = format!(
-        "Hello, {}!\n\n\
-        You have requested to reset your password. Please copy the following token into the app to continue:\n\n\
-        {}\n\n\
-        If you did not request to reset your password, please ignore this email.\n\n\
-        Best regards,\n\
-        The {} team",
-        &user.username,
-        token,
-        &data.env.app_name,
-    );
+    let token = purpose_token(&data.env, &user, TokenClaimsPurpose::ResetPassword)?;
+    let link = action_link(&data.env, "reset", &token);
 
-    match sendmail(
-        &data.env,
+    match mailer.send(
+        MailTemplate::ResetPassword,
         &user.username,
         &user.email,
-        &format!("{} - Reset your password", &data.env.app_name),
-        &email,
+        &[("token", &token), ("link", &link)],
     ) {
         Ok(_) => Ok(GenericRsp::create("Token sent to user's email.")),
-        Err(e) => Err(ErrRsp::internal(format!(
-            "Failed to send email. SMTP error: {}",
-            e
-        ))),
+        Err(e) => Err(ErrRsp::internal(format!("Failed to send email: {}", e))),
     }
 }
 
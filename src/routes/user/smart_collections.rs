@@ -0,0 +1,266 @@
+use crate::{
+    models::prelude::*,
+    routes::{
+        filter_titles, validate_filter, ErrRsp, FilterRequest, FilterTitleResponseBody, GenericRsp,
+    },
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+    QueryTrait, Set,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SmartCollectionRequest {
+    pub name: String,
+    /// Same as the body of `POST /api/index/filter`
+    pub filter: FilterRequest,
+    /// Show it on the home screen, `false` by default
+    pub is_pinned: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ModifySmartCollectionRequest {
+    pub name: Option<String>,
+    pub filter: Option<FilterRequest>,
+    pub is_pinned: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SmartCollectionsQuery {
+    /// Only list the pinned ones
+    pub pinned: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SmartCollectionResponseBody {
+    pub id: String,
+    pub name: String,
+    pub filter: FilterRequest,
+    pub is_pinned: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SmartCollectionsResponseBody {
+    pub data: Vec<SmartCollectionResponseBody>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SmartCollectionTitlesResponseBody {
+    pub smart_collection: SmartCollectionResponseBody,
+    /// The titles matching the filter right now
+    pub titles: Vec<FilterTitleResponseBody>,
+}
+
+fn check_name(name: &str) -> Result<String, ErrRsp> {
+    let name = name.trim();
+    match name.chars().count() {
+        1..=100 => Ok(name.to_string()),
+        _ => Err(ErrRsp::bad_request(
+            "Smart collection name must be between 1 and 100 characters long.",
+        )),
+    }
+}
+
+pub fn smart_collection_response(
+    smart_collection: smart_collections::Model,
+) -> Result<SmartCollectionResponseBody, ErrRsp> {
+    let filter = serde_json::from_str(&smart_collection.filter)
+        .map_err(|e| ErrRsp::internal(format!("Invalid saved filter: {}", e)))?;
+
+    Ok(SmartCollectionResponseBody {
+        id: smart_collection.id,
+        name: smart_collection.name,
+        filter,
+        is_pinned: smart_collection.is_pinned,
+        created_at: smart_collection.created_at,
+        updated_at: smart_collection.updated_at,
+    })
+}
+
+async fn find_smart_collection(
+    db: &DatabaseConnection,
+    user_id: &str,
+    smart_collection_id: &str,
+) -> Result<smart_collections::Model, ErrRsp> {
+    SmartCollections::find_by_id(smart_collection_id)
+        .filter(smart_collections::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("Smart collection not found."))
+}
+
+/// Re-evaluate a smart collection's filter.
+pub async fn smart_collection_titles(
+    db: &DatabaseConnection,
+    user_id: &str,
+    smart_collection: smart_collections::Model,
+) -> Result<SmartCollectionTitlesResponseBody, ErrRsp> {
+    let smart_collection = smart_collection_response(smart_collection)?;
+    let titles = filter_titles(db, user_id, smart_collection.filter.clone()).await?;
+
+    Ok(SmartCollectionTitlesResponseBody {
+        smart_collection,
+        titles,
+    })
+}
+
+/// Get the user's smart collections, pinned ones first.
+#[utoipa::path(get, path = "/api/user/smart_collections", params(SmartCollectionsQuery), responses(
+    (status = 200, description = "Fetch smart collections successful", body = SmartCollectionsResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_smart_collections(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Query(query): Query<SmartCollectionsQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let smart_collections = SmartCollections::find()
+        .filter(smart_collections::Column::UserId.eq(&user.id))
+        .apply_if(query.pinned, |select, is_pinned| {
+            select.filter(smart_collections::Column::IsPinned.eq(is_pinned))
+        })
+        .order_by_desc(smart_collections::Column::IsPinned)
+        .order_by_asc(smart_collections::Column::Name)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let data = smart_collections
+        .into_iter()
+        .map(smart_collection_response)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((StatusCode::OK, Json(SmartCollectionsResponseBody { data })))
+}
+
+/// Save a filter as a smart collection.
+#[utoipa::path(post, path = "/api/user/smart_collections", request_body = SmartCollectionRequest, responses(
+    (status = 200, description = "Create smart collection successful", body = SmartCollectionResponseBody),
+    (status = 400, description = "Bad request, or invalid ids in the filter", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn post_smart_collection(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Json(query): Json<SmartCollectionRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let name = check_name(&query.name)?;
+    validate_filter(&data.db, &user.id, &query.filter).await?;
+    let filter = serde_json::to_string(&query.filter)
+        .map_err(|e| ErrRsp::internal(format!("Can't save filter: {}", e)))?;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let smart_collection = smart_collections::ActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        user_id: Set(user.id),
+        name: Set(name),
+        filter: Set(filter),
+        is_pinned: Set(query.is_pinned.unwrap_or(false)),
+        created_at: Set(now.clone()),
+        updated_at: Set(now),
+    }
+    .insert(&data.db)
+    .await
+    .map_err(ErrRsp::db)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(smart_collection_response(smart_collection)?),
+    ))
+}
+
+/// Get a smart collection and the titles currently matching it.
+#[utoipa::path(get, path = "/api/user/smart_collections/{smart_collection_id}", responses(
+    (status = 200, description = "Fetch smart collection successful", body = SmartCollectionTitlesResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Smart collection not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_smart_collection(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(smart_collection_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let smart_collection = find_smart_collection(&data.db, &user.id, &smart_collection_id).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(smart_collection_titles(&data.db, &user.id, smart_collection).await?),
+    ))
+}
+
+/// Rename, change the filter of, or (un)pin a smart collection.
+#[utoipa::path(put, path = "/api/user/smart_collections/{smart_collection_id}", request_body = ModifySmartCollectionRequest, responses(
+    (status = 200, description = "Modify smart collection successful", body = SmartCollectionResponseBody),
+    (status = 400, description = "Bad request, or invalid ids in the filter", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Smart collection not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn put_smart_collection(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(smart_collection_id): Path<String>,
+    Json(query): Json<ModifySmartCollectionRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let smart_collection = find_smart_collection(&data.db, &user.id, &smart_collection_id).await?;
+
+    let mut active_model: smart_collections::ActiveModel = smart_collection.into();
+    if let Some(name) = query.name {
+        active_model.name = Set(check_name(&name)?);
+    }
+    if let Some(filter) = query.filter {
+        validate_filter(&data.db, &user.id, &filter).await?;
+        let filter = serde_json::to_string(&filter)
+            .map_err(|e| ErrRsp::internal(format!("Can't save filter: {}", e)))?;
+        active_model.filter = Set(filter);
+    }
+    if let Some(is_pinned) = query.is_pinned {
+        active_model.is_pinned = Set(is_pinned);
+    }
+    active_model.updated_at = Set(chrono::Utc::now().to_rfc3339());
+    let smart_collection = active_model.update(&data.db).await.map_err(ErrRsp::db)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(smart_collection_response(smart_collection)?),
+    ))
+}
+
+/// Delete a smart collection.
+#[utoipa::path(delete, path = "/api/user/smart_collections/{smart_collection_id}", responses(
+    (status = 200, description = "Delete smart collection successful", body = GenericResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "Smart collection not found", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn delete_smart_collection(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(smart_collection_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let smart_collection = find_smart_collection(&data.db, &user.id, &smart_collection_id).await?;
+
+    SmartCollections::delete_by_id(smart_collection.id)
+        .exec(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    Ok(GenericRsp::create("Delete smart collection successful."))
+}
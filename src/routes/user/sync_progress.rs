@@ -0,0 +1,305 @@
+use super::record_reading;
+use crate::{
+    models::prelude::*,
+    routes::{find_page_count, ErrRsp},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, Set, TryIntoModel,
+};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::sync::Arc;
+use tracing::warn;
+use utoipa::ToSchema;
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProgressResponseBody {
+    pub title_id: String,
+    /// 0-based index of the page being read
+    pub page: i64,
+    /// How far down the page, from 0.0 to 1.0
+    pub scroll_offset: f32,
+    /// How much of the title has been read, from 0.0 to 100.0
+    pub percent: f32,
+    pub completed: bool,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub last_read_at: String,
+    pub device_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProgressesResponseBody {
+    pub data: Vec<ProgressResponseBody>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStrategy {
+    /// The update recorded last on its device wins
+    #[default]
+    LastWriterWins,
+    /// The update furthest into the title wins
+    MaxPage,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ProgressUpdate {
+    pub title_id: String,
+    /// 0-based index of the page being read
+    pub page: i64,
+    /// How far down the page, from 0.0 to 1.0
+    pub scroll_offset: Option<f32>,
+    pub completed: Option<bool>,
+    pub device_id: Option<String>,
+    /// When the device recorded this progress, RFC 3339, defaults to now
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ProgressSyncRequest {
+    pub strategy: Option<SyncStrategy>,
+    pub progresses: Vec<ProgressUpdate>,
+}
+
+pub async fn progress_response(
+    db: &DatabaseConnection,
+    progress: progresses::Model,
+) -> ProgressResponseBody {
+    let scroll_offset = progress.scroll_offset as f32 / 1000.0;
+    let percent = match progress.completed {
+        true => 100.0,
+        false => match find_page_count(db, &progress.title_id).await {
+            0 => 0.0,
+            page_count => {
+                ((progress.page as f32 + scroll_offset) / page_count as f32 * 100.0).min(100.0)
+            }
+        },
+    };
+
+    ProgressResponseBody {
+        title_id: progress.title_id,
+        page: progress.page,
+        scroll_offset,
+        percent,
+        completed: progress.completed,
+        started_at: progress.started_at,
+        finished_at: progress.finished_at,
+        last_read_at: progress.last_read_at,
+        device_id: progress.device_id,
+    }
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Merge an update from a device into the stored progress, returns `None`
+/// when the stored progress wins.
+fn merge_progress(
+    user_id: &str,
+    stored: Option<progresses::Model>,
+    update: &ProgressUpdate,
+    strategy: SyncStrategy,
+) -> Option<progresses::ActiveModel> {
+    let now = Utc::now();
+    // a device with a clock set in the future shouldn't win forever
+    let updated_at = update
+        .updated_at
+        .as_deref()
+        .and_then(parse_time)
+        .map_or(now, |time| time.min(now));
+    let scroll_offset = update
+        .scroll_offset
+        .map(|offset| (offset.clamp(0.0, 1.0) * 1000.0) as u32);
+    let page = update.page.max(0);
+
+    let stored = match stored {
+        Some(stored) => stored,
+        None => {
+            let completed = update.completed.unwrap_or(false);
+            return Some(progresses::ActiveModel {
+                id: NotSet,
+                user_id: Set(user_id.to_string()),
+                title_id: Set(update.title_id.clone()),
+                last_read_at: Set(updated_at.to_rfc3339()),
+                page: Set(page),
+                scroll_offset: Set(scroll_offset.unwrap_or(0)),
+                completed: Set(completed),
+                started_at: Set(Some(updated_at.to_rfc3339())),
+                finished_at: Set(completed.then(|| updated_at.to_rfc3339())),
+                device_id: Set(update.device_id.clone()),
+            });
+        }
+    };
+
+    // updates without an offset, like `put_progress` and Komga's, keep the
+    // stored one while on the same page
+    let scroll_offset = match scroll_offset {
+        Some(scroll_offset) => scroll_offset,
+        None if page == stored.page => stored.scroll_offset,
+        None => 0,
+    };
+    let stored_at = parse_time(&stored.last_read_at).unwrap_or_default();
+    let update_wins = match strategy {
+        SyncStrategy::LastWriterWins => updated_at > stored_at,
+        SyncStrategy::MaxPage => {
+            (page, scroll_offset) > (stored.page, stored.scroll_offset)
+                || (update.completed == Some(true) && !stored.completed)
+        }
+    };
+    if !update_wins {
+        return None;
+    }
+
+    let completed = update.completed.unwrap_or(stored.completed);
+    let finished_at = match completed && !stored.completed {
+        true => Some(updated_at.to_rfc3339()),
+        false => stored.finished_at.clone(),
+    };
+    let started_at = stored.started_at.clone().unwrap_or(updated_at.to_rfc3339());
+
+    let mut active_model: progresses::ActiveModel = stored.into();
+    active_model.last_read_at = Set(updated_at.max(stored_at).to_rfc3339());
+    active_model.page = Set(page);
+    active_model.scroll_offset = Set(scroll_offset);
+    active_model.completed = Set(completed);
+    active_model.started_at = Set(Some(started_at));
+    active_model.finished_at = Set(finished_at);
+    active_model.device_id = Set(update.device_id.clone());
+    Some(active_model)
+}
+
+/// Merge `update` into the `stored` progress and save it, recording the
+/// reading session if the update wins.
+pub async fn apply_progress_update(
+    db: &DatabaseConnection,
+    user_id: &str,
+    stored: Option<progresses::Model>,
+    update: &ProgressUpdate,
+    strategy: SyncStrategy,
+) -> Result<(), ErrRsp> {
+    let Some(active_model) = merge_progress(user_id, stored, update, strategy) else {
+        return Ok(());
+    };
+
+    let progress = active_model
+        .save(db)
+        .await
+        .and_then(|progress| progress.try_into_model())
+        .map_err(ErrRsp::db)?;
+    if let Some(at) = parse_time(&progress.last_read_at) {
+        if let Err(e) = record_reading(db, user_id, &update.title_id, progress.page, at).await {
+            warn!(
+                "record reading failed | title {} | user {}: {}",
+                update.title_id, user_id, e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the user's progress on a title.
+#[utoipa::path(get, path = "/api/user/progress/{title_id}", responses(
+    (status = 200, description = "Fetch progress successful", body = ProgressResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 404, description = "No progress for this title", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_progress(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Path(title_id): Path<String>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let progress = Progresses::find()
+        .filter(progresses::Column::TitleId.eq(&title_id))
+        .filter(progresses::Column::UserId.eq(&user.id))
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?
+        .ok_or_else(|| ErrRsp::not_found("No progress for this title."))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(progress_response(&data.db, progress).await),
+    ))
+}
+
+/// Get the user's progress on every title, most recently read first.
+#[utoipa::path(get, path = "/api/user/progress", responses(
+    (status = 200, description = "Fetch progresses successful", body = ProgressesResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_progresses(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let progresses = Progresses::find()
+        .filter(progresses::Column::UserId.eq(&user.id))
+        .order_by_desc(progresses::Column::LastReadAt)
+        .all(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let mut resp_data = Vec::with_capacity(progresses.len());
+    for progress in progresses {
+        resp_data.push(progress_response(&data.db, progress).await);
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(ProgressesResponseBody { data: resp_data }),
+    ))
+}
+
+/// Push the progresses recorded on a device and pull everything back.
+///
+/// Conflicts with the stored progress are resolved with `strategy`,
+/// `last_writer_wins` by default. The response holds the merged progress of
+/// every title the user has started, most recently read first.
+#[utoipa::path(post, path = "/api/user/progress/sync", request_body = ProgressSyncRequest, responses(
+    (status = 200, description = "Sync progresses successful", body = ProgressesResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn post_sync_progress(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Json(query): Json<ProgressSyncRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let strategy = query.strategy.unwrap_or_default();
+
+    for update in &query.progresses {
+        Titles::find_by_id(&update.title_id)
+            .one(&data.db)
+            .await
+            .map_err(ErrRsp::db)?
+            .ok_or_else(|| ErrRsp::bad_request(format!("Invalid title id: {}", update.title_id)))?;
+
+        let stored = Progresses::find()
+            .filter(progresses::Column::TitleId.eq(&update.title_id))
+            .filter(progresses::Column::UserId.eq(&user.id))
+            .one(&data.db)
+            .await
+            .map_err(ErrRsp::db)?;
+
+        apply_progress_update(&data.db, &user.id, stored, update, strategy).await?;
+    }
+
+    get_progresses(State(data), Extension(user)).await
+}
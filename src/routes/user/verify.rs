@@ -1,9 +1,7 @@
-use super::sendmail;
+use super::{action_link, purpose_token};
 use crate::{
-    models::{
-        auth::{TokenClaims, TokenClaimsPurpose},
-        prelude::*,
-    },
+    mailer::MailTemplate,
+    models::{auth::TokenClaimsPurpose, prelude::*},
     routes::{ErrRsp, GenericRsp},
     AppState,
 };
@@ -26,49 +24,21 @@ pub async fn get_verify(
         return Err(ErrRsp::bad_request("User is already verified."));
     }
 
-    if data.env.smtp_host.is_none() {
-        return Err(ErrRsp::internal(
-            "SMTP is not configured, please contact the server administrator.",
-        ));
-    }
-
-    let now = chrono::Utc::now();
-    let token_claims = TokenClaims {
-        sub: user.id,
-        iat: now.timestamp() as usize,
-        exp: (now + chrono::Duration::hours(1)).timestamp() as usize,
-        purpose: Some(TokenClaimsPurpose::VerifyRegister),
-    };
-    let token = jsonwebtoken::encode(
-        &jsonwebtoken::Header::default(),
-        &token_claims,
-        &jsonwebtoken::EncodingKey::from_secret(data.env.jwt_secret.as_ref()),
-    )
-    .map_err(|e| ErrRsp::internal(format!("Failed to generate token. JWT error: {}", e)))?;
+    let mailer = data.mailer.as_ref().ok_or_else(|| {
+        ErrRsp::internal("Mail is not configured, please contact the server administrator.")
+    })?;
 
-    let body = format!(
-        "Hello {},\n\n\
-        You have requested to verify your account. \
-        Please click copy the following token into the app to continue:\n\n\
-        {}\n\n\
-        If you did not request this, please ignore this email.\n\n\
-        Thanks,\n\
-        The {} Team",
-        &user.username, token, &data.env.app_name,
-    );
+    let token = purpose_token(&data.env, &user, TokenClaimsPurpose::VerifyRegister)?;
+    let link = action_link(&data.env, "verify", &token);
 
-    match sendmail(
-        &data.env,
+    match mailer.send(
+        MailTemplate::VerifyAccount,
         &user.username,
         &user.email,
-        &format!("{} - Verify your account", &data.env.app_name),
-        &body,
+        &[("token", &token), ("link", &link)],
     ) {
         Ok(_) => Ok(GenericRsp::create("Verification email sent.")),
-        Err(e) => Err(ErrRsp::internal(format!(
-            "Failed to send email. SMTP error: {}",
-            e
-        ))),
+        Err(e) => Err(ErrRsp::internal(format!("Failed to send email: {}", e))),
     }
 }
 
@@ -1,9 +1,11 @@
 mod get_scanning_progress;
 mod get_tags;
 mod ssim_eval;
+mod stats;
 mod status;
 
 pub use get_scanning_progress::*;
 pub use get_tags::*;
 pub use ssim_eval::*;
+pub use stats::*;
 pub use status::*;
@@ -1,14 +1,18 @@
 use std::{path::PathBuf, sync::Arc};
 
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Extension, Json};
 use rand::{thread_rng, Rng};
-use sea_orm::{ColumnTrait, Condition, EntityTrait, PaginatorTrait, QueryFilter, QuerySelect};
-use serde::Serialize;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, Condition, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QuerySelect, Set,
+};
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use crate::{
+    livescan::title_ssim_score::title_similarity,
     models::prelude::*,
-    routes::{calculate_dimension, ErrRsp},
+    routes::{calculate_dimension, ErrRsp, GenericRsp},
     AppState,
 };
 
@@ -26,6 +30,14 @@ pub struct SsimEvalTitle {
     pub format: String,
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SsimFeedbackRequest {
+    pub title_id_a: String,
+    pub title_id_b: String,
+    /// Whether the user thinks the titles are similar
+    pub is_similar: bool,
+}
+
 #[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct SsimEvalBody {
     pub title_a: SsimEvalTitle,
@@ -86,6 +98,71 @@ async fn get_title(index: u64, data: &Arc<AppState>) -> Result<SsimEvalTitle, Er
     })
 }
 
+async fn find_cover_hashes(
+    db: &DatabaseConnection,
+    title_id: &str,
+) -> Result<Option<(i64, i64)>, ErrRsp> {
+    let thumbnail = Thumbnails::find_by_id(title_id)
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?;
+    Ok(thumbnail.and_then(|thumbnail| Some((thumbnail.dhash?, thumbnail.phash?))))
+}
+
+/// The similarity of two titles, from 0.0 to 1.0.
+pub async fn find_ssim(
+    db: &DatabaseConnection,
+    title_id_a: &str,
+    title_id_b: &str,
+) -> Result<Option<f32>, ErrRsp> {
+    let condition = Condition::any()
+        .add(
+            Condition::all()
+                .add(titles_ssim::Column::TitleIdA.eq(title_id_a))
+                .add(titles_ssim::Column::TitleIdB.eq(title_id_b)),
+        )
+        .add(
+            Condition::all()
+                .add(titles_ssim::Column::TitleIdA.eq(title_id_b))
+                .add(titles_ssim::Column::TitleIdB.eq(title_id_a)),
+        );
+
+    if let Some(pair) = TitlesSsim::find()
+        .filter(condition)
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?
+    {
+        return Ok(Some(pair.ssim as f32 / 1000.0));
+    }
+
+    // only the top-k neighbours are kept, score the pair like the scan does
+    let embedding_a = TitleEmbeddings::find_by_id(title_id_a)
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?;
+    let embedding_b = TitleEmbeddings::find_by_id(title_id_b)
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?;
+    let (embedding_a, embedding_b) = match (embedding_a, embedding_b) {
+        (Some(a), Some(b)) if a.model_id == b.model_id => (a, b),
+        _ => return Ok(None),
+    };
+    let cover_a = find_cover_hashes(db, title_id_a).await?;
+    let cover_b = find_cover_hashes(db, title_id_b).await?;
+
+    Ok(Some(
+        title_similarity(
+            &embedding_a.decode_vector(),
+            &embedding_b.decode_vector(),
+            cover_a,
+            cover_b,
+        )
+        .clamp(0.0, 1.0),
+    ))
+}
+
 // Return 2 random titles from DB and their SSIM score
 #[utoipa::path(get, path = "/api/utils/ssim_eval", responses(
     (status = 200, description = "2 random title", body = SsimEvalBody),
@@ -99,26 +176,9 @@ pub async fn get_ssim_eval(State(data): State<Arc<AppState>>) -> Result<impl Int
     let title_a = get_title(title_a_index, &data).await?;
     let title_b = get_title(title_b_index, &data).await?;
 
-    let condition = Condition::any()
-        .add(
-            Condition::all()
-                .add(titles_ssim::Column::TitleIdA.eq(title_a.id.clone()))
-                .add(titles_ssim::Column::TitleIdB.eq(title_b.id.clone())),
-        )
-        .add(
-            Condition::all()
-                .add(titles_ssim::Column::TitleIdA.eq(title_b.id.clone()))
-                .add(titles_ssim::Column::TitleIdB.eq(title_a.id.clone())),
-        );
-
-    let ssim_score = TitlesSsim::find()
-        .filter(condition)
-        .one(&data.db)
-        .await
-        .map_err(ErrRsp::db)?
-        .ok_or_else(|| ErrRsp::internal("Can't find ssim score"))?
-        .ssim as f32
-        / 1000.0;
+    let ssim_score = find_ssim(&data.db, &title_a.id, &title_b.id)
+        .await?
+        .ok_or_else(|| ErrRsp::internal("Can't find ssim score"))?;
 
     Ok((
         StatusCode::OK,
@@ -129,3 +189,74 @@ pub async fn get_ssim_eval(State(data): State<Arc<AppState>>) -> Result<impl Int
         }),
     ))
 }
+
+/// Tell whether two titles are actually similar, answering again replaces
+/// the previous answer.
+#[utoipa::path(post, path = "/api/utils/ssim_eval", request_body = SsimFeedbackRequest, responses(
+    (status = 200, description = "Feedback saved", body = GenericResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn post_ssim_eval(
+    State(data): State<Arc<AppState>>,
+    Extension(user): Extension<users::Model>,
+    Json(query): Json<SsimFeedbackRequest>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    if query.title_id_a == query.title_id_b {
+        return Err(ErrRsp::bad_request("The two titles must be different."));
+    }
+    for title_id in [&query.title_id_a, &query.title_id_b] {
+        Titles::find_by_id(title_id)
+            .one(&data.db)
+            .await
+            .map_err(ErrRsp::db)?
+            .ok_or_else(|| ErrRsp::bad_request(format!("Invalid title id: {}", title_id)))?;
+    }
+
+    let ssim = find_ssim(&data.db, &query.title_id_a, &query.title_id_b)
+        .await?
+        .ok_or_else(|| ErrRsp::internal("Can't find ssim score"))?;
+
+    // stored in a fixed order so both orders count as the same pair
+    let (title_id_a, title_id_b) = match query.title_id_a < query.title_id_b {
+        true => (query.title_id_a, query.title_id_b),
+        false => (query.title_id_b, query.title_id_a),
+    };
+
+    let feedback = SsimFeedbacks::find()
+        .filter(ssim_feedbacks::Column::UserId.eq(&user.id))
+        .filter(ssim_feedbacks::Column::TitleIdA.eq(&title_id_a))
+        .filter(ssim_feedbacks::Column::TitleIdB.eq(&title_id_b))
+        .one(&data.db)
+        .await
+        .map_err(ErrRsp::db)?;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    match feedback {
+        Some(feedback) => {
+            let mut active_model: ssim_feedbacks::ActiveModel = feedback.into();
+            active_model.is_similar = Set(query.is_similar);
+            active_model.ssim = Set((ssim * 1000.0) as u16);
+            active_model.updated_at = Set(now);
+            active_model.update(&data.db).await.map_err(ErrRsp::db)?;
+        }
+        None => {
+            ssim_feedbacks::ActiveModel {
+                id: NotSet,
+                user_id: Set(user.id),
+                title_id_a: Set(title_id_a),
+                title_id_b: Set(title_id_b),
+                is_similar: Set(query.is_similar),
+                ssim: Set((ssim * 1000.0) as u16),
+                created_at: Set(now.clone()),
+                updated_at: Set(now),
+            }
+            .insert(&data.db)
+            .await
+            .map_err(ErrRsp::db)?;
+        }
+    }
+
+    Ok(GenericRsp::create("Feedback saved."))
+}
@@ -0,0 +1,656 @@
+use crate::{routes::ErrRsp, AppState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use sea_orm::{ConnectionTrait, DatabaseConnection, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    sync::Arc,
+};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct StatsQuery {
+    /// First day of the range, `YYYY-MM-DD` in UTC, 30 days before `to` by default
+    pub from: Option<NaiveDate>,
+    /// Last day of the range, `YYYY-MM-DD` in UTC, today by default
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct PeriodStats {
+    /// `YYYY-MM-DD` for a day, `YYYY-Www` for an ISO week
+    pub period: String,
+    pub pages_read: i64,
+    pub titles_read: u64,
+    /// In seconds
+    pub time_spent: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RankedStats {
+    /// Tag id or author name
+    pub id: String,
+    pub name: String,
+    pub score: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CategoryStats {
+    pub id: String,
+    pub name: String,
+    pub started: u64,
+    pub completed: u64,
+    /// From 0.0 to 1.0
+    pub completion_rate: f32,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatsResponseBody {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub pages_read: i64,
+    pub titles_read: u64,
+    pub titles_finished: u64,
+    /// In seconds
+    pub time_spent: i64,
+    /// Consecutive days with some reading, up to today
+    pub current_streak: u64,
+    pub longest_streak: u64,
+    pub daily: Vec<PeriodStats>,
+    pub weekly: Vec<PeriodStats>,
+    pub top_tags: Vec<RankedStats>,
+    pub top_authors: Vec<RankedStats>,
+    pub categories: Vec<CategoryStats>,
+    /// Server-wide only, users who read something in the range
+    pub readers: Option<u64>,
+}
+
+/// Longest run of consecutive days, and the run ending today or yesterday.
+fn streaks(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> (u64, u64) {
+    let (mut longest, mut run) = (0, 0);
+    let mut previous: Option<NaiveDate> = None;
+    for day in days {
+        run = match previous {
+            Some(previous) if *day - previous == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*day);
+    }
+
+    let current = match previous {
+        Some(last) if today - last <= Duration::days(1) => run,
+        _ => 0,
+    };
+    (current, longest)
+}
+
+#[derive(Debug, FromQueryResult)]
+struct TitleDayStats {
+    day: String,
+    title_id: String,
+    pages_read: i64,
+    time_spent: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct DayRow {
+    day: String,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct CountRow {
+    count: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct RankedRow {
+    id: String,
+    name: String,
+    score: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct CategoryRow {
+    id: String,
+    name: String,
+    started: i64,
+    completed: i64,
+}
+
+/// Titles scoring tags and authors: the ones read in the range once, plus one
+/// for each favorite and bookmark.
+const SCORED_TITLES: &str = "
+    WITH scored AS (
+        SELECT DISTINCT title_id FROM reading_sessions
+        WHERE (? IS NULL OR user_id = ?) AND date(started_at) BETWEEN ? AND ?
+        UNION ALL
+        SELECT title_id FROM favorites WHERE (? IS NULL OR user_id = ?)
+        UNION ALL
+        SELECT title_id FROM bookmarks WHERE (? IS NULL OR user_id = ?)
+    )";
+
+/// Aggregate the reading statistics of a user, or of everyone with `None`.
+///
+/// Reading activity comes from the reading sessions, completion from the
+/// progresses. Tags and authors are scored by the titles read in the range,
+/// plus one for each favorite and bookmark. The aggregation is done by SQLite,
+/// only the per day and title sums are loaded.
+pub async fn reading_stats(
+    db: &DatabaseConnection,
+    user_id: Option<&str>,
+    query: &StatsQuery,
+) -> Result<StatsResponseBody, ErrRsp> {
+    let today = Utc::now().date_naive();
+    let to = query.to.unwrap_or(today);
+    let from = query.from.unwrap_or(to - Duration::days(30));
+    if from > to {
+        return Err(ErrRsp::bad_request("`from` must not be after `to`."));
+    }
+
+    let user = || Value::from(user_id.map(|user_id| user_id.to_string()));
+    let range = || vec![Value::from(from.to_string()), Value::from(to.to_string())];
+    let statement = |sql: &str, values: Vec<Value>| {
+        Statement::from_sql_and_values(db.get_database_backend(), sql, values)
+    };
+
+    // streaks are counted over the whole history, not only the range
+    let reading_days = DayRow::find_by_statement(statement(
+        "SELECT DISTINCT date(started_at) AS day FROM reading_sessions
+        WHERE (? IS NULL OR user_id = ?) AND date(started_at) IS NOT NULL",
+        vec![user(), user()],
+    ))
+    .all(db)
+    .await
+    .map_err(ErrRsp::db)?
+    .into_iter()
+    .filter_map(|row| row.day.parse().ok())
+    .collect::<BTreeSet<NaiveDate>>();
+    let (current_streak, longest_streak) = streaks(&reading_days, today);
+
+    let title_days = TitleDayStats::find_by_statement(statement(
+        "SELECT date(started_at) AS day, title_id,
+            SUM(pages_read) AS pages_read, SUM(duration) AS time_spent
+        FROM reading_sessions
+        WHERE (? IS NULL OR user_id = ?) AND date(started_at) BETWEEN ? AND ?
+        GROUP BY day, title_id",
+        [vec![user(), user()], range()].concat(),
+    ))
+    .all(db)
+    .await
+    .map_err(ErrRsp::db)?;
+
+    let mut daily: BTreeMap<NaiveDate, (PeriodStats, HashSet<&str>)> = BTreeMap::new();
+    let mut weekly: BTreeMap<(i32, u32), (PeriodStats, HashSet<&str>)> = BTreeMap::new();
+    let mut titles_read: HashSet<&str> = HashSet::new();
+    for title_day in &title_days {
+        let Ok(day) = title_day.day.parse::<NaiveDate>() else {
+            continue;
+        };
+        let week = day.iso_week();
+
+        for (stats, titles) in [
+            daily.entry(day).or_default(),
+            weekly.entry((week.year(), week.week())).or_default(),
+        ] {
+            stats.pages_read += title_day.pages_read;
+            stats.time_spent += title_day.time_spent;
+            titles.insert(&title_day.title_id);
+        }
+        titles_read.insert(&title_day.title_id);
+    }
+
+    let collect = |(period, (mut stats, titles)): (String, (PeriodStats, HashSet<&str>))| {
+        stats.period = period;
+        stats.titles_read = titles.len() as u64;
+        stats
+    };
+    let daily = daily
+        .into_iter()
+        .map(|(day, stats)| collect((day.to_string(), stats)))
+        .collect::<Vec<_>>();
+    let weekly = weekly
+        .into_iter()
+        .map(|((year, week), stats)| collect((format!("{}-W{:02}", year, week), stats)))
+        .collect::<Vec<_>>();
+
+    let readers = match user_id {
+        Some(_) => None,
+        None => CountRow::find_by_statement(statement(
+            "SELECT COUNT(DISTINCT user_id) AS count FROM reading_sessions
+            WHERE date(started_at) BETWEEN ? AND ?",
+            range(),
+        ))
+        .one(db)
+        .await
+        .map_err(ErrRsp::db)?
+        .map(|row| row.count as u64),
+    };
+
+    let titles_finished = CountRow::find_by_statement(statement(
+        "SELECT COUNT(*) AS count FROM progresses
+        WHERE (? IS NULL OR user_id = ?) AND date(finished_at) BETWEEN ? AND ?",
+        [vec![user(), user()], range()].concat(),
+    ))
+    .one(db)
+    .await
+    .map_err(ErrRsp::db)?
+    .map_or(0, |row| row.count as u64);
+
+    let scored_values = || {
+        [
+            vec![user(), user()],
+            range(),
+            vec![user(), user(), user(), user()],
+        ]
+        .concat()
+    };
+    let ranked = |row: RankedRow| RankedStats {
+        id: row.id,
+        name: row.name,
+        score: row.score as u64,
+    };
+    let top_tags = RankedRow::find_by_statement(statement(
+        &format!(
+            "{} SELECT CAST(tags.id AS TEXT) AS id, tags.name AS name, COUNT(*) AS score
+            FROM scored
+            JOIN titles_tags ON titles_tags.title_id = scored.title_id
+            JOIN tags ON tags.id = titles_tags.tag_id
+            GROUP BY tags.id, tags.name
+            ORDER BY score DESC, name
+            LIMIT 10",
+            SCORED_TITLES
+        ),
+        scored_values(),
+    ))
+    .all(db)
+    .await
+    .map_err(ErrRsp::db)?
+    .into_iter()
+    .map(ranked)
+    .collect();
+    let top_authors = RankedRow::find_by_statement(statement(
+        &format!(
+            "{} SELECT titles.author AS id, titles.author AS name, COUNT(*) AS score
+            FROM scored
+            JOIN titles ON titles.id = scored.title_id
+            WHERE titles.author IS NOT NULL
+            GROUP BY titles.author
+            ORDER BY score DESC, name
+            LIMIT 10",
+            SCORED_TITLES
+        ),
+        scored_values(),
+    ))
+    .all(db)
+    .await
+    .map_err(ErrRsp::db)?
+    .into_iter()
+    .map(ranked)
+    .collect();
+
+    let categories = CategoryRow::find_by_statement(statement(
+        "SELECT categories.id AS id, categories.name AS name,
+            COUNT(*) AS started, SUM(progresses.completed) AS completed
+        FROM progresses
+        JOIN titles ON titles.id = progresses.title_id
+        JOIN categories ON categories.id = titles.category_id
+        WHERE (? IS NULL OR progresses.user_id = ?)
+            AND date(progresses.last_read_at) BETWEEN ? AND ?
+        GROUP BY categories.id, categories.name
+        ORDER BY categories.name",
+        [vec![user(), user()], range()].concat(),
+    ))
+    .all(db)
+    .await
+    .map_err(ErrRsp::db)?
+    .into_iter()
+    .map(|row| CategoryStats {
+        id: row.id,
+        name: row.name,
+        started: row.started as u64,
+        completed: row.completed as u64,
+        completion_rate: row.completed as f32 / row.started.max(1) as f32,
+    })
+    .collect();
+
+    Ok(StatsResponseBody {
+        from,
+        to,
+        pages_read: daily.iter().map(|stats| stats.pages_read).sum(),
+        titles_read: titles_read.len() as u64,
+        titles_finished,
+        time_spent: daily.iter().map(|stats| stats.time_spent).sum(),
+        current_streak,
+        longest_streak,
+        top_tags,
+        top_authors,
+        categories,
+        readers,
+        daily,
+        weekly,
+    })
+}
+
+/// Server-wide reading statistics over a date range.
+#[utoipa::path(get, path = "/api/utils/stats", params(StatsQuery), responses(
+    (status = 200, description = "Fetch statistics successful", body = StatsResponseBody),
+    (status = 400, description = "Bad request", body = ErrorResponseBody),
+    (status = 401, description = "Unauthorized", body = ErrorResponseBody),
+    (status = 500, description = "Internal server error", body = ErrorResponseBody),
+))]
+pub async fn get_stats(
+    State(data): State<Arc<AppState>>,
+    Query(query): Query<StatsQuery>,
+) -> Result<impl IntoResponse, ErrRsp> {
+    let stats = reading_stats(&data.db, None, &query).await?;
+
+    Ok((StatusCode::OK, Json(stats)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{migrator::Migrator, models::prelude::*};
+    use sea_orm::{
+        ActiveModelTrait, ColumnTrait, ConnectOptions, Database, EntityTrait, QueryFilter, Set,
+    };
+    use sea_orm_migration::MigratorTrait;
+
+    fn date(day: &str) -> NaiveDate {
+        day.parse().unwrap()
+    }
+
+    fn at(day: &str) -> String {
+        format!("{}T10:00:00+00:00", day)
+    }
+
+    async fn session(
+        db: &DatabaseConnection,
+        user_id: &str,
+        title_id: &str,
+        day: &str,
+        pages: i64,
+    ) {
+        reading_sessions::ActiveModel {
+            user_id: Set(user_id.to_string()),
+            title_id: Set(title_id.to_string()),
+            started_at: Set(at(day)),
+            ended_at: Set(at(day)),
+            first_page: Set(0),
+            last_page: Set(pages),
+            pages_read: Set(pages),
+            duration: Set(pages * 10),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    async fn progress(
+        db: &DatabaseConnection,
+        user_id: &str,
+        title_id: &str,
+        day: &str,
+        completed: bool,
+    ) {
+        progresses::ActiveModel {
+            user_id: Set(user_id.to_string()),
+            title_id: Set(title_id.to_string()),
+            last_read_at: Set(at(day)),
+            page: Set(0),
+            scroll_offset: Set(0),
+            completed: Set(completed),
+            started_at: Set(Some(at(day))),
+            finished_at: Set(completed.then(|| at(day))),
+            device_id: Set(None),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    /// Two readers of three titles, in two categories, with two tags.
+    async fn seeded_db() -> DatabaseConnection {
+        let mut options = ConnectOptions::new("sqlite::memory:");
+        options
+            .max_connections(1)
+            .min_connections(1)
+            .sqlx_logging(false);
+        let db = Database::connect(options).await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        for user_id in ["u1", "u2"] {
+            users::ActiveModel {
+                id: Set(user_id.to_string()),
+                username: Set(user_id.to_string()),
+                email: Set(format!("{}@example.com", user_id)),
+                profile_picture: Set(None),
+                created_at: Set(at("2024-01-01")),
+                updated_at: Set(at("2024-01-01")),
+                password: Set(String::new()),
+                is_verified: Set(true),
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+        for (id, name) in [("c1", "Manga"), ("c2", "Novel")] {
+            categories::ActiveModel {
+                id: Set(id.to_string()),
+                name: Set(name.to_string()),
+                description: Set(None),
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+        for (id, category_id, author) in [("t1", "c1", "A"), ("t2", "c1", "B"), ("t3", "c2", "A")] {
+            titles::ActiveModel {
+                id: Set(id.to_string()),
+                title: Set(id.to_string()),
+                category_id: Set(category_id.to_string()),
+                author: Set(Some(author.to_string())),
+                hash: Set(id.to_string()),
+                path: Set(format!("/library/{}.cbz", id)),
+                date_added: Set(at("2024-01-01")),
+                date_updated: Set(at("2024-01-01")),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+        for (name, title_ids) in [("Action", ["t1", "t2"]), ("Drama", ["t2", "t3"])] {
+            let tag = tags::ActiveModel {
+                name: Set(name.to_string()),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+            for title_id in title_ids {
+                titles_tags::ActiveModel {
+                    title_id: Set(title_id.to_string()),
+                    tag_id: Set(tag.id),
+                    ..Default::default()
+                }
+                .insert(&db)
+                .await
+                .unwrap();
+            }
+        }
+
+        // 2024-01-01 is a Monday, three days in a row in the first week and
+        // two in the second
+        session(&db, "u1", "t1", "2024-01-03", 10).await;
+        session(&db, "u1", "t1", "2024-01-04", 5).await;
+        session(&db, "u1", "t2", "2024-01-04", 7).await;
+        session(&db, "u1", "t2", "2024-01-05", 3).await;
+        session(&db, "u1", "t1", "2024-01-08", 4).await;
+        session(&db, "u1", "t3", "2024-01-09", 6).await;
+        session(&db, "u1", "t3", "2024-01-09", 2).await;
+        session(&db, "u2", "t2", "2024-01-04", 9).await;
+
+        progress(&db, "u1", "t1", "2024-01-08", true).await;
+        progress(&db, "u1", "t2", "2024-01-05", false).await;
+        progress(&db, "u1", "t3", "2024-01-09", false).await;
+        progress(&db, "u2", "t2", "2024-01-04", true).await;
+
+        favorites::ActiveModel {
+            user_id: Set("u1".to_string()),
+            title_id: Set("t3".to_string()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        bookmarks::ActiveModel {
+            user_id: Set("u1".to_string()),
+            title_id: Set("t3".to_string()),
+            page_id: Set(None),
+            note: Set(None),
+            created_at: Set(at("2024-01-09")),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        db
+    }
+
+    fn range(from: &str, to: &str) -> StatsQuery {
+        StatsQuery {
+            from: Some(date(from)),
+            to: Some(date(to)),
+        }
+    }
+
+    #[test]
+    fn streaks_count_consecutive_days() {
+        let days = [
+            "2024-01-03",
+            "2024-01-04",
+            "2024-01-05",
+            "2024-01-08",
+            "2024-01-09",
+        ]
+        .into_iter()
+        .map(date)
+        .collect::<BTreeSet<_>>();
+
+        assert_eq!(streaks(&days, date("2024-01-09")), (2, 3));
+        assert_eq!(streaks(&days, date("2024-01-10")), (2, 3));
+        assert_eq!(streaks(&days, date("2024-01-11")), (0, 3));
+        assert_eq!(streaks(&BTreeSet::new(), date("2024-01-11")), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn user_stats() {
+        let db = seeded_db().await;
+        let stats = reading_stats(&db, Some("u1"), &range("2024-01-01", "2024-01-14"))
+            .await
+            .unwrap();
+
+        assert_eq!(stats.pages_read, 37);
+        assert_eq!(stats.time_spent, 370);
+        assert_eq!(stats.titles_read, 3);
+        assert_eq!(stats.titles_finished, 1);
+        assert_eq!((stats.current_streak, stats.longest_streak), (0, 3));
+        assert_eq!(stats.readers, None);
+
+        let daily = stats
+            .daily
+            .iter()
+            .map(|day| (day.period.as_str(), day.pages_read, day.titles_read))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            daily,
+            [
+                ("2024-01-03", 10, 1),
+                ("2024-01-04", 12, 2),
+                ("2024-01-05", 3, 1),
+                ("2024-01-08", 4, 1),
+                ("2024-01-09", 8, 1),
+            ]
+        );
+        let weekly = stats
+            .weekly
+            .iter()
+            .map(|week| (week.period.as_str(), week.pages_read, week.titles_read))
+            .collect::<Vec<_>>();
+        assert_eq!(weekly, [("2024-W01", 25, 2), ("2024-W02", 12, 2)]);
+
+        // t3 is read, a favorite and bookmarked
+        let top_tags = stats
+            .top_tags
+            .iter()
+            .map(|tag| (tag.name.as_str(), tag.score))
+            .collect::<Vec<_>>();
+        assert_eq!(top_tags, [("Drama", 4), ("Action", 2)]);
+        let top_authors = stats
+            .top_authors
+            .iter()
+            .map(|author| (author.name.as_str(), author.score))
+            .collect::<Vec<_>>();
+        assert_eq!(top_authors, [("A", 4), ("B", 1)]);
+
+        let categories = stats
+            .categories
+            .iter()
+            .map(|category| {
+                (
+                    category.name.as_str(),
+                    category.started,
+                    category.completed,
+                    category.completion_rate,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(categories, [("Manga", 2, 1, 0.5), ("Novel", 1, 0, 0.0)]);
+
+        // t1 is finished in the range, and opened again after it
+        let t1 = Progresses::find()
+            .filter(progresses::Column::UserId.eq("u1"))
+            .filter(progresses::Column::TitleId.eq("t1"))
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut t1: progresses::ActiveModel = t1.into();
+        t1.last_read_at = Set(at("2024-01-20"));
+        t1.update(&db).await.unwrap();
+        let stats = reading_stats(&db, Some("u1"), &range("2024-01-01", "2024-01-14"))
+            .await
+            .unwrap();
+        assert_eq!(stats.titles_finished, 1);
+    }
+
+    #[tokio::test]
+    async fn server_stats_and_range() {
+        let db = seeded_db().await;
+        let stats = reading_stats(&db, None, &range("2024-01-04", "2024-01-05"))
+            .await
+            .unwrap();
+
+        assert_eq!(stats.pages_read, 24);
+        assert_eq!(stats.readers, Some(2));
+        assert_eq!(stats.daily[0].period, "2024-01-04");
+        assert_eq!(stats.daily[0].titles_read, 2);
+        assert_eq!(stats.titles_finished, 1);
+
+        let inverted = reading_stats(&db, None, &range("2024-01-05", "2024-01-04")).await;
+        assert!(inverted.is_err());
+    }
+}